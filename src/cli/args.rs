@@ -1,48 +1,320 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use kira_qc::core::metrics::{DEFAULT_MAX_READ_LEN, DEFAULT_OVERREP_SEQ_LEN};
 use std::path::PathBuf;
 
-fn default_threads() -> usize {
-    std::thread::available_parallelism()
-        .map(usize::from)
-        .unwrap_or(1)
-}
-
 #[derive(Parser)]
 #[command(name = "kira-qc", version, about = "FastQC-style QC for plain FASTQ")]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Stderr log verbosity; debug adds per-stage timings
+    #[arg(long, global = true, value_enum, default_value_t = LogLevelArg::Info)]
+    pub log_level: LogLevelArg,
+
+    /// Only print errors, and no module status table
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Same as --log-level debug
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Plain module status table even on a terminal
+    #[arg(long, global = true)]
+    pub no_color: bool,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Run QC on one input, or on every file of --file-list
     Run(RunArgs),
+    /// Merge aggregates saved with --save-agg into one report
+    Report(ReportArgs),
+    /// Print the built-in adapter sequences and exit
+    ListAdapters,
+}
+
+#[derive(Args)]
+pub struct OutputArgs {
+    /// Output directory
+    #[arg(long, required_unless_present = "single_file")]
+    pub out: Option<PathBuf>,
+
+    /// Write one self-contained HTML report to this path instead of a report directory
+    #[arg(
+        long,
+        conflicts_with_all = ["out", "flat", "no_zip", "compress_output", "export_latex", "emit_figures", "only"]
+    )]
+    pub single_file: Option<PathBuf>,
+
+    /// Sample name used for the report directory and ZIP (default: input file name without extensions)
+    #[arg(long)]
+    pub sample_name: Option<String>,
+
+    /// Report directory (and ZIP) name under --out; {sample} is replaced by the sample name
+    #[arg(long, default_value = "{sample}_fastqc", conflicts_with = "flat")]
+    pub out_name: String,
+
+    /// Write report files directly into --out
+    #[arg(long, default_value_t = false)]
+    pub flat: bool,
+
+    /// Custom heading for the HTML report
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// Description line under the HTML report heading
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// Target genome size in bp; adds estimated coverage to Basic Statistics
+    #[arg(long)]
+    pub genome_size: Option<u64>,
+
+    /// Base Composition warns when |A-T| or |G-C| exceeds this percentage of all bases (default: 10)
+    #[arg(long)]
+    pub strand_bias_pct: Option<f64>,
+
+    /// Short mode: rate the first N bp of Per base sequence content separately from the rest
+    #[arg(long)]
+    pub content_bias_window: Option<usize>,
+
+    /// Draw a moving average over N positions on the per base quality box plot
+    #[arg(long)]
+    pub qual_trend_window: Option<usize>,
+
+    /// Percentage of reads at which a sequence is overrepresented and the module fails; warns at half (default: 0.1)
+    #[arg(long)]
+    pub overrep_threshold: Option<f64>,
+
+    /// List at most N overrepresented sequences
+    #[arg(long)]
+    pub max_overrep_rows: Option<usize>,
+
+    /// List at most N k-mers in K-mer content (default: 50)
+    #[arg(long)]
+    pub max_kmer_rows: Option<usize>,
+
+    /// Rate Per sequence GC content against a normal centred on this GC%
+    #[arg(long, conflicts_with = "reference_gc")]
+    pub expected_gc: Option<f64>,
+
+    /// Rate Per sequence GC content against a distribution of "<gc percent> <weight>" lines
+    #[arg(long)]
+    pub reference_gc: Option<PathBuf>,
+
+    /// Do not create the ZIP bundle
+    #[arg(long, default_value_t = false)]
+    pub no_zip: bool,
+
+    /// Gzip fastqc_data.txt and summary.txt after writing them
+    #[arg(long, default_value_t = false)]
+    pub compress_output: bool,
+
+    /// Also write a LaTeX export with this layout
+    #[arg(long, value_enum)]
+    pub export_latex: Option<LatexExportArg>,
+
+    /// Write each module plot to figures/ in this format
+    #[arg(long, value_enum)]
+    pub emit_figures: Option<FigureFormatArg>,
+
+    /// Zoomable, hoverable quality and adapter plots in the HTML report
+    #[arg(long, default_value_t = false)]
+    pub html_interactive: bool,
+
+    /// Embed HTML plots as PNG images instead of inline SVG
+    #[arg(long, default_value_t = false, conflicts_with = "html_interactive")]
+    pub html_png: bool,
+
+    /// Per base quality plot style in the HTML report
+    #[arg(long, value_enum, default_value_t = QualPlotArg::Boxplot)]
+    pub qual_plot: QualPlotArg,
+
+    /// Label the Per base sequence content lines with their percentages
+    #[arg(long, default_value_t = false)]
+    pub content_annotate: bool,
+
+    /// HTML report palette
+    #[arg(long, value_enum, default_value_t = ThemeArg::Default)]
+    pub theme: ThemeArg,
+
+    /// Write exactly these outputs
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub only: Vec<OutputArg>,
 }
 
 #[derive(Parser)]
 pub struct RunArgs {
-    pub reads1: PathBuf,
+    /// FASTQ input, plain or gzip/bzip2 compressed (unaligned BAM with the bam feature)
+    #[arg(required_unless_present = "file_list", conflicts_with = "file_list")]
+    pub reads1: Option<PathBuf>,
 
-    #[arg(long)]
-    pub out: PathBuf,
+    /// QC every path listed in this file (one per line) and write index.html under --out
+    #[arg(long, conflicts_with_all = ["save_agg", "dump_overrep", "profile_json", "sample_name", "flat", "single_file"])]
+    pub file_list: Option<PathBuf>,
 
-    #[arg(long, default_value_t = default_threads())]
-    pub threads: usize,
+    #[command(flatten)]
+    pub output: OutputArgs,
 
+    /// Worker threads (default: one per 16 MiB of estimated input, up to the CPU count)
     #[arg(long)]
-    pub sample_name: Option<String>,
+    pub threads: Option<usize>,
 
+    /// Quality offset, or auto to detect it
     #[arg(long, value_enum, default_value_t = PhredOffsetArg::Auto)]
     pub phred_offset: PhredOffsetArg,
 
-    #[arg(long, default_value_t = false)]
-    pub no_zip: bool,
+    /// Encoding label reported in Basic Statistics; qualities are still decoded with the phred offset
+    #[arg(long, value_enum)]
+    pub encoding: Option<EncodingArg>,
 
+    /// Short-read or long-read QC
     #[arg(long, value_enum, default_value_t = ModeArg::Short)]
     pub mode: ModeArg,
 
+    /// Only QC the first N reads
+    #[arg(long, conflicts_with_all = ["subsample_fraction", "hash"])]
+    pub subsample: Option<u64>,
+
+    /// QC about this fraction of the reads, spread over the whole file
+    #[arg(long)]
+    pub subsample_fraction: Option<f64>,
+
+    /// BGZF input: QC 10,000 reads at each of N evenly spaced points in the file
+    #[arg(long, conflicts_with_all = ["subsample", "subsample_fraction", "hash"])]
+    pub sample_regions: Option<u64>,
+
+    /// Stop after N reads and report on what was read
+    #[arg(long)]
+    pub max_reads: Option<u64>,
+
+    /// Stop before the read bases would exceed N and report on what was read
+    #[arg(long)]
+    pub max_bases: Option<u64>,
+
+    /// Print periodic progress to stderr
+    #[arg(long, default_value_t = false)]
+    pub progress: bool,
+
+    /// Pin worker i to logical CPU i
+    #[arg(long, default_value_t = false)]
+    pub pin_threads: bool,
+
+    /// Log peak memory and worker threads after each input
+    #[arg(long, default_value_t = false)]
+    pub resource_stats: bool,
+
+    /// Fail on quality bytes outside the range of the phred offset instead of counting them
+    #[arg(long, default_value_t = false)]
+    pub strict_phred: bool,
+
+    /// Skip malformed FASTQ records instead of failing
+    #[arg(long, default_value_t = false)]
+    pub lenient: bool,
+
+    /// Where sample names come from without --sample-name
+    #[arg(long, value_enum, default_value_t = SampleNameFromArg::Stem, conflicts_with = "sample_name")]
+    pub sample_name_from: SampleNameFromArg,
+
+    /// Strip trailing spaces and tabs from FASTQ lines instead of failing
+    #[arg(long, default_value_t = false)]
+    pub trim_trailing_ws: bool,
+
+    /// Hash the input file and report the digest in Basic Statistics
     #[arg(long, value_enum)]
-    pub export_latex: Option<LatexExportArg>,
+    pub hash: Option<HashArg>,
+
+    /// Count reads shorter than N bp as filtered
+    #[arg(long)]
+    pub min_length: Option<usize>,
+
+    /// Leave filtered reads out of every other metric
+    #[arg(long, default_value_t = false, requires = "min_length")]
+    pub exclude_filtered: bool,
+
+    /// Short mode: track per-position metrics up to this read length
+    #[arg(long, default_value_t = DEFAULT_MAX_READ_LEN)]
+    pub max_read_len: usize,
+
+    /// Bases kept per overrepresented-sequence candidate
+    #[arg(long, default_value_t = DEFAULT_OVERREP_SEQ_LEN)]
+    pub overrep_max_len: usize,
+
+    /// Count overrepresented sequences by their first N bases only
+    #[arg(long)]
+    pub overrep_prefix_len: Option<usize>,
+
+    /// Short mode: split duplicates into optical and library ones within this pixel distance
+    #[arg(long)]
+    pub optical_dup_distance: Option<u32>,
+
+    /// Long mode: add a read length vs mean quality heatmap
+    #[arg(long, default_value_t = false)]
+    pub length_quality_map: bool,
+
+    /// Long mode: report adapters at the start, middle and end of reads
+    #[arg(long, default_value_t = false)]
+    pub adapter_positions: bool,
+
+    /// Long mode: Per sequence GC content bin width in percent
+    #[arg(long, value_enum, default_value_t = GcResolutionArg::Percent)]
+    pub gc_resolution: GcResolutionArg,
+
+    /// Write a report for inputs without reads instead of failing
+    #[arg(long, default_value_t = false)]
+    pub allow_empty: bool,
+
+    /// Short mode: per base quality quantiles from sampled values instead of the histogram
+    #[arg(long, default_value_t = false)]
+    pub exact_quantiles: bool,
+
+    /// Seed for sampled metrics
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Report the share of lowercase (soft-masked) bases
+    #[arg(long, default_value_t = false)]
+    pub track_softmask: bool,
+
+    /// Count a sequence and its reverse complement as one for duplication and overrepresentation
+    #[arg(long, default_value_t = false)]
+    pub collapse_revcomp: bool,
+
+    /// Sketch sizes for duplication, overrepresented sequences and k-mers
+    #[arg(long, value_enum, default_value_t = MemoryArg::High)]
+    pub memory: MemoryArg,
+
+    /// Save the merged aggregate as JSON for a later report merge
+    #[arg(long)]
+    pub save_agg: Option<PathBuf>,
+
+    /// Write every tracked overrepresented-sequence candidate to this CSV
+    #[arg(long)]
+    pub dump_overrep: Option<PathBuf>,
+
+    /// Write producer, worker and stage timings to this JSON file
+    #[arg(long)]
+    pub profile_json: Option<PathBuf>,
+
+    /// Exit non-zero if any module reaches this status
+    #[arg(long, value_enum)]
+    pub fail_on: Option<FailOnArg>,
+
+    /// Skip these modules
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub skip: Vec<SkipModuleArg>,
+}
+
+#[derive(Parser)]
+pub struct ReportArgs {
+    /// Aggregates saved with run --save-agg
+    #[arg(required = true)]
+    pub aggregates: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
 }
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
@@ -55,6 +327,28 @@ pub enum PhredOffsetArg {
     P64,
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum EncodingArg {
+    #[value(name = "sanger")]
+    Sanger,
+    #[value(name = "illumina13")]
+    Illumina13,
+    #[value(name = "illumina15")]
+    Illumina15,
+    #[value(name = "illumina18")]
+    Illumina18,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SampleNameFromArg {
+    /// Input file name without its extensions
+    #[value(name = "stem")]
+    Stem,
+    /// Barcode or flowcell from the first read ID
+    #[value(name = "id")]
+    Id,
+}
+
 #[derive(Clone, Copy, Debug, ValueEnum)]
 pub enum ModeArg {
     #[value(name = "short")]
@@ -63,6 +357,40 @@ pub enum ModeArg {
     Long,
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum LogLevelArg {
+    #[value(name = "error")]
+    Error,
+    #[value(name = "warn")]
+    Warn,
+    #[value(name = "info")]
+    Info,
+    #[value(name = "debug")]
+    Debug,
+    #[value(name = "trace")]
+    Trace,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SkipModuleArg {
+    #[value(name = "duplication")]
+    Duplication,
+    #[value(name = "overrepresented")]
+    Overrepresented,
+    #[value(name = "adapter")]
+    Adapter,
+    #[value(name = "kmer")]
+    Kmer,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum FailOnArg {
+    #[value(name = "warn")]
+    Warn,
+    #[value(name = "fail")]
+    Fail,
+}
+
 #[derive(Clone, Copy, Debug, ValueEnum)]
 pub enum LatexExportArg {
     #[value(name = "summary")]
@@ -70,3 +398,75 @@ pub enum LatexExportArg {
     #[value(name = "supplement")]
     Supplement,
 }
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum FigureFormatArg {
+    #[value(name = "svg")]
+    Svg,
+    #[value(name = "png")]
+    Png,
+    #[value(name = "pdf")]
+    Pdf,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum QualPlotArg {
+    #[value(name = "boxplot")]
+    Boxplot,
+    #[value(name = "heatmap")]
+    Heatmap,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum GcResolutionArg {
+    #[value(name = "1")]
+    Percent,
+    #[value(name = "0.5")]
+    HalfPercent,
+    #[value(name = "0.1")]
+    TenthPercent,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum MemoryArg {
+    #[value(name = "low")]
+    Low,
+    #[value(name = "medium")]
+    Medium,
+    #[value(name = "high")]
+    High,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum HashArg {
+    #[value(name = "crc32")]
+    Crc32,
+    #[value(name = "md5")]
+    Md5,
+    #[value(name = "sha256")]
+    Sha256,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ThemeArg {
+    #[value(name = "default")]
+    Default,
+    #[value(name = "dark")]
+    Dark,
+    #[value(name = "colorblind")]
+    Colorblind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum OutputArg {
+    #[value(name = "txt")]
+    Txt,
+    #[value(name = "summary")]
+    Summary,
+    #[value(name = "html")]
+    Html,
+    #[value(name = "zip")]
+    Zip,
+    #[value(name = "latex")]
+    Latex,
+}
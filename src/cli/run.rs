@@ -1,50 +1,301 @@
-use crate::cli::args::{Cli, Commands, LatexExportArg, ModeArg, PhredOffsetArg, RunArgs};
-use crate::core::engine::{self, PhredOffsetConfig, RunConfig};
-use crate::core::model::Mode;
-use crate::report;
+use crate::cli::args::{
+    Cli, Commands, EncodingArg, FailOnArg, FigureFormatArg, GcResolutionArg, HashArg,
+    LatexExportArg, LogLevelArg, MemoryArg, ModeArg, OutputArg, OutputArgs, PhredOffsetArg,
+    QualPlotArg, ReportArgs, RunArgs, SampleNameFromArg, SkipModuleArg, ThemeArg,
+};
+use crate::cli::resources;
 use anyhow::{Context, Result, bail};
 use clap::Parser;
+use kira_qc::core::engine::{self, PhredOffsetConfig, RunConfig, RunOutput, Subsample};
+use kira_qc::core::input::InputReader;
+use kira_qc::core::metrics::{
+    ADAPTER_NAMES, ADAPTERS, DEFAULT_MAX_KMER_ROWS, DEFAULT_OVERREP_THRESHOLD_PCT,
+    DEFAULT_STRAND_BIAS_PCT, GcReference, GcResolution, MemoryProfile, SOURCE_ADAPTERS,
+    SkipModules, sample_name_from_id,
+};
+use kira_qc::core::model::{Encoding, HashAlgorithm, Mode, Status};
+use kira_qc::core::saved_agg;
+use kira_qc::report;
+use log::{Level, LevelFilter, debug, error, info, log_enabled, warn};
+use std::collections::HashSet;
 use std::env;
 use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 pub fn entry() -> Result<()> {
     let cli = Cli::parse();
+    init_logging(&cli);
+    let console = Console {
+        summary: !cli.quiet,
+        color: !cli.no_color
+            && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+            && std::io::stdout().is_terminal(),
+    };
     match cli.command {
-        Commands::Run(args) => run(args),
+        Commands::Run(args) => run(args, console),
+        Commands::Report(args) => merge_report(args),
+        Commands::ListAdapters => list_adapters(),
+    }
+}
+
+// Prints the Adapter Content sequences and the fragments behind the
+// "Adapter" source of Overrepresented sequences, tab-separated.
+fn list_adapters() -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    writeln!(out, "#Adapter Content")?;
+    for (name, seq) in ADAPTER_NAMES.iter().zip(ADAPTERS) {
+        writeln!(out, "{name}\t{seq}")?;
     }
+    writeln!(out, "#Overrepresented sequences source")?;
+    for (name, seq) in SOURCE_ADAPTERS {
+        writeln!(out, "{name}\t{}", seq.escape_ascii())?;
+    }
+    Ok(())
+}
+
+// Module status table printed to stdout when a run finishes.
+#[derive(Clone, Copy)]
+struct Console {
+    // Off under --quiet.
+    summary: bool,
+    // Stdout is a TTY and neither --no-color nor NO_COLOR is set.
+    color: bool,
 }
 
-fn run(args: RunArgs) -> Result<()> {
+fn run(args: RunArgs, console: Console) -> Result<()> {
     let stats = stats_enabled();
     let t0 = Instant::now();
 
+    let outputs = selected_outputs(&args.output)?;
     stage(stats, "preflight", || {
-        if args.reads1.as_os_str() == "-" {
-            bail!("stdin is not supported in Stage 1; provide a FASTQ file path");
+        if let Some(reads1) = &args.reads1 {
+            if reads1.as_os_str() == "-" {
+                bail!("stdin is not supported in Stage 1; provide a FASTQ file path");
+            }
+            if !reads1.is_file() {
+                bail!("input file not found: {}", reads1.display());
+            }
         }
-        if !args.reads1.is_file() {
-            bail!("input file not found: {}", args.reads1.display());
+        if args.file_list.is_some() && !args.output.out_name.contains("{sample}") {
+            bail!(
+                "--file-list needs an --out-name containing {{sample}} so reports do not overwrite each other"
+            );
         }
-        if args.threads == 0 {
+        if args.threads == Some(0) {
             bail!("--threads must be >= 1");
         }
+        validate_report_settings(&args.output)?;
+        if args.subsample == Some(0) {
+            bail!("--subsample must be >= 1");
+        }
+        if args.sample_regions == Some(0) {
+            bail!("--sample-regions must be >= 1");
+        }
+        if let Some(f) = args.subsample_fraction
+            && !(f > 0.0 && f <= 1.0)
+        {
+            bail!("--subsample-fraction must be in (0, 1]");
+        }
+        if args.max_reads == Some(0) {
+            bail!("--max-reads must be >= 1");
+        }
+        if args.max_bases == Some(0) {
+            bail!("--max-bases must be >= 1");
+        }
+        if args.max_read_len == 0 {
+            bail!("--max-read-len must be >= 1");
+        }
+        if args.overrep_max_len == 0 {
+            bail!("--overrep-max-len must be >= 1");
+        }
+        if args.overrep_prefix_len == Some(0) {
+            bail!("--overrep-prefix-len must be >= 1");
+        }
+        if args.length_quality_map && !matches!(args.mode, ModeArg::Long) {
+            bail!("--length-quality-map requires --mode long");
+        }
+        if !matches!(args.gc_resolution, GcResolutionArg::Percent)
+            && !matches!(args.mode, ModeArg::Long)
+        {
+            bail!("--gc-resolution finer than 1 requires --mode long");
+        }
+        if args.adapter_positions && !matches!(args.mode, ModeArg::Long) {
+            bail!("--adapter-positions requires --mode long");
+        }
+        if args.optical_dup_distance.is_some() && !matches!(args.mode, ModeArg::Short) {
+            bail!("--optical-dup-distance requires --mode short");
+        }
+        if args.exact_quantiles && !matches!(args.mode, ModeArg::Short) {
+            bail!(
+                "--exact-quantiles applies to the per-base quality plot, which requires --mode short"
+            );
+        }
         Ok(())
     })?;
 
-    let input_size = fs::metadata(&args.reads1).map(|m| m.len()).unwrap_or(0);
+    if let Some(list) = &args.file_list {
+        return run_file_list(stats, &args, list, outputs, console);
+    }
+    let Some(reads1) = args.reads1.as_deref() else {
+        bail!("provide an input file or --file-list");
+    };
 
-    let t_name = Instant::now();
-    let sample_name = match args.sample_name {
+    // An explicit --threads wins; otherwise size the pool to the input.
+    let threads = match args.threads {
+        Some(n) => n,
+        None => {
+            let n = engine::auto_threads(reads1);
+            if stats {
+                let input_size = fs::metadata(reads1).map(|m| m.len()).unwrap_or(0);
+                debug!(
+                    "KIRA_STATS threads={} (auto, input_bytes={})",
+                    n, input_size
+                );
+            }
+            n
+        }
+    };
+    let pool = engine::WorkerPool::new(threads, args.pin_threads);
+
+    let sample_name = match args.output.sample_name.clone() {
         Some(s) => s,
-        None => args
-            .reads1
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_string())
-            .context("failed to determine sample name from input file")?,
+        None => sample_name_for(&args, reads1)?,
     };
-    stage_done(stats, "sample-name", t_name);
+    let done = run_file(stats, &args, reads1, &sample_name, outputs, &pool)?;
+
+    if stats {
+        debug!("KIRA_STATS output_dir={}", done.out_dir.display());
+        debug!("KIRA_STATS total={}", fmt_dur(t0.elapsed()));
+    }
+    info!(
+        "{}: {} reads, {} bases in {} -> {}",
+        done.output.ctx.file_name,
+        done.output.agg.total_reads,
+        done.output.agg.total_bases,
+        fmt_dur(t0.elapsed()),
+        done.out_dir.display()
+    );
+    if console.summary {
+        print_summary(&done.output, console.color);
+    }
+
+    if let Some(fail_on) = args.fail_on {
+        check_verdict(&done.output, fail_on)?;
+    }
+
+    Ok(())
+}
+
+// Sample name of an input run without --sample-name.
+fn sample_name_for(args: &RunArgs, reads1: &Path) -> Result<String> {
+    match args.sample_name_from {
+        SampleNameFromArg::Stem => default_sample_name(reads1),
+        SampleNameFromArg::Id => match first_read_sample_name(args, reads1) {
+            Some(name) => Ok(name),
+            None => {
+                let stem = default_sample_name(reads1)?;
+                warn!(
+                    "{}: no usable sample name in the first read ID; using {}",
+                    reads1.display(),
+                    stem
+                );
+                Ok(stem)
+            }
+        },
+    }
+}
+
+// --sample-name-from id. Unreadable input is left to the input check that
+// follows, so failures here only mean "no name".
+fn first_read_sample_name(args: &RunArgs, reads1: &Path) -> Option<String> {
+    let mut reader =
+        InputReader::open(reads1, 1, args.lenient, args.trim_trailing_ws, None).ok()?;
+    let read = reader.next_read().ok()??;
+    sample_name_from_id(&read.id).filter(|name| is_safe_file_name(name))
+}
+
+// The name becomes a directory and ZIP name, so it is kept to characters
+// that are portable in file names.
+fn is_safe_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+'))
+}
+
+// Extensions stripped from the input file name for its sample name, in any
+// case: an optional compression suffix, then an optional format suffix.
+const COMPRESSION_EXTENSIONS: [&str; 3] = ["gz", "bgz", "bz2"];
+const FORMAT_EXTENSIONS: [&str; 7] = ["fastq", "fq", "fasta", "fa", "fna", "fas", "bam"];
+
+// `sample.fastq.gz`, `sample.FQ` and `sample.fa.bz2` all give `sample`;
+// names with neither kind of extension lose only their last one.
+fn default_sample_name(reads1: &Path) -> Result<String> {
+    let name = reads1
+        .file_name()
+        .and_then(|s| s.to_str())
+        .context("failed to determine sample name from input file")?;
+    let mut stem = name;
+    // None matches any extension, once neither list has matched.
+    for extensions in [
+        Some(&COMPRESSION_EXTENSIONS[..]),
+        Some(&FORMAT_EXTENSIONS[..]),
+        None,
+    ] {
+        if extensions.is_none() && stem.len() < name.len() {
+            break;
+        }
+        if let Some((head, ext)) = stem.rsplit_once('.')
+            && !head.is_empty()
+            && extensions.is_none_or(|list| list.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+        {
+            stem = head;
+        }
+    }
+    Ok(stem.to_string())
+}
+
+// Records parsed up front so that a non-FASTQ or corrupt file fails with the
+// parser's message (and byte offset, where known) before the engine starts.
+const PREFLIGHT_RECORDS: usize = 16;
+
+fn check_input_head(reads1: &Path, lenient: bool, trim_trailing_ws: bool) -> Result<()> {
+    let mut reader = InputReader::open(reads1, 1, lenient, trim_trailing_ws, None)
+        .with_context(|| format!("failed to open {}", reads1.display()))?;
+    for _ in 0..PREFLIGHT_RECORDS {
+        let read = reader
+            .next_read()
+            .with_context(|| format!("{} is not readable as sequence input", reads1.display()))?;
+        if read.is_none() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// A finished input and where its report went.
+struct FileRun {
+    output: RunOutput,
+    out_dir: PathBuf,
+    report_name: Option<String>,
+}
+
+fn run_file(
+    stats: bool,
+    args: &RunArgs,
+    reads1: &Path,
+    sample_name: &str,
+    outputs: Outputs,
+    pool: &engine::WorkerPool,
+) -> Result<FileRun> {
+    let input_size = fs::metadata(reads1).map(|m| m.len()).unwrap_or(0);
+
+    let t_check = Instant::now();
+    check_input_head(reads1, args.lenient, args.trim_trailing_ws)?;
+    stage_done(stats, "input-check", t_check);
 
     let t_phred = Instant::now();
     let phred_offset = match args.phred_offset {
@@ -61,86 +312,549 @@ fn run(args: RunArgs) -> Result<()> {
     };
     stage_done(stats, "mode", t_mode);
 
-    let t_out = Instant::now();
-    let out_dir = args.out.join(format!("{}_fastqc", sample_name));
-    fs::create_dir_all(&out_dir)
-        .with_context(|| format!("failed to create output dir {}", out_dir.display()))?;
-    stage_done(stats, "mkdir", t_out);
+    let subsample = match (args.subsample, args.subsample_fraction, args.sample_regions) {
+        (Some(n), _, _) => Some(Subsample::Head(n)),
+        (None, Some(f), _) => Some(Subsample::Fraction(f)),
+        (None, None, Some(n)) => Some(Subsample::Regions(n)),
+        (None, None, None) => None,
+    };
 
-    let config = RunConfig {
-        reads1: args.reads1.clone(),
-        out_dir: out_dir.clone(),
-        sample_name: sample_name.clone(),
-        threads: args.threads,
-        phred_offset,
-        mode,
+    let report_name = report_dir_name(&args.output, sample_name)?;
+
+    let mut config = RunConfig::default();
+    config.reads1 = reads1.to_path_buf();
+    config.sample_name = sample_name.to_string();
+    config.threads = pool.threads();
+    config.phred_offset = phred_offset;
+    config.encoding_label = args.encoding.map(|e| match e {
+        EncodingArg::Sanger => Encoding::Sanger,
+        EncodingArg::Illumina13 => Encoding::Illumina13,
+        EncodingArg::Illumina15 => Encoding::Illumina15,
+        EncodingArg::Illumina18 => Encoding::Illumina18,
+    });
+    config.mode = mode;
+    config.subsample = subsample;
+    config.max_reads = args.max_reads;
+    config.max_bases = args.max_bases;
+    config.progress = args.progress;
+    config.pin_threads = args.pin_threads;
+    config.strict_phred = args.strict_phred;
+    config.lenient = args.lenient;
+    config.trim_trailing_ws = args.trim_trailing_ws;
+    config.hash = args.hash.map(|h| match h {
+        HashArg::Crc32 => HashAlgorithm::Crc32,
+        HashArg::Md5 => HashAlgorithm::Md5,
+        HashArg::Sha256 => HashAlgorithm::Sha256,
+    });
+    config.min_length = args.min_length;
+    config.exclude_filtered = args.exclude_filtered;
+    config.max_read_len = args.max_read_len;
+    config.overrep_max_len = args.overrep_max_len;
+    config.overrep_prefix_len = args.overrep_prefix_len;
+    config.optical_dup_distance = args.optical_dup_distance;
+    config.length_quality_map = args.length_quality_map;
+    config.adapter_positions = args.adapter_positions;
+    config.gc_resolution = match args.gc_resolution {
+        GcResolutionArg::Percent => GcResolution::Percent,
+        GcResolutionArg::HalfPercent => GcResolution::HalfPercent,
+        GcResolutionArg::TenthPercent => GcResolution::TenthPercent,
+    };
+    config.allow_empty = args.allow_empty;
+    config.exact_quantiles = args.exact_quantiles;
+    config.seed = args.seed;
+    config.track_softmask = args.track_softmask;
+    config.collapse_revcomp = args.collapse_revcomp;
+    config.skip = skip_modules(&args.skip);
+    config.memory = match args.memory {
+        MemoryArg::Low => MemoryProfile::Low,
+        MemoryArg::Medium => MemoryProfile::Medium,
+        MemoryArg::High => MemoryProfile::High,
     };
+    config.title = args.output.title.clone();
+    config.description = args.output.description.clone();
+    config.genome_size = args.output.genome_size;
+    config.strand_bias_pct = args
+        .output
+        .strand_bias_pct
+        .unwrap_or(DEFAULT_STRAND_BIAS_PCT);
+    config.content_bias_window = args.output.content_bias_window;
+    config.qual_trend_window = args.output.qual_trend_window;
+    config.overrep_threshold_pct = args
+        .output
+        .overrep_threshold
+        .unwrap_or(DEFAULT_OVERREP_THRESHOLD_PCT);
+    config.max_overrep_rows = args.output.max_overrep_rows;
+    config.max_kmer_rows = args.output.max_kmer_rows.unwrap_or(DEFAULT_MAX_KMER_ROWS);
+    config.gc_reference = gc_reference(&args.output)?;
+    config.profile = args.profile_json.is_some();
 
+    let agg_config = config.agg_config();
     let t_engine = Instant::now();
-    let output = engine::run(config)?;
+    let output = engine::run_with_pool(config, pool)?;
     stage_done(stats, "engine", t_engine);
+    if let (Some(path), Some(profile)) = (&args.profile_json, &output.profile) {
+        fs::write(path, profile).with_context(|| format!("create {} failed", path.display()))?;
+    }
     if stats {
-        eprintln!(
+        debug!(
             "KIRA_STATS input={} bytes={} reads={} bases={}",
-            args.reads1.display(),
+            reads1.display(),
             input_size,
             output.agg.total_reads,
             output.agg.total_bases
         );
     }
 
-    let fastqc_path = out_dir.join("fastqc_data.txt");
-    let summary_path = out_dir.join("summary.txt");
-    let html_path = out_dir.join("fastqc_report.html");
+    // Created only now so that a failed run leaves no empty report directory.
+    let t_out = Instant::now();
+    let out_dir = prepare_out_dir(&args.output, report_name.as_deref())?;
+    stage_done(stats, "mkdir", t_out);
 
-    let t_fastqc = Instant::now();
-    report::fastqc_txt::write(&fastqc_path, &output)
-        .with_context(|| format!("failed to write {}", fastqc_path.display()))?;
-    stage_done(stats, "fastqc_data", t_fastqc);
-    if stats {
-        let fastqc_size = fs::metadata(&fastqc_path).map(|m| m.len()).unwrap_or(0);
-        eprintln!(
-            "KIRA_STATS output fastqc_data={} bytes={}",
-            fastqc_path.display(),
-            fastqc_size
+    if let Some(path) = &args.save_agg {
+        let t_save = Instant::now();
+        saved_agg::save(path, &output, &agg_config)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        stage_done(stats, "save-agg", t_save);
+    }
+
+    if let Some(path) = &args.dump_overrep {
+        let t_dump = Instant::now();
+        report::overrep_csv::write(path, &output)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        stage_done(stats, "dump-overrep", t_dump);
+    }
+
+    let zip_root = report_name.as_deref().unwrap_or(sample_name);
+    write_outputs(stats, &args.output, outputs, &out_dir, zip_root, &output)?;
+
+    // Peak RSS is process-wide, so with --file-list it covers every input
+    // run so far.
+    if args.resource_stats
+        && let Some(rss) = resources::peak_rss_bytes()
+    {
+        info!(
+            "{}: peak RSS {:.1} MiB, {} worker threads",
+            output.ctx.file_name,
+            rss as f64 / (1024.0 * 1024.0),
+            output.workers
         );
     }
 
-    let t_summary = Instant::now();
-    report::summary_txt::write(&summary_path, &output)
-        .with_context(|| format!("failed to write {}", summary_path.display()))?;
-    stage_done(stats, "summary", t_summary);
+    Ok(FileRun {
+        output,
+        out_dir,
+        report_name,
+    })
+}
+
+// Runs every path listed in `list` (one per line; blank lines and lines
+// starting with '#' are skipped) on one worker pool, writes index.html under
+// --out and fails at the end if any input failed.
+fn run_file_list(
+    stats: bool,
+    args: &RunArgs,
+    list: &Path,
+    outputs: Outputs,
+    console: Console,
+) -> Result<()> {
+    let t0 = Instant::now();
+    let text = fs::read_to_string(list)
+        .with_context(|| format!("failed to read file list {}", list.display()))?;
+    let inputs: Vec<PathBuf> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect();
+    if inputs.is_empty() {
+        bail!("file list {} has no input paths", list.display());
+    }
+
+    // Sized for the largest input, as `run` sizes its pool for its one input.
+    let threads = args.threads.unwrap_or_else(|| {
+        inputs
+            .iter()
+            .map(|path| engine::auto_threads(path))
+            .max()
+            .unwrap_or(1)
+    });
+    let pool = engine::WorkerPool::new(threads, args.pin_threads);
+    let out_root = out_root(&args.output);
+    fs::create_dir_all(out_root)
+        .with_context(|| format!("failed to create output dir {}", out_root.display()))?;
+
+    let mut entries = Vec::with_capacity(inputs.len());
+    let mut failed = Vec::new();
+    let mut seen_samples = HashSet::new();
+    for (i, reads1) in inputs.iter().enumerate() {
+        info!("[{}/{}] {}", i + 1, inputs.len(), reads1.display());
+        let result = sample_name_for(args, reads1).and_then(|sample_name| {
+            if !reads1.is_file() {
+                bail!("input file not found: {}", reads1.display());
+            }
+            if !seen_samples.insert(sample_name.clone()) {
+                bail!(
+                    "sample name {} is already used by an earlier input",
+                    sample_name
+                );
+            }
+            run_file(stats, args, reads1, &sample_name, outputs, &pool)
+                .map(|done| (sample_name, done))
+        });
+        match result {
+            Ok((sample_name, done)) => {
+                if console.summary {
+                    print_summary(&done.output, console.color);
+                }
+                let metrics = done.output.agg.finalize(&done.output.ctx);
+                let statuses: Vec<Status> = metrics
+                    .module_statuses(done.output.ctx.mode)
+                    .map(|m| m.status)
+                    .collect();
+                let verdict = args
+                    .fail_on
+                    .map(|fail_on| check_verdict(&done.output, fail_on));
+                entries.push(report::index::IndexEntry {
+                    sample_name,
+                    file_name: done.output.ctx.file_name.clone(),
+                    report_href: outputs.html.then(|| {
+                        let dir = done.report_name.as_deref().unwrap_or(".");
+                        format!("{}/fastqc_report.html", dir)
+                    }),
+                    reads: done.output.agg.total_reads,
+                    warn: statuses.iter().filter(|s| **s == Status::Warn).count(),
+                    fail: statuses.iter().filter(|s| **s == Status::Fail).count(),
+                    error: None,
+                });
+                if let Some(Err(e)) = verdict {
+                    warn!("{}: {:#}", reads1.display(), e);
+                    failed.push(reads1.display().to_string());
+                }
+            }
+            Err(e) => {
+                error!("{}: {:#}", reads1.display(), e);
+                entries.push(report::index::IndexEntry {
+                    sample_name: String::new(),
+                    file_name: reads1.display().to_string(),
+                    report_href: None,
+                    reads: 0,
+                    warn: 0,
+                    fail: 0,
+                    error: Some(format!("{:#}", e)),
+                });
+                failed.push(reads1.display().to_string());
+            }
+        }
+    }
+
+    let index_path = out_root.join("index.html");
+    report::index::write(&index_path, &entries)
+        .with_context(|| format!("failed to write {}", index_path.display()))?;
     if stats {
-        let summary_size = fs::metadata(&summary_path).map(|m| m.len()).unwrap_or(0);
-        eprintln!(
-            "KIRA_STATS output summary={} bytes={}",
-            summary_path.display(),
-            summary_size
+        debug!("KIRA_STATS total={}", fmt_dur(t0.elapsed()));
+    }
+    info!(
+        "{} of {} inputs done in {} -> {}",
+        inputs.len() - failed.len(),
+        inputs.len(),
+        fmt_dur(t0.elapsed()),
+        index_path.display()
+    );
+    if !failed.is_empty() {
+        bail!(
+            "{} of {} inputs failed: {}",
+            failed.len(),
+            inputs.len(),
+            failed.join(", ")
         );
     }
+    Ok(())
+}
+
+// One aligned row per module: title and status, colored by status when
+// `color` is set.
+fn print_summary(output: &RunOutput, color: bool) {
+    let metrics = output.agg.finalize(&output.ctx);
+    let modules: Vec<_> = metrics.module_statuses(output.ctx.mode).collect();
+    let width = modules.iter().map(|m| m.title.len()).max().unwrap_or(0);
+    println!("{}", output.ctx.file_name);
+    for m in &modules {
+        let label = m.status.as_str_upper();
+        if color {
+            let code = match m.status {
+                Status::Pass => "32",
+                Status::Warn => "33",
+                Status::Fail => "31",
+                Status::NotApplicable => "2",
+            };
+            println!("  {:<width$}  \x1b[{}m{}\x1b[0m", m.title, code, label);
+        } else {
+            println!("  {:<width$}  {}", m.title, label);
+        }
+    }
+}
+
+// Prints a one-line module verdict and fails the run if any module reaches
+// the --fail-on severity, so CI can gate on the exit code.
+fn check_verdict(output: &RunOutput, fail_on: FailOnArg) -> Result<()> {
+    let metrics = output.agg.finalize(&output.ctx);
+    let mut flagged: Vec<_> = metrics
+        .module_statuses(output.ctx.mode)
+        .filter(|m| matches!(m.status, Status::Warn | Status::Fail))
+        .collect();
+    flagged.sort_by_key(|m| m.status != Status::Fail);
+
+    let fails = flagged.iter().filter(|m| m.status == Status::Fail).count();
+    let warns = flagged.len() - fails;
+    let mut counts = Vec::new();
+    if fails > 0 {
+        counts.push(format!("{fails} FAIL"));
+    }
+    if warns > 0 {
+        counts.push(format!("{warns} WARN"));
+    }
+    if counts.is_empty() {
+        println!("kira-qc: PASS");
+    } else {
+        let modules: Vec<_> = flagged
+            .iter()
+            .map(|m| format!("{}={}", m.key, m.status.as_str_upper()))
+            .collect();
+        println!("kira-qc: {} ({})", counts.join(", "), modules.join(", "));
+    }
+
+    let (hits, level) = match fail_on {
+        FailOnArg::Warn => (flagged.len(), "warn"),
+        FailOnArg::Fail => (fails, "fail"),
+    };
+    if hits > 0 {
+        bail!("{hits} module(s) at or above --fail-on {level}");
+    }
+    Ok(())
+}
+
+fn skip_modules(args: &[SkipModuleArg]) -> SkipModules {
+    let mut skip = SkipModules::default();
+    for arg in args {
+        match arg {
+            SkipModuleArg::Duplication => skip.duplication = true,
+            SkipModuleArg::Overrepresented => skip.overrepresented = true,
+            SkipModuleArg::Adapter => skip.adapter_content = true,
+            SkipModuleArg::Kmer => skip.kmer_content = true,
+        }
+    }
+    skip
+}
+
+// Range checks on the report settings shared by `run` and `report`.
+fn validate_report_settings(opts: &OutputArgs) -> Result<()> {
+    if opts.genome_size == Some(0) {
+        bail!("--genome-size must be >= 1");
+    }
+    if opts
+        .strand_bias_pct
+        .is_some_and(|pct| !(0.0..=100.0).contains(&pct))
+    {
+        bail!("--strand-bias-pct must be in [0, 100]");
+    }
+    if opts.content_bias_window == Some(0) {
+        bail!("--content-bias-window must be >= 1");
+    }
+    if opts.qual_trend_window == Some(0) {
+        bail!("--qual-trend-window must be >= 1");
+    }
+    if opts
+        .overrep_threshold
+        .is_some_and(|pct| !(pct > 0.0 && pct <= 100.0))
+    {
+        bail!("--overrep-threshold must be in (0, 100]");
+    }
+    if opts.max_overrep_rows == Some(0) {
+        bail!("--max-overrep-rows must be >= 1");
+    }
+    if opts.max_kmer_rows == Some(0) {
+        bail!("--max-kmer-rows must be >= 1");
+    }
+    Ok(())
+}
+
+fn merge_report(args: ReportArgs) -> Result<()> {
+    let stats = stats_enabled();
+    let t0 = Instant::now();
+
+    let sample_name = args
+        .output
+        .sample_name
+        .clone()
+        .unwrap_or_else(|| "merged".to_string());
+    validate_report_settings(&args.output)?;
+    let outputs = selected_outputs(&args.output)?;
+    let report_name = report_dir_name(&args.output, &sample_name)?;
+    let out_dir = prepare_out_dir(&args.output, report_name.as_deref())?;
+
+    let t_merge = Instant::now();
+    let mut output = saved_agg::merge_files(&args.aggregates, &sample_name)?;
+    // Optional settings left off the command line keep the saved values.
+    let ctx = &mut output.ctx;
+    let opts = &args.output;
+    ctx.title = opts.title.clone().or(ctx.title.take());
+    ctx.description = opts.description.clone().or(ctx.description.take());
+    ctx.genome_size = opts.genome_size.or(ctx.genome_size);
+    ctx.strand_bias_pct = opts.strand_bias_pct.unwrap_or(ctx.strand_bias_pct);
+    ctx.content_bias_window = opts.content_bias_window.or(ctx.content_bias_window);
+    ctx.qual_trend_window = opts.qual_trend_window.or(ctx.qual_trend_window);
+    ctx.overrep_threshold_pct = opts.overrep_threshold.unwrap_or(ctx.overrep_threshold_pct);
+    ctx.max_overrep_rows = opts.max_overrep_rows.or(ctx.max_overrep_rows);
+    ctx.max_kmer_rows = opts.max_kmer_rows.unwrap_or(ctx.max_kmer_rows);
+    ctx.gc_reference = gc_reference(opts)?.or(ctx.gc_reference.take());
+    stage_done(stats, "merge-aggregates", t_merge);
+
+    let zip_root = report_name.as_deref().unwrap_or(&sample_name);
+    write_outputs(stats, &args.output, outputs, &out_dir, zip_root, &output)?;
 
-    let t_html = Instant::now();
-    report::html::write(&html_path, &output)
-        .with_context(|| format!("failed to write {}", html_path.display()))?;
-    stage_done(stats, "html", t_html);
     if stats {
-        let html_size = fs::metadata(&html_path).map(|m| m.len()).unwrap_or(0);
-        eprintln!(
-            "KIRA_STATS output html={} bytes={}",
-            html_path.display(),
-            html_size
+        debug!("KIRA_STATS output_dir={}", out_dir.display());
+        debug!("KIRA_STATS total={}", fmt_dur(t0.elapsed()));
+    }
+    info!(
+        "merged {} aggregates: {} reads, {} bases in {} -> {}",
+        args.aggregates.len(),
+        output.agg.total_reads,
+        output.agg.total_bases,
+        fmt_dur(t0.elapsed()),
+        out_dir.display()
+    );
+
+    Ok(())
+}
+
+// Directory under --out that holds the report files; None with --flat,
+// where they go into --out itself, and with --single-file.
+fn report_dir_name(opts: &OutputArgs, sample_name: &str) -> Result<Option<String>> {
+    if opts.flat || opts.single_file.is_some() {
+        return Ok(None);
+    }
+    let name = opts.out_name.replace("{sample}", sample_name);
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        bail!(
+            "--out-name must expand to a single directory name, got {:?}",
+            name
         );
     }
+    Ok(Some(name))
+}
+
+// --out; clap only lets it be missing under --single-file, which never
+// reaches the directory outputs.
+fn out_root(opts: &OutputArgs) -> &Path {
+    opts.out
+        .as_deref()
+        .expect("--out is required without --single-file")
+}
+
+// Creates the directory the reports go into: `report_name` under --out, or
+// the directory of the --single-file HTML.
+fn prepare_out_dir(opts: &OutputArgs, report_name: Option<&str>) -> Result<PathBuf> {
+    if let Some(path) = &opts.single_file {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        return create_out_dir(&dir, None);
+    }
+    create_out_dir(out_root(opts), report_name)
+}
+
+fn create_out_dir(out: &Path, report_name: Option<&str>) -> Result<PathBuf> {
+    let out_dir = match report_name {
+        Some(name) => out.join(name),
+        None => out.to_path_buf(),
+    };
+    fs::create_dir_all(&out_dir)
+        .with_context(|| format!("failed to create output dir {}", out_dir.display()))?;
+    Ok(out_dir)
+}
+
+// Report files to write. Without --only that is everything except LaTeX,
+// which --export-latex turns on; with --only it is exactly the listed set.
+#[derive(Clone, Copy, Debug)]
+struct Outputs {
+    txt: bool,
+    summary: bool,
+    html: bool,
+    zip: bool,
+    latex: bool,
+}
+
+fn selected_outputs(opts: &OutputArgs) -> Result<Outputs> {
+    if opts.only.is_empty() {
+        return Ok(Outputs {
+            txt: true,
+            summary: true,
+            html: true,
+            zip: !opts.no_zip,
+            latex: opts.export_latex.is_some(),
+        });
+    }
+    let outputs = Outputs {
+        txt: opts.only.contains(&OutputArg::Txt),
+        summary: opts.only.contains(&OutputArg::Summary),
+        html: opts.only.contains(&OutputArg::Html),
+        zip: opts.only.contains(&OutputArg::Zip),
+        latex: opts.only.contains(&OutputArg::Latex),
+    };
+    if outputs.latex != opts.export_latex.is_some() {
+        bail!("--only latex and --export-latex must be given together");
+    }
+    if outputs.zip && opts.no_zip {
+        bail!("--only zip conflicts with --no-zip");
+    }
+    if outputs.zip && !(outputs.txt || outputs.summary || outputs.html) {
+        bail!("--only zip needs at least one of txt, summary or html to bundle");
+    }
+    Ok(outputs)
+}
+
+fn write_outputs(
+    stats: bool,
+    opts: &OutputArgs,
+    outputs: Outputs,
+    out_dir: &Path,
+    zip_root: &str,
+    output: &RunOutput,
+) -> Result<()> {
+    // The one HTML file replaces every other output, zip included.
+    if let Some(path) = &opts.single_file {
+        return write_html(stats, opts, path, output);
+    }
+
+    let fastqc_path = out_dir.join("fastqc_data.txt");
+    let summary_path = out_dir.join("summary.txt");
+    let html_path = out_dir.join("fastqc_report.html");
+    let mut written = Vec::new();
+
+    if outputs.txt {
+        write_fastqc_data(stats, &fastqc_path, output)?;
+        written.push("fastqc_data.txt");
+    }
+    if outputs.summary {
+        write_summary(stats, &summary_path, output)?;
+        written.push("summary.txt");
+    }
+    if outputs.html {
+        write_html(stats, opts, &html_path, output)?;
+        written.push("fastqc_report.html");
+    }
 
-    if !args.no_zip {
+    if outputs.zip {
         let t_zip = Instant::now();
-        report::zip::write_zip(&args.out, &sample_name)
+        report::zip::write_zip(out_root(opts), out_dir, zip_root, &written)
             .with_context(|| "failed to create zip output")?;
         stage_done(stats, "zip", t_zip);
         if stats {
-            let zip_path = args.out.join(format!("{}_fastqc.zip", sample_name));
+            let zip_path = out_root(opts).join(format!("{}.zip", zip_root));
             let zip_size = fs::metadata(&zip_path).map(|m| m.len()).unwrap_or(0);
-            eprintln!(
+            debug!(
                 "KIRA_STATS output zip={} bytes={}",
                 zip_path.display(),
                 zip_size
@@ -148,27 +862,143 @@ fn run(args: RunArgs) -> Result<()> {
         }
     }
 
-    if let Some(export) = args.export_latex {
+    // The zip above already deflates its entries, so it bundles the plain
+    // files; only the loose text files are gzipped. The HTML report stays
+    // plain so it still opens in a browser.
+    if opts.compress_output {
+        let t_gzip = Instant::now();
+        for (path, wrote) in [
+            (&fastqc_path, outputs.txt),
+            (&summary_path, outputs.summary),
+        ] {
+            if wrote {
+                report::gzip::compress_file(path)?;
+            }
+        }
+        stage_done(stats, "gzip", t_gzip);
+    }
+
+    if outputs.latex
+        && let Some(export) = opts.export_latex
+    {
         let t_latex = Instant::now();
         let mode = match export {
             LatexExportArg::Summary => report::latex::LatexMode::Summary,
             LatexExportArg::Supplement => report::latex::LatexMode::Supplement,
         };
-        report::latex::write(&out_dir, &output, mode)
+        report::latex::write(out_dir, output, mode)
             .with_context(|| "failed to write LaTeX export")?;
         stage_done(stats, "latex", t_latex);
     }
 
+    if let Some(format) = opts.emit_figures {
+        let t_figures = Instant::now();
+        let format = match format {
+            FigureFormatArg::Svg => report::figures::FigureFormat::Svg,
+            FigureFormatArg::Png => report::figures::FigureFormat::Png,
+            FigureFormatArg::Pdf => report::figures::FigureFormat::Pdf,
+        };
+        report::figures::write(out_dir, output, format)
+            .with_context(|| "failed to write figures")?;
+        stage_done(stats, "figures", t_figures);
+    }
+
+    Ok(())
+}
+
+fn write_fastqc_data(stats: bool, fastqc_path: &Path, output: &RunOutput) -> Result<()> {
+    let t_fastqc = Instant::now();
+    report::fastqc_txt::write(fastqc_path, output)
+        .with_context(|| format!("failed to write {}", fastqc_path.display()))?;
+    stage_done(stats, "fastqc_data", t_fastqc);
     if stats {
-        eprintln!("KIRA_STATS output_dir={}", out_dir.display());
-        eprintln!("KIRA_STATS total={}", fmt_dur(t0.elapsed()));
+        let fastqc_size = fs::metadata(fastqc_path).map(|m| m.len()).unwrap_or(0);
+        debug!(
+            "KIRA_STATS output fastqc_data={} bytes={}",
+            fastqc_path.display(),
+            fastqc_size
+        );
     }
+    Ok(())
+}
 
+fn write_summary(stats: bool, summary_path: &Path, output: &RunOutput) -> Result<()> {
+    let t_summary = Instant::now();
+    report::summary_txt::write(summary_path, output)
+        .with_context(|| format!("failed to write {}", summary_path.display()))?;
+    stage_done(stats, "summary", t_summary);
+    if stats {
+        let summary_size = fs::metadata(summary_path).map(|m| m.len()).unwrap_or(0);
+        debug!(
+            "KIRA_STATS output summary={} bytes={}",
+            summary_path.display(),
+            summary_size
+        );
+    }
+    Ok(())
+}
+
+fn write_html(stats: bool, opts: &OutputArgs, html_path: &Path, output: &RunOutput) -> Result<()> {
+    let t_html = Instant::now();
+    let html_opts = report::html::HtmlOptions {
+        interactive: opts.html_interactive,
+        png_plots: opts.html_png,
+        qual_plot: match opts.qual_plot {
+            QualPlotArg::Boxplot => report::html::QualPlot::Boxplot,
+            QualPlotArg::Heatmap => report::html::QualPlot::Heatmap,
+        },
+        theme: match opts.theme {
+            ThemeArg::Default => report::html::Theme::Default,
+            ThemeArg::Dark => report::html::Theme::Dark,
+            ThemeArg::Colorblind => report::html::Theme::Colorblind,
+        },
+        content_annotate: opts.content_annotate,
+        embed_data: opts.single_file.is_some(),
+    };
+    report::html::write(html_path, output, &html_opts)
+        .with_context(|| format!("failed to write {}", html_path.display()))?;
+    stage_done(stats, "html", t_html);
+    if stats {
+        let html_size = fs::metadata(html_path).map(|m| m.len()).unwrap_or(0);
+        debug!(
+            "KIRA_STATS output html={} bytes={}",
+            html_path.display(),
+            html_size
+        );
+    }
     Ok(())
 }
 
 fn stats_enabled() -> bool {
-    matches!(env::var("KIRA_STATS").as_deref(), Ok("1"))
+    log_enabled!(Level::Debug)
+}
+
+// KIRA_STATS=1 predates --log-level and is kept as an alias for debug.
+fn init_logging(cli: &Cli) {
+    let level = if cli.quiet {
+        LevelFilter::Error
+    } else if cli.verbose || matches!(env::var("KIRA_STATS").as_deref(), Ok("1")) {
+        LevelFilter::Debug
+    } else {
+        match cli.log_level {
+            LogLevelArg::Error => LevelFilter::Error,
+            LogLevelArg::Warn => LevelFilter::Warn,
+            LogLevelArg::Info => LevelFilter::Info,
+            LogLevelArg::Debug => LevelFilter::Debug,
+            LogLevelArg::Trace => LevelFilter::Trace,
+        }
+    };
+    // Dependencies (e.g. usvg's font fallback notices) only get to report
+    // errors; the chosen level applies to kira-qc's own messages.
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Error)
+        .filter_module("kira_qc", level)
+        .format(|buf, record| match record.level() {
+            Level::Error => writeln!(buf, "error: {}", record.args()),
+            Level::Warn => writeln!(buf, "warning: {}", record.args()),
+            _ => writeln!(buf, "{}", record.args()),
+        })
+        .init();
 }
 
 fn stage<F>(stats: bool, name: &str, f: F) -> Result<()>
@@ -178,14 +1008,31 @@ where
     let t = Instant::now();
     let res = f();
     if stats {
-        eprintln!("KIRA_STATS stage={} time={}", name, fmt_dur(t.elapsed()));
+        debug!("KIRA_STATS stage={} time={}", name, fmt_dur(t.elapsed()));
     }
     res
 }
 
+fn gc_reference(opts: &OutputArgs) -> Result<Option<GcReference>> {
+    if let Some(gc) = opts.expected_gc {
+        if !(0.0..=100.0).contains(&gc) {
+            bail!("--expected-gc must be in [0, 100]");
+        }
+        return Ok(Some(GcReference::Mean(gc)));
+    }
+    let Some(path) = &opts.reference_gc else {
+        return Ok(None);
+    };
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    GcReference::parse_distribution(&text)
+        .map(Some)
+        .with_context(|| format!("invalid --reference-gc file {}", path.display()))
+}
+
 fn stage_done(stats: bool, name: &str, t: Instant) {
     if stats {
-        eprintln!("KIRA_STATS stage={} time={}", name, fmt_dur(t.elapsed()));
+        debug!("KIRA_STATS stage={} time={}", name, fmt_dur(t.elapsed()));
     }
 }
 
@@ -196,3 +1043,28 @@ fn fmt_dur(d: Duration) -> String {
         format!("{:.3}s", d.as_secs_f64())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_sample_name_strips_known_extensions() {
+        let cases = [
+            ("sample.fastq.gz", "sample"),
+            ("sample.FQ.GZ", "sample"),
+            ("contigs.fa.bz2", "contigs"),
+            ("sample.fastq", "sample"),
+            ("lane1.bam", "lane1"),
+            ("name.with.dots.txt", "name.with.dots"),
+            ("reads.txt.gz", "reads.txt"),
+            ("sample", "sample"),
+            (".hidden", ".hidden"),
+            (".fastq.gz", ".fastq"),
+        ];
+        for (file, want) in cases {
+            let got = default_sample_name(Path::new(file)).unwrap();
+            assert_eq!(got, want, "{file}");
+        }
+    }
+}
@@ -1,84 +1,516 @@
-use crate::core::fastq::{self, OwnedRead};
-use crate::core::metrics::{Agg, UpdateTimings};
-use crate::core::model::{Encoding, FinalizeContext, Mode};
+use crate::core::fastq::OwnedRead;
+use crate::core::input::{self, InputReader};
+use crate::core::metrics::{
+    Agg, AggConfig, DEFAULT_MAX_KMER_ROWS, DEFAULT_MAX_READ_LEN, DEFAULT_OVERREP_SEQ_LEN,
+    DEFAULT_OVERREP_THRESHOLD_PCT, DEFAULT_STRAND_BIAS_PCT, GcReference, GcResolution,
+    MemoryProfile, SkipModules, UpdateTimings,
+};
+use crate::core::model::{self, Encoding, FileHash, FinalizeContext, HashAlgorithm, Mode};
+use crate::core::progress::{self, Progress};
 use anyhow::{Context, Result, anyhow, bail};
 use crossbeam_channel as channel;
-use kira_fastq::FastqReader;
-use std::path::PathBuf;
+use log::{Level, debug, info, log_enabled, warn};
+use serde::{Serialize, Serializer};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::{Duration, Instant};
 
 const AUTO_DETECT_READS: usize = 50_000;
 const TARGET_CHUNK_BYTES: usize = 16 * 1024 * 1024;
+const PROGRESS_EVERY_READS: u64 = 4096;
+// Below this size a single-threaded gzip decode is not worth warning about.
+const GZIP_WARN_BYTES: u64 = 1024 * 1024 * 1024;
+// Rough decompressed/compressed size ratio of FASTQ; only used to size the
+// worker pool for compressed inputs.
+const COMPRESSION_RATIO_ESTIMATE: u64 = 4;
+// Lowest quality byte accepted for each offset. Phred+64 data starts at ';'
+// (Solexa -5), so anything below it means phred+33.
+const STRICT_MIN_QUAL_33: u8 = 33;
+const STRICT_MIN_QUAL_64: u8 = 59;
+// Highest printable quality byte for either offset.
+const MAX_QUAL_BYTE: u8 = b'~';
 
 pub enum PhredOffsetConfig {
     Auto,
     Fixed(u8),
 }
 
+#[derive(Clone, Copy, Debug)]
+pub enum Subsample {
+    Head(u64),
+    Stride(u64),
+    // About this fraction of the reads, as a Stride worked out from the file
+    // size (see fraction_stride).
+    Fraction(f64),
+    // Windows of SAMPLE_REGION_READS reads at this many evenly spaced
+    // points of an indexed BGZF file; the head of the file otherwise.
+    Regions(u64),
+}
+
+// Reads taken from each --sample-regions window.
+pub const SAMPLE_REGION_READS: u64 = 10_000;
+
+// Reads from the head of the file used to size records for
+// Subsample::Fraction.
+const FRACTION_PROBE_READS: u64 = 1_000;
+
+// Settings of one run. Start from `RunConfig::new` (or `Default`) and set the
+// fields that differ; new options are added with defaults, so the struct is
+// non-exhaustive.
+#[non_exhaustive]
 pub struct RunConfig {
     pub reads1: PathBuf,
-    pub out_dir: PathBuf,
     pub sample_name: String,
+    // Worker count and pinning for `run`; `run_with_pool` uses the pool's.
     pub threads: usize,
     pub phred_offset: PhredOffsetConfig,
+    // Reported Encoding; does not change how qualities are decoded.
+    pub encoding_label: Option<Encoding>,
     pub mode: Mode,
+    pub subsample: Option<Subsample>,
+    // Stop reading once this many reads/bases have been taken; the report
+    // covers what was read up to that point.
+    pub max_reads: Option<u64>,
+    pub max_bases: Option<u64>,
+    pub progress: bool,
+    // Pin worker i to logical CPU i; ignored where affinity is unsupported.
+    pub pin_threads: bool,
+    pub strict_phred: bool,
+    // Skip and count malformed FASTQ records instead of failing the run.
+    pub lenient: bool,
+    // Strip trailing spaces/tabs from sequence and quality lines before the
+    // length check, counting the records that needed it.
+    pub trim_trailing_ws: bool,
+    // Hash the input file as it is read and report the digest.
+    pub hash: Option<HashAlgorithm>,
+    // Reads shorter than this are reported as filtered sequences and, with
+    // exclude_filtered, left out of every other metric.
+    pub min_length: Option<usize>,
+    pub exclude_filtered: bool,
+    pub max_read_len: usize,
+    pub overrep_max_len: usize,
+    // --overrep-prefix-len; see Agg::overrep_prefix_len.
+    pub overrep_prefix_len: Option<usize>,
+    // Short mode: pixel distance for the optical duplicate estimate.
+    pub optical_dup_distance: Option<u32>,
+    pub length_quality_map: bool,
+    // Long mode: also bin adapter matches by relative read position.
+    pub adapter_positions: bool,
+    // Long mode: per sequence GC bin width.
+    pub gc_resolution: GcResolution,
+    pub allow_empty: bool,
+    pub exact_quantiles: bool,
+    // Seed for the sampled metrics (--exact-quantiles reservoirs); output
+    // is identical for a given seed, whatever the thread count.
+    pub seed: u64,
+    // Count lowercase (soft-masked) bases for Basic Statistics.
+    pub track_softmask: bool,
+    pub collapse_revcomp: bool,
+    pub skip: SkipModules,
+    // Sketch sizes for duplication, overrepresented and k-mer content.
+    pub memory: MemoryProfile,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub genome_size: Option<u64>,
+    pub strand_bias_pct: f64,
+    pub content_bias_window: Option<usize>,
+    pub qual_trend_window: Option<usize>,
+    pub overrep_threshold_pct: f64,
+    pub max_overrep_rows: Option<usize>,
+    pub max_kmer_rows: usize,
+    pub gc_reference: Option<GcReference>,
+    // Collect producer/worker timings and stage durations into
+    // RunOutput::profile.
+    pub profile: bool,
+}
+
+// The `kira-qc run` defaults, on one thread.
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            reads1: PathBuf::new(),
+            sample_name: String::new(),
+            threads: 1,
+            phred_offset: PhredOffsetConfig::Auto,
+            encoding_label: None,
+            mode: Mode::Short,
+            subsample: None,
+            max_reads: None,
+            max_bases: None,
+            progress: false,
+            pin_threads: false,
+            strict_phred: false,
+            lenient: false,
+            trim_trailing_ws: false,
+            hash: None,
+            min_length: None,
+            exclude_filtered: false,
+            max_read_len: DEFAULT_MAX_READ_LEN,
+            overrep_max_len: DEFAULT_OVERREP_SEQ_LEN,
+            overrep_prefix_len: None,
+            optical_dup_distance: None,
+            length_quality_map: false,
+            adapter_positions: false,
+            gc_resolution: GcResolution::Percent,
+            allow_empty: false,
+            exact_quantiles: false,
+            seed: 0,
+            track_softmask: false,
+            collapse_revcomp: false,
+            skip: SkipModules::default(),
+            memory: MemoryProfile::High,
+            title: None,
+            description: None,
+            genome_size: None,
+            strand_bias_pct: DEFAULT_STRAND_BIAS_PCT,
+            content_bias_window: None,
+            qual_trend_window: None,
+            overrep_threshold_pct: DEFAULT_OVERREP_THRESHOLD_PCT,
+            max_overrep_rows: None,
+            max_kmer_rows: DEFAULT_MAX_KMER_ROWS,
+            gc_reference: None,
+            profile: false,
+        }
+    }
+}
+
+impl RunConfig {
+    // Defaults for `reads1`, with the sample named after the file (as
+    // FastQC's Filename row is) and the worker count `kira-qc run` would
+    // pick for it.
+    pub fn new(reads1: impl Into<PathBuf>) -> Self {
+        let reads1 = reads1.into();
+        Self {
+            sample_name: reads1
+                .file_name()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            threads: auto_threads(&reads1),
+            reads1,
+            ..Self::default()
+        }
+    }
+
+    pub fn agg_config(&self) -> AggConfig {
+        AggConfig {
+            mode: self.mode,
+            max_read_len: self.max_read_len,
+            overrep_max_len: self.overrep_max_len,
+            overrep_prefix_len: self.overrep_prefix_len,
+            optical_dup_distance: self.optical_dup_distance,
+            length_quality_map: self.length_quality_map,
+            adapter_positions: self.adapter_positions,
+            gc_resolution: self.gc_resolution,
+            exact_quantiles: self.exact_quantiles,
+            seed: self.seed,
+            track_softmask: self.track_softmask,
+            collapse_revcomp: self.collapse_revcomp,
+            skip: self.skip,
+            memory: self.memory,
+        }
+    }
 }
 
 pub struct RunOutput {
     pub agg: Agg,
     pub ctx: FinalizeContext,
+    // Worker threads given at least one chunk; 0 for merged saved
+    // aggregates.
+    pub workers: usize,
+    // Producer/worker timings and stage durations as pretty-printed JSON
+    // (what --profile-json writes), when RunConfig::profile is set.
+    pub profile: Option<String>,
 }
 
 struct WorkChunk {
     index: usize,
     reads: Vec<OwnedRead>,
+    // 1-based record number of each read in the input, counting reads
+    // skipped by subsampling.
+    read_records: Vec<u64>,
     bytes: usize,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 struct ProducerStats {
     chunks: u64,
     bytes: u64,
     reads: u64,
+    #[serde(serialize_with = "secs")]
     parse: Duration,
+    // Flag of the limit that stopped the producer early, if any.
+    truncated: Option<&'static str>,
+    malformed_records: u64,
+    ws_trimmed_records: u64,
+    #[serde(skip)]
+    file_hash: Option<FileHash>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 struct WorkerStats {
     chunks: u64,
     bytes: u64,
     reads: u64,
+    #[serde(serialize_with = "secs")]
     parse: Duration,
-    metrics_core: Duration,
+    #[serde(serialize_with = "secs")]
+    base_counting: Duration,
+    #[serde(serialize_with = "secs")]
+    per_position: Duration,
+    #[serde(serialize_with = "secs")]
+    length_gc: Duration,
+    #[serde(serialize_with = "secs")]
     adapters: Duration,
+    #[serde(serialize_with = "secs")]
     heavyhitters: Duration,
+    #[serde(serialize_with = "secs")]
     kmer: Duration,
+    #[serde(serialize_with = "secs")]
     kmer_encode: Duration,
+    #[serde(serialize_with = "secs")]
     kmer_keygen: Duration,
+    #[serde(serialize_with = "secs")]
     kmer_binning: Duration,
+    #[serde(serialize_with = "secs")]
     kmer_cms: Duration,
+    #[serde(serialize_with = "secs")]
     kmer_hh: Duration,
     kmer_updates: u64,
 }
 
+impl WorkerStats {
+    fn add(&mut self, other: &WorkerStats) {
+        self.chunks += other.chunks;
+        self.bytes += other.bytes;
+        self.reads += other.reads;
+        self.parse += other.parse;
+        self.base_counting += other.base_counting;
+        self.per_position += other.per_position;
+        self.length_gc += other.length_gc;
+        self.adapters += other.adapters;
+        self.heavyhitters += other.heavyhitters;
+        self.kmer += other.kmer;
+        self.kmer_encode += other.kmer_encode;
+        self.kmer_keygen += other.kmer_keygen;
+        self.kmer_binning += other.kmer_binning;
+        self.kmer_cms += other.kmer_cms;
+        self.kmer_hh += other.kmer_hh;
+        self.kmer_updates += other.kmer_updates;
+    }
+}
+
+#[derive(Serialize)]
+struct StageTime {
+    name: &'static str,
+    #[serde(serialize_with = "secs")]
+    time: Duration,
+}
+
+// Written by --profile-json; all durations are in seconds and worker
+// timings are summed over all workers.
+#[derive(Serialize)]
+struct Profile<'a> {
+    threads: usize,
+    producer: &'a ProducerStats,
+    workers: &'a WorkerStats,
+    #[serde(serialize_with = "secs")]
+    reducer_wait: Duration,
+    #[serde(serialize_with = "secs")]
+    reducer_merge: Duration,
+    stages: &'a [StageTime],
+}
+
+// Settings and result channels of one run, shared by all of its chunks.
+struct RunJob {
+    phred_offset: u8,
+    encoding: Encoding,
+    agg: AggConfig,
+    strict_phred: bool,
+    min_length: Option<usize>,
+    exclude_filtered: bool,
+    stats_enabled: bool,
+    result_tx: channel::Sender<(usize, Agg, WorkerStats)>,
+    err_tx: channel::Sender<anyhow::Error>,
+    // Set once a chunk fails; the producer stops and workers drop the run's
+    // remaining chunks.
+    failed: AtomicBool,
+}
+
+type Job = (Arc<RunJob>, WorkChunk);
+
+// Worker threads that outlive a single run, so a list of inputs spawns (and
+// pins) them once. Each chunk carries the settings of the run it belongs to.
+pub struct WorkerPool {
+    jobs: channel::Sender<Job>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    // With `pin_threads`, worker i is pinned to logical CPU i (wrapping
+    // around the CPU count); ignored where affinity is unsupported.
+    pub fn new(threads: usize, pin_threads: bool) -> Self {
+        let threads = threads.max(1);
+        let (jobs, job_rx) = channel::bounded::<Job>(threads * 2);
+        let core_ids = if pin_threads {
+            let ids = core_affinity::get_core_ids().unwrap_or_default();
+            if ids.is_empty() {
+                debug!("--pin-threads: CPU affinity is not supported here, workers are not pinned");
+            }
+            ids
+        } else {
+            Vec::new()
+        };
+        let workers = (0..threads)
+            .map(|worker_index| {
+                let core_id = if core_ids.is_empty() {
+                    None
+                } else {
+                    Some(core_ids[worker_index % core_ids.len()])
+                };
+                let rx = job_rx.clone();
+                thread::spawn(move || {
+                    if let Some(id) = core_id
+                        && !core_affinity::set_for_current(id)
+                    {
+                        debug!("failed to pin worker {} to CPU {}", worker_index, id.id);
+                    }
+                    for (job, chunk) in rx.iter() {
+                        if job.failed.load(Ordering::Relaxed) {
+                            continue;
+                        }
+                        match process_chunk(&job, chunk) {
+                            Ok(result) => {
+                                let _ = job.result_tx.send(result);
+                            }
+                            Err(e) => {
+                                job.failed.store(true, Ordering::Relaxed);
+                                let _ = job.err_tx.try_send(e);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        Self { jobs, workers }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // Disconnect the job channel so the workers' loops end.
+        let (closed, _) = channel::bounded(0);
+        drop(std::mem::replace(&mut self.jobs, closed));
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn process_chunk(job: &RunJob, mut chunk: WorkChunk) -> Result<(usize, Agg, WorkerStats)> {
+    let mut wstats = WorkerStats::default();
+    let invalid = scan_invalid_qual(&chunk, job.phred_offset);
+    if job.strict_phred
+        && let Some(invalid) = &invalid
+    {
+        return Err(invalid.to_error(job.phred_offset));
+    }
+    if job.encoding == Encoding::Solexa {
+        for read in &mut chunk.reads {
+            model::solexa_to_phred64(&mut read.qual);
+        }
+    }
+    let mut agg = Agg::new(&job.agg);
+    if let Some(invalid) = invalid {
+        agg.invalid_qual_bytes = invalid.bytes;
+        agg.first_invalid_qual_record = Some(invalid.first_record);
+    }
+    let t_parse = Instant::now();
+    for read in &chunk.reads {
+        if job.min_length.is_some_and(|min| read.seq.len() < min) {
+            agg.filtered_reads += 1;
+            if job.exclude_filtered {
+                continue;
+            }
+        }
+        let read_view = read.as_view();
+        if job.stats_enabled {
+            let mut ut = UpdateTimings::default();
+            agg.update_read_timed(&read_view, job.phred_offset, &mut ut);
+            wstats.base_counting += ut.base_counting;
+            wstats.per_position += ut.per_position;
+            wstats.length_gc += ut.length_gc;
+            wstats.adapters += ut.adapters;
+            wstats.heavyhitters += ut.heavyhitters;
+            wstats.kmer += ut.kmer;
+            wstats.kmer_encode += ut.kmer_encode;
+            wstats.kmer_keygen += ut.kmer_keygen;
+            wstats.kmer_binning += ut.kmer_binning;
+            wstats.kmer_cms += ut.kmer_cms;
+            wstats.kmer_hh += ut.kmer_hh;
+            wstats.kmer_updates += ut.kmer_updates;
+        } else {
+            agg.update_read(&read_view, job.phred_offset);
+        }
+    }
+    wstats.parse += t_parse.elapsed();
+    wstats.chunks += 1;
+    wstats.bytes += chunk.bytes as u64;
+    wstats.reads += chunk.reads.len() as u64;
+    Ok((chunk.index, agg, wstats))
+}
+
 pub fn run(cfg: RunConfig) -> Result<RunOutput> {
-    let stats = stats_enabled();
+    let pool = WorkerPool::new(cfg.threads, cfg.pin_threads);
+    run_with_pool(cfg, &pool)
+}
+
+pub fn run_with_pool(cfg: RunConfig, pool: &WorkerPool) -> Result<RunOutput> {
+    run_chunked(cfg, pool, TARGET_CHUNK_BYTES)
+}
+
+// Chunks close once they hold `chunk_target` bytes of reads (lowered in
+// tests).
+fn run_chunked(cfg: RunConfig, pool: &WorkerPool, chunk_target: usize) -> Result<RunOutput> {
+    // Timings are collected for the profile even when they are not logged.
+    let stats = stats_enabled() || cfg.profile;
+    let mut stages = Vec::new();
     let t_total = Instant::now();
 
     let t_phred = Instant::now();
-    let phred_offset = match cfg.phred_offset {
-        PhredOffsetConfig::Fixed(v) => v,
-        PhredOffsetConfig::Auto => detect_phred_offset(&cfg.reads1)
+    let input_kind = input::detect_input_kind(&cfg.reads1)?;
+    let encoding = match cfg.phred_offset {
+        PhredOffsetConfig::Fixed(v) => encoding_for_offset(v),
+        PhredOffsetConfig::Auto => match input_kind.fixed_phred_offset() {
+            Some(v) => encoding_for_offset(v),
+            None => detect_encoding(
+                &cfg.reads1,
+                cfg.allow_empty,
+                cfg.lenient,
+                cfg.trim_trailing_ws,
+            )
             .with_context(|| "failed to auto-detect phred offset")?,
+        },
     };
-    log_stage(stats, "engine.phred_detect", t_phred);
-
-    let encoding = if phred_offset == 64 {
-        Encoding::Illumina15
-    } else {
-        Encoding::Sanger
-    };
+    let phred_offset = encoding.phred_offset();
+    log_stage(stats, &mut stages, "engine.phred_detect", t_phred);
+    if let Some(label) = cfg.encoding_label
+        && label.phred_offset() != phred_offset
+    {
+        warn!(
+            "--encoding {} implies phred+{}, but qualities are decoded as phred+{}",
+            label.label(),
+            label.phred_offset(),
+            phred_offset
+        );
+    }
 
     let file_name = cfg
         .reads1
@@ -87,29 +519,123 @@ pub fn run(cfg: RunConfig) -> Result<RunOutput> {
         .map(|s| s.to_string())
         .context("failed to determine input filename")?;
 
-    let ctx = FinalizeContext {
+    let mut ctx = FinalizeContext {
         phred_offset,
-        encoding,
+        encoding: cfg.encoding_label.unwrap_or(encoding),
         file_name,
         sample_name: cfg.sample_name.clone(),
         mode: cfg.mode,
+        subsampled: None,
+        truncated: None,
+        title: cfg.title.clone(),
+        description: cfg.description.clone(),
+        genome_size: cfg.genome_size,
+        strand_bias_pct: cfg.strand_bias_pct,
+        content_bias_window: cfg.content_bias_window,
+        qual_trend_window: cfg.qual_trend_window,
+        overrep_threshold_pct: cfg.overrep_threshold_pct,
+        max_overrep_rows: cfg.max_overrep_rows,
+        max_kmer_rows: cfg.max_kmer_rows,
+        gc_reference: cfg.gc_reference.clone(),
+        file_hash: None,
     };
 
-    let (chunk_tx, chunk_rx) = channel::bounded::<WorkChunk>(cfg.threads * 2);
-    let (result_tx, result_rx) = channel::unbounded::<(usize, Agg)>();
+    let threads = pool.threads();
+    let chunk_tx = pool.jobs.clone();
+    let (result_tx, result_rx) = channel::unbounded::<(usize, Agg, WorkerStats)>();
     let (total_tx, total_rx) = channel::bounded::<usize>(1);
     let (err_tx, err_rx) = channel::bounded::<anyhow::Error>(1);
     let (prod_stats_tx, prod_stats_rx) = channel::bounded::<ProducerStats>(1);
-    let (worker_stats_tx, worker_stats_rx) = channel::unbounded::<WorkerStats>();
+    let job = Arc::new(RunJob {
+        phred_offset,
+        encoding,
+        agg: cfg.agg_config(),
+        strict_phred: cfg.strict_phred,
+        min_length: cfg.min_length,
+        exclude_filtered: cfg.exclude_filtered,
+        stats_enabled: stats,
+        result_tx,
+        err_tx,
+        failed: AtomicBool::new(false),
+    });
 
     let producer_path = cfg.reads1.clone();
-    let producer_err = err_tx.clone();
+    let decode_threads = threads;
+    let subsample = match cfg.subsample {
+        Some(Subsample::Fraction(f)) => Some(Subsample::Stride(fraction_stride(
+            &cfg.reads1,
+            f,
+            cfg.lenient,
+            cfg.trim_trailing_ws,
+        )?)),
+        other => other,
+    };
+    let max_reads = cfg.max_reads;
+    let max_bases = cfg.max_bases;
+    let lenient = cfg.lenient;
+    let trim_trailing_ws = cfg.trim_trailing_ws;
+    let hash = cfg.hash;
+    if threads > 1 && input_kind == input::InputKind::Fastq && is_gzip(&cfg.reads1) {
+        let size = std::fs::metadata(&cfg.reads1).map(|m| m.len()).unwrap_or(0);
+        if size >= GZIP_WARN_BYTES {
+            warn!(
+                "{} is single-stream gzip; decompression runs on one thread and may limit throughput (bgzip-compressed input avoids this)",
+                ctx.file_name
+            );
+        }
+    }
+
+    let mut progress = if cfg.progress {
+        // Compressed inputs have no known uncompressed size, so only
+        // throughput is reported for them.
+        let total = if input_kind == input::InputKind::Bzip2 || is_gzip(&cfg.reads1) {
+            None
+        } else {
+            std::fs::metadata(&cfg.reads1).ok().map(|m| m.len())
+        };
+        Some(Progress::new(total))
+    } else {
+        None
+    };
     let t_producer = Instant::now();
     let producer = thread::spawn(move || {
-        let mut reader = match FastqReader::from_path_auto(&producer_path) {
+        let mut subsample = subsample;
+        let regions = match subsample {
+            Some(Subsample::Regions(n)) => InputReader::open_regions(
+                &producer_path,
+                decode_threads,
+                n as usize,
+                SAMPLE_REGION_READS,
+                lenient,
+                trim_trailing_ws,
+            ),
+            _ => Ok(None),
+        };
+        let opened = match regions {
+            Ok(Some(reader)) => Ok(reader),
+            Ok(None) => {
+                if let Some(Subsample::Regions(n)) = subsample {
+                    warn!(
+                        "{}: --sample-regions needs a BGZF file with a block index; sampling the first {} reads instead",
+                        producer_path.display(),
+                        n * SAMPLE_REGION_READS
+                    );
+                    subsample = Some(Subsample::Head(n * SAMPLE_REGION_READS));
+                }
+                InputReader::open(
+                    &producer_path,
+                    decode_threads,
+                    lenient,
+                    trim_trailing_ws,
+                    hash,
+                )
+            }
+            Err(e) => Err(e),
+        };
+        let mut reader = match opened {
             Ok(reader) => reader,
             Err(e) => {
-                let _ = producer_err.send(anyhow!("failed to open FASTQ input: {e:?}"));
+                let _ = job.err_tx.send(e);
                 return;
             }
         };
@@ -117,33 +643,74 @@ pub fn run(cfg: RunConfig) -> Result<RunOutput> {
         let mut stats = ProducerStats::default();
         let mut chunk_index = 0usize;
         let mut batch_reads = Vec::new();
+        let mut batch_records = Vec::new();
         let mut batch_bytes = 0usize;
+        let mut seen = 0u64;
+        let mut kept = 0u64;
+        let mut kept_bases = 0u64;
+        let mut input_bytes = 0u64;
 
         loop {
+            if let Some(Subsample::Head(n)) = subsample
+                && kept >= n
+            {
+                break;
+            }
             let t_next = Instant::now();
-            let rec = match reader.next() {
-                Ok(Some(rec)) => rec,
+            let owned = match reader.next_read() {
+                Ok(Some(read)) => read,
                 Ok(None) => break,
                 Err(e) => {
-                    let _ = producer_err.send(anyhow!("FASTQ parse/read error: {e:?}"));
+                    let _ = job.err_tx.send(e);
                     return;
                 }
             };
             stats.parse += t_next.elapsed();
 
-            let owned = fastq::OwnedRead::from_record(rec);
+            seen += 1;
+            input_bytes += owned.byte_len() as u64 + progress::RECORD_OVERHEAD;
+            if let Some(p) = progress.as_mut()
+                && seen.is_multiple_of(PROGRESS_EVERY_READS)
+            {
+                p.update(seen, input_bytes);
+            }
+            if let Some(Subsample::Stride(k)) = subsample
+                && !(seen - 1).is_multiple_of(k)
+            {
+                continue;
+            }
+            if let Some(limit) = max_reads
+                && kept >= limit
+            {
+                stats.truncated = Some("--max-reads");
+                break;
+            }
+            let read_bases = owned.seq.len() as u64;
+            if let Some(limit) = max_bases
+                && kept_bases + read_bases > limit
+            {
+                stats.truncated = Some("--max-bases");
+                break;
+            }
+            kept += 1;
+            kept_bases += read_bases;
+
             batch_bytes += owned.byte_len();
             batch_reads.push(owned);
+            batch_records.push(seen);
 
-            if batch_bytes >= TARGET_CHUNK_BYTES {
+            if batch_bytes >= chunk_target {
                 let read_count = batch_reads.len() as u64;
                 let chunk_bytes = batch_bytes as u64;
                 let chunk = WorkChunk {
                     index: chunk_index,
                     reads: std::mem::take(&mut batch_reads),
+                    read_records: std::mem::take(&mut batch_records),
                     bytes: batch_bytes,
                 };
-                if chunk_tx.send(chunk).is_err() {
+                if job.failed.load(Ordering::Relaxed)
+                    || chunk_tx.send((Arc::clone(&job), chunk)).is_err()
+                {
                     return;
                 }
                 stats.chunks += 1;
@@ -160,9 +727,12 @@ pub fn run(cfg: RunConfig) -> Result<RunOutput> {
             let chunk = WorkChunk {
                 index: chunk_index,
                 reads: batch_reads,
+                read_records: batch_records,
                 bytes: batch_bytes,
             };
-            if chunk_tx.send(chunk).is_err() {
+            if job.failed.load(Ordering::Relaxed)
+                || chunk_tx.send((Arc::clone(&job), chunk)).is_err()
+            {
                 return;
             }
             stats.chunks += 1;
@@ -171,168 +741,129 @@ pub fn run(cfg: RunConfig) -> Result<RunOutput> {
             chunk_index += 1;
         }
 
+        if let Some(p) = progress.as_ref() {
+            p.finish(seen, input_bytes);
+        }
+        stats.malformed_records = reader.malformed_records();
+        stats.ws_trimmed_records = reader.ws_trimmed_records();
+        // A run stopped by --max-reads/--max-bases or after the first
+        // --subsample reads has not read the whole file, so its digest would
+        // not identify the input.
+        if stats.truncated.is_none() && !matches!(subsample, Some(Subsample::Head(_))) {
+            stats.file_hash = reader.file_hash();
+        }
         let _ = total_tx.send(chunk_index);
         let _ = prod_stats_tx.send(stats);
     });
-    log_stage(stats, "engine.spawn_producer", t_producer);
-
-    let mut workers = Vec::with_capacity(cfg.threads);
-    let t_workers = Instant::now();
-    for _ in 0..cfg.threads {
-        let rx = chunk_rx.clone();
-        let tx = result_tx.clone();
-        let stats_enabled = stats;
-        let stats_tx = worker_stats_tx.clone();
-        let mode = cfg.mode;
-        workers.push(thread::spawn(move || {
-            let mut wstats = WorkerStats::default();
-            for chunk in rx.iter() {
-                let mut agg = Agg::new(mode);
-                let t_parse = Instant::now();
-                for read in &chunk.reads {
-                    let read_view = read.as_view();
-                    if stats_enabled {
-                        let mut ut = UpdateTimings::default();
-                        agg.update_read_timed(&read_view, phred_offset, &mut ut);
-                        wstats.metrics_core += ut.metrics_core;
-                        wstats.adapters += ut.adapters;
-                        wstats.heavyhitters += ut.heavyhitters;
-                        wstats.kmer += ut.kmer;
-                        wstats.kmer_encode += ut.kmer_encode;
-                        wstats.kmer_keygen += ut.kmer_keygen;
-                        wstats.kmer_binning += ut.kmer_binning;
-                        wstats.kmer_cms += ut.kmer_cms;
-                        wstats.kmer_hh += ut.kmer_hh;
-                        wstats.kmer_updates += ut.kmer_updates;
-                    } else {
-                        agg.update_read(&read_view, phred_offset);
-                    }
-                }
-                wstats.parse += t_parse.elapsed();
-                wstats.chunks += 1;
-                wstats.bytes += chunk.bytes as u64;
-                wstats.reads += chunk.reads.len() as u64;
-
-                if tx.send((chunk.index, agg)).is_err() {
-                    break;
-                }
-            }
-
-            if stats_enabled {
-                let _ = stats_tx.send(wstats);
-            }
-        }));
-    }
-    log_stage(stats, "engine.spawn_workers", t_workers);
-    drop(result_tx);
-    drop(err_tx);
-    drop(worker_stats_tx);
+    log_stage(stats, &mut stages, "engine.spawn_producer", t_producer);
 
     let t_collect = Instant::now();
-    let total_chunks = total_rx.recv().context("failed to receive chunk count")?;
-    if total_chunks == 0 {
+    // A failed chunk stops the producer before it reports its total, so
+    // errors have to be picked up here too.
+    let total_chunks = channel::select! {
+        recv(total_rx) -> total => total.context("failed to receive chunk count")?,
+        recv(err_rx) -> err => match err {
+            Ok(err) => return Err(err),
+            Err(_) => total_rx.recv().context("failed to receive chunk count")?,
+        },
+    };
+    if total_chunks == 0 && !cfg.allow_empty {
         return Err(anyhow!("input file is empty"));
     }
 
     let mut parts: Vec<Option<Agg>> = vec![None; total_chunks];
+    let mut worker_stats = WorkerStats::default();
     let mut wait_time = Duration::ZERO;
     let mut err_open = true;
-    for _ in 0..total_chunks {
+    let mut received = 0usize;
+    while received < total_chunks {
         if err_open {
             let t_wait = Instant::now();
             channel::select! {
                 recv(err_rx) -> err => {
                     match err {
                         Ok(err) => return Err(err),
-                        Err(_) => {
-                            err_open = false;
-                            continue;
-                        }
+                        // Producer finished cleanly; keep waiting for results
+                        // without counting this wakeup as a received chunk.
+                        Err(_) => err_open = false,
                     }
                 }
                 recv(result_rx) -> msg => {
                     wait_time += t_wait.elapsed();
-                    let (index, agg) = msg.context("failed to receive chunk result")?;
+                    let (index, agg, wstats) = msg.context("failed to receive chunk result")?;
                     if index >= parts.len() {
                         return Err(anyhow!("invalid chunk index {}", index));
                     }
                     parts[index] = Some(agg);
+                    worker_stats.add(&wstats);
+                    received += 1;
                 }
             }
         } else {
             let t_wait = Instant::now();
-            let (index, agg) = result_rx.recv().context("failed to receive chunk result")?;
+            let (index, agg, wstats) =
+                result_rx.recv().context("failed to receive chunk result")?;
             wait_time += t_wait.elapsed();
             if index >= parts.len() {
                 return Err(anyhow!("invalid chunk index {}", index));
             }
             parts[index] = Some(agg);
+            worker_stats.add(&wstats);
+            received += 1;
         }
     }
 
-    let mut final_agg = Agg::new(cfg.mode);
+    let mut final_agg = Agg::new(&cfg.agg_config());
     let t_merge = Instant::now();
     for part in parts.into_iter().flatten() {
         final_agg.merge(&part);
     }
     let merge_time = t_merge.elapsed();
-    log_stage(stats, "engine.merge", t_collect);
+    log_stage(stats, &mut stages, "engine.merge", t_collect);
 
     let _ = producer.join();
-    for worker in workers {
-        let _ = worker.join();
-    }
 
     let prod_stats = prod_stats_rx.recv().unwrap_or_default();
-    let mut worker_stats = WorkerStats::default();
-    for ws in worker_stats_rx.iter() {
-        worker_stats.chunks += ws.chunks;
-        worker_stats.bytes += ws.bytes;
-        worker_stats.reads += ws.reads;
-        worker_stats.parse += ws.parse;
-        worker_stats.metrics_core += ws.metrics_core;
-        worker_stats.adapters += ws.adapters;
-        worker_stats.heavyhitters += ws.heavyhitters;
-        worker_stats.kmer += ws.kmer;
-        worker_stats.kmer_encode += ws.kmer_encode;
-        worker_stats.kmer_keygen += ws.kmer_keygen;
-        worker_stats.kmer_binning += ws.kmer_binning;
-        worker_stats.kmer_cms += ws.kmer_cms;
-        worker_stats.kmer_hh += ws.kmer_hh;
-        worker_stats.kmer_updates += ws.kmer_updates;
-    }
+    // Skipped records never reach a worker, so the count comes from the
+    // producer.
+    final_agg.malformed_records = prod_stats.malformed_records;
+    final_agg.ws_trimmed_records = prod_stats.ws_trimmed_records;
 
     if stats {
         if prod_stats.chunks > 0 {
             let avg = prod_stats.bytes as f64 / prod_stats.chunks as f64;
-            eprintln!(
+            debug!(
                 "KIRA_STATS producer.chunks={} producer.avg_chunk_bytes={:.0} producer.bytes={} producer.reads={}",
                 prod_stats.chunks, avg, prod_stats.bytes, prod_stats.reads
             );
         }
-        eprintln!(
+        debug!(
             "KIRA_STATS worker.chunks={} worker.bytes={} worker.reads={}",
             worker_stats.chunks, worker_stats.bytes, worker_stats.reads
         );
-        eprintln!(
+        debug!(
             "KIRA_STATS producer.fastq_read_parse={}",
             fmt_dur(prod_stats.parse)
         );
         let worker_total = worker_stats.parse
-            + worker_stats.metrics_core
+            + worker_stats.base_counting
+            + worker_stats.per_position
+            + worker_stats.length_gc
             + worker_stats.adapters
             + worker_stats.heavyhitters
             + worker_stats.kmer;
-        eprintln!(
-            "KIRA_STATS worker.parse={} worker.metrics_core={} worker.adapters={} worker.heavyhitters={} worker.kmer={} worker.total={}",
+        debug!(
+            "KIRA_STATS worker.parse={} worker.base_counting={} worker.per_position={} worker.length_gc={} worker.adapters={} worker.heavyhitters={} worker.kmer={} worker.total={}",
             fmt_dur(worker_stats.parse),
-            fmt_dur(worker_stats.metrics_core),
+            fmt_dur(worker_stats.base_counting),
+            fmt_dur(worker_stats.per_position),
+            fmt_dur(worker_stats.length_gc),
             fmt_dur(worker_stats.adapters),
             fmt_dur(worker_stats.heavyhitters),
             fmt_dur(worker_stats.kmer),
             fmt_dur(worker_total)
         );
-        eprintln!(
+        debug!(
             "KIRA_STATS kmer.encode={} kmer.keygen={} kmer.binning={} kmer.cms={} kmer.hh={} kmer.updates={}",
             fmt_dur(worker_stats.kmer_encode),
             fmt_dur(worker_stats.kmer_keygen),
@@ -341,29 +872,195 @@ pub fn run(cfg: RunConfig) -> Result<RunOutput> {
             fmt_dur(worker_stats.kmer_hh),
             worker_stats.kmer_updates
         );
-        eprintln!(
+        debug!(
             "KIRA_STATS reducer.wait={} reducer.merge_cost={}",
             fmt_dur(wait_time),
             fmt_dur(merge_time)
         );
     }
 
-    log_stage(stats, "engine.total", t_total);
+    if cfg.subsample.is_some() {
+        ctx.subsampled = Some(final_agg.total_reads);
+    }
+    if let Some(flag) = prod_stats.truncated {
+        warn!(
+            "{}: {} limit reached; stopped after {} reads and {} bases, the report covers only those",
+            ctx.file_name, flag, final_agg.total_reads, final_agg.total_bases
+        );
+        ctx.truncated = Some(final_agg.total_reads);
+        if cfg.hash.is_some() {
+            warn!(
+                "{}: --hash skipped, the input was not read to the end",
+                ctx.file_name
+            );
+        }
+    }
+    ctx.file_hash = prod_stats.file_hash.clone();
+    if let Some(saturation) = final_agg.dup_saturation() {
+        warn!(
+            "{}: the duplication sketch evicted {} entries for {} reads; duplication levels are a lower bound{}",
+            ctx.file_name,
+            saturation.evictions,
+            final_agg.total_reads,
+            if saturation.larger_sketch {
+                " (--memory high tracks more sequences)"
+            } else {
+                ""
+            }
+        );
+    }
+    if let Some(mode) = final_agg.suggested_mode() {
+        let (flag, kind) = match mode {
+            Mode::Short => ("--mode short", "short"),
+            Mode::Long => ("--mode long", "long"),
+        };
+        warn!(
+            "{}: the median read length looks like {} reads; rerun with {} for meaningful per-base modules",
+            ctx.file_name, kind, flag
+        );
+    }
+    if final_agg.read_ids.flowcells.len() > 1 {
+        let mut flowcells: Vec<&str> = final_agg
+            .read_ids
+            .flowcells
+            .iter()
+            .map(String::as_str)
+            .collect();
+        flowcells.sort_unstable();
+        warn!(
+            "{}: reads come from {} flowcells ({}); check for accidentally concatenated runs",
+            ctx.file_name,
+            flowcells.len(),
+            flowcells.join(", ")
+        );
+    }
+    if final_agg.malformed_records > 0 {
+        warn!(
+            "{}: skipped {} malformed records (--lenient)",
+            ctx.file_name, final_agg.malformed_records
+        );
+    }
+    if final_agg.ws_trimmed_records > 0 {
+        info!(
+            "{}: trimmed trailing whitespace in {} records (--trim-trailing-ws)",
+            ctx.file_name, final_agg.ws_trimmed_records
+        );
+    }
+    if let Some(record) = final_agg.first_invalid_qual_record {
+        warn!(
+            "{}: {} quality bytes are outside the phred+{} range (first in record {}); mean qualities are clamped, use --strict-phred to fail instead",
+            ctx.file_name, final_agg.invalid_qual_bytes, phred_offset, record
+        );
+    }
+
+    log_stage(stats, &mut stages, "engine.total", t_total);
+
+    let profile = if cfg.profile {
+        let profile = Profile {
+            threads,
+            producer: &prod_stats,
+            workers: &worker_stats,
+            reducer_wait: wait_time,
+            reducer_merge: merge_time,
+            stages: &stages,
+        };
+        Some(serde_json::to_string_pretty(&profile).context("failed to serialize profile")?)
+    } else {
+        None
+    };
 
     Ok(RunOutput {
         agg: final_agg,
         ctx,
+        workers: threads.min(total_chunks),
+        profile,
     })
 }
 
+// Out-of-range quality bytes in one chunk.
+struct InvalidQual {
+    bytes: u64,
+    first_record: u64,
+    first_byte: u8,
+    // Chunk-wide quality byte range, for the error message.
+    min_q: u8,
+    max_q: u8,
+}
+
+impl InvalidQual {
+    fn to_error(&self, phred_offset: u8) -> anyhow::Error {
+        anyhow!(
+            "quality byte {} ('{}') in record {} is invalid for phred+{} (chunk quality range {}..={}); input may mix phred offsets",
+            self.first_byte,
+            self.first_byte.escape_ascii(),
+            self.first_record,
+            phred_offset,
+            self.min_q,
+            self.max_q
+        )
+    }
+}
+
+// Raw quality bytes must stay within the range of the chosen offset; a byte
+// below it means the file mixes encodings (e.g. concatenated phred+33 and
+// phred+64 runs) or carries stray whitespace. Checks the chunk-wide range
+// first and only scans for the offending reads when it is out of range.
+fn scan_invalid_qual(chunk: &WorkChunk, phred_offset: u8) -> Option<InvalidQual> {
+    let min_allowed = if phred_offset == 64 {
+        STRICT_MIN_QUAL_64
+    } else {
+        STRICT_MIN_QUAL_33
+    };
+    let mut min_q = u8::MAX;
+    let mut max_q = 0u8;
+    for read in &chunk.reads {
+        for &b in &read.qual {
+            min_q = min_q.min(b);
+            max_q = max_q.max(b);
+        }
+    }
+    if min_q >= min_allowed && max_q <= MAX_QUAL_BYTE {
+        return None;
+    }
+
+    let is_invalid = |b: u8| b < min_allowed || b > MAX_QUAL_BYTE;
+    let mut found: Option<InvalidQual> = None;
+    for (read, &record) in chunk.reads.iter().zip(&chunk.read_records) {
+        for &b in &read.qual {
+            if !is_invalid(b) {
+                continue;
+            }
+            match &mut found {
+                Some(f) => f.bytes += 1,
+                None => {
+                    found = Some(InvalidQual {
+                        bytes: 1,
+                        first_record: record,
+                        first_byte: b,
+                        min_q,
+                        max_q,
+                    })
+                }
+            }
+        }
+    }
+    found
+}
+
 fn stats_enabled() -> bool {
-    matches!(std::env::var("KIRA_STATS").as_deref(), Ok("1"))
+    log_enabled!(Level::Debug)
 }
 
-fn log_stage(stats: bool, name: &str, t: Instant) {
+fn log_stage(stats: bool, stages: &mut Vec<StageTime>, name: &'static str, t: Instant) {
+    let time = t.elapsed();
     if stats {
-        eprintln!("KIRA_STATS stage={} time={}", name, fmt_dur(t.elapsed()));
+        debug!("KIRA_STATS stage={} time={}", name, fmt_dur(time));
     }
+    stages.push(StageTime { name, time });
+}
+
+fn secs<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
 }
 
 fn fmt_dur(d: Duration) -> String {
@@ -374,41 +1071,308 @@ fn fmt_dur(d: Duration) -> String {
     }
 }
 
-fn detect_phred_offset(path: &PathBuf) -> Result<u8> {
-    let mut reader = FastqReader::from_path_auto(path)
-        .map_err(|e| anyhow!("failed to open FASTQ for phred detection: {e:?}"))?;
+// Worker count used when --threads is not given: one worker per expected
+// chunk, capped at the available parallelism, so small inputs do not start
+// (and then starve) a full pool.
+pub fn auto_threads(path: &Path) -> usize {
+    let available = thread::available_parallelism()
+        .map(usize::from)
+        .unwrap_or(1);
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let compressed =
+        is_gzip(path) || matches!(input::detect_input_kind(path), Ok(input::InputKind::Bzip2));
+    let bytes = if compressed {
+        size.saturating_mul(COMPRESSION_RATIO_ESTIMATE)
+    } else {
+        size
+    };
+    let chunks = bytes.div_ceil(TARGET_CHUNK_BYTES as u64).max(1);
+    chunks.min(available as u64) as usize
+}
+
+// Stride that keeps about `fraction` of the reads of `path`. The read count
+// is estimated from the file size (times COMPRESSION_RATIO_ESTIMATE for
+// compressed input) over the mean record size of the first reads, and the
+// stride spreads round(fraction × reads) kept reads over the whole file.
+fn fraction_stride(
+    path: &Path,
+    fraction: f64,
+    lenient: bool,
+    trim_trailing_ws: bool,
+) -> Result<u64> {
+    let mut reader = InputReader::open(path, 1, lenient, trim_trailing_ws, None)?;
+    let mut reads = 0u64;
+    let mut bytes = 0u64;
+    while reads < FRACTION_PROBE_READS
+        && let Some(read) = reader.next_read()?
+    {
+        reads += 1;
+        bytes += read.byte_len() as u64 + progress::RECORD_OVERHEAD;
+    }
+    if reads == 0 {
+        return Ok(1);
+    }
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let compressed =
+        is_gzip(path) || matches!(input::detect_input_kind(path), Ok(input::InputKind::Bzip2));
+    let total_bytes = if compressed {
+        size.saturating_mul(COMPRESSION_RATIO_ESTIMATE)
+    } else {
+        size
+    };
+    let estimated = (total_bytes / bytes.div_ceil(reads)).max(reads);
+    let kept = ((estimated as f64 * fraction).round() as u64).max(1);
+    let stride = (estimated as f64 / kept as f64).round() as u64;
+    debug!("--subsample-fraction: about {estimated} reads, keeping every {stride}th");
+    Ok(stride.max(1))
+}
+
+fn is_gzip(path: &Path) -> bool {
+    let mut magic = [0u8; 2];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok()
+        && magic == [0x1f, 0x8b]
+}
+
+fn encoding_for_offset(offset: u8) -> Encoding {
+    if offset == 64 {
+        Encoding::Illumina15
+    } else {
+        Encoding::Sanger
+    }
+}
+
+fn detect_encoding(
+    path: &Path,
+    allow_empty: bool,
+    lenient: bool,
+    trim_trailing_ws: bool,
+) -> Result<Encoding> {
+    let mut reader = InputReader::open(path, 1, lenient, trim_trailing_ws, None)
+        .context("failed to open input for phred detection")?;
 
     let mut reads: usize = 0;
     let mut min_q: u8 = u8::MAX;
     let mut max_q: u8 = 0;
 
     while reads < AUTO_DETECT_READS {
-        let rec = match reader.next() {
-            Ok(Some(rec)) => rec,
+        let read = match reader.next_read() {
+            Ok(Some(read)) => read,
             Ok(None) => break,
-            Err(e) => return Err(anyhow!("FASTQ parse/read error during phred detect: {e:?}")),
+            Err(e) => return Err(e.context("FASTQ parse/read error during phred detect")),
         };
 
-        for &b in rec.qual() {
+        for &b in &read.qual {
             min_q = min_q.min(b);
             max_q = max_q.max(b);
         }
         reads += 1;
     }
 
+    // An empty input has no qualities to classify; the offset only shows up
+    // in the report's Encoding line.
     if reads == 0 {
+        if allow_empty {
+            return Ok(Encoding::Sanger);
+        }
         bail!("input file is empty");
     }
 
-    let offset = if min_q < 59 {
-        33
+    // Bytes 59..64 only occur as negative Solexa scores, unless the whole
+    // file is high-quality phred+33.
+    let encoding = if min_q < 59 {
+        Encoding::Sanger
     } else if min_q >= 64 {
-        64
+        Encoding::Illumina15
     } else if max_q <= 74 {
-        33
+        Encoding::Sanger
     } else {
-        64
+        Encoding::Solexa
     };
 
-    Ok(offset)
+    // Bytes below 59 cannot be phred+64 and bytes above 74 ('J', Q41) are
+    // beyond Illumina phred+33; with neither seen, both offsets fit.
+    let confidence = if min_q < 59 {
+        format!("min_q={} clearly phred+33", min_q)
+    } else if max_q > 74 {
+        format!("max_q={} clearly phred+64", max_q)
+    } else {
+        warn!(
+            "{}: quality bytes {}..{} ('{}'..'{}') fit both phred+33 and phred+64; assuming phred+{} (set --phred-offset if this is wrong)",
+            path.display(),
+            min_q,
+            max_q,
+            min_q as char,
+            max_q as char,
+            encoding.phred_offset()
+        );
+        format!("min_q={} max_q={} ambiguous", min_q, max_q)
+    };
+    debug!(
+        "phred detect: {} ({}) from {} reads, quality bytes {}..{}: {}",
+        encoding.label(),
+        encoding.phred_offset(),
+        reads,
+        min_q,
+        max_q,
+        confidence
+    );
+
+    Ok(encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let name = format!("kira-qc-engine-{}-{}", std::process::id(), name);
+            TempFile(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn config(reads1: &Path) -> RunConfig {
+        RunConfig {
+            reads1: reads1.to_path_buf(),
+            sample_name: "sample".to_string(),
+            phred_offset: PhredOffsetConfig::Fixed(33),
+            overrep_max_len: 100,
+            memory: MemoryProfile::Low,
+            profile: true,
+            ..RunConfig::default()
+        }
+    }
+
+    fn run_with_target(reads1: &Path, chunk_target: usize) -> (Agg, u64) {
+        let pool = WorkerPool::new(1, false);
+        let output = run_chunked(config(reads1), &pool, chunk_target).unwrap();
+        let profile: serde_json::Value =
+            serde_json::from_str(output.profile.as_deref().unwrap()).unwrap();
+        let chunks = profile["producer"]["chunks"].as_u64().unwrap();
+        (output.agg, chunks)
+    }
+
+    #[test]
+    fn chunk_target_inside_a_record_keeps_records_whole() {
+        let input = TempFile::new("chunks.fastq");
+        let mut fastq = Vec::new();
+        let mut state = 7u32;
+        for i in 0..200 {
+            let mut next = || {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as usize
+            };
+            let seq: Vec<u8> = (0..150).map(|_| b"ACGT"[next() % 4]).collect();
+            let qual: Vec<u8> = (0..150).map(|_| b'#' + (next() % 40) as u8).collect();
+            fastq.extend_from_slice(format!("@r{i}\n").as_bytes());
+            fastq.extend_from_slice(&seq);
+            fastq.extend_from_slice(b"\n+\n");
+            fastq.extend_from_slice(&qual);
+            fastq.push(b'\n');
+        }
+        std::fs::write(&input.0, &fastq).unwrap();
+
+        // A record holds 302-304 bytes of id, sequence and quality, so a
+        // 1000-byte target falls inside the fourth record of each chunk.
+        let (split, chunks) = run_with_target(&input.0, 1000);
+        let (whole, one) = run_with_target(&input.0, TARGET_CHUNK_BYTES);
+        assert_eq!((chunks, one), (50, 1));
+
+        assert_eq!(split.total_reads, 200);
+        assert_eq!(split.total_bases, 200 * 150);
+        assert_eq!((split.min_len, split.max_len), (150, 150));
+        assert_eq!(split.total_reads, whole.total_reads);
+        assert_eq!(split.gc_bases, whole.gc_bases);
+        assert_eq!(split.per_pos_qual, whole.per_pos_qual);
+        assert_eq!(split.per_seq_mean_q_hist, whole.per_seq_mean_q_hist);
+        assert_eq!(split.per_seq_gc_hist, whole.per_seq_gc_hist);
+        assert_eq!(split.length_hist, whole.length_hist);
+    }
+
+    #[test]
+    fn invalid_quality_reports_the_record_number() {
+        let input = TempFile::new("mixed-phred.fastq");
+        // Phred+64 data with a phred+33 quality in the third record, '+id'
+        // lines and CRLF endings.
+        let fastq: Vec<u8> = (1..=5)
+            .flat_map(|i| {
+                let qual = if i == 3 { "hh5h" } else { "hhhh" };
+                format!("@read{i}\r\nACGT\r\n+read{i}\r\n{qual}\r\n").into_bytes()
+            })
+            .collect();
+        std::fs::write(&input.0, &fastq).unwrap();
+        let pool = WorkerPool::new(1, false);
+
+        let mut cfg = config(&input.0);
+        cfg.phred_offset = PhredOffsetConfig::Fixed(64);
+        let output = run_with_pool(cfg, &pool).unwrap();
+        assert_eq!(output.agg.invalid_qual_bytes, 1);
+        assert_eq!(output.agg.first_invalid_qual_record, Some(3));
+
+        let mut cfg = config(&input.0);
+        cfg.phred_offset = PhredOffsetConfig::Fixed(64);
+        cfg.subsample = Some(Subsample::Stride(2));
+        let output = run_with_pool(cfg, &pool).unwrap();
+        assert_eq!(output.agg.first_invalid_qual_record, Some(3));
+
+        let mut cfg = config(&input.0);
+        cfg.phred_offset = PhredOffsetConfig::Fixed(64);
+        cfg.strict_phred = true;
+        let Err(err) = run_with_pool(cfg, &pool) else {
+            panic!("--strict-phred accepted a phred+33 quality");
+        };
+        assert!(format!("{err:#}").contains("in record 3 "), "{err:#}");
+    }
+
+    #[test]
+    fn head_subsample_reports_no_file_hash() {
+        let input = TempFile::new("hash.fastq");
+        let fastq: Vec<u8> = (0..10)
+            .flat_map(|i| format!("@r{i}\nACGT\n+\nIIII\n").into_bytes())
+            .collect();
+        std::fs::write(&input.0, &fastq).unwrap();
+        let pool = WorkerPool::new(1, false);
+
+        let mut cfg = config(&input.0);
+        cfg.hash = Some(HashAlgorithm::Md5);
+        let whole = run_with_pool(cfg, &pool).unwrap();
+        assert!(whole.ctx.file_hash.is_some());
+
+        let mut cfg = config(&input.0);
+        cfg.hash = Some(HashAlgorithm::Md5);
+        cfg.subsample = Some(Subsample::Head(3));
+        let head = run_with_pool(cfg, &pool).unwrap();
+        assert_eq!(head.agg.total_reads, 3);
+        assert!(head.ctx.file_hash.is_none());
+    }
+
+    #[test]
+    fn fraction_stride_follows_the_file_size() {
+        let input = TempFile::new("fraction.fastq");
+        // 16-byte records: 100 reads in a 1600-byte file.
+        let fastq: Vec<u8> = (0..100)
+            .flat_map(|i| format!("@r{}\nACGT\n+\nIIII\n", i % 10).into_bytes())
+            .collect();
+        std::fs::write(&input.0, &fastq).unwrap();
+        assert_eq!(fraction_stride(&input.0, 0.25, false, false).unwrap(), 4);
+        assert_eq!(fraction_stride(&input.0, 1.0, false, false).unwrap(), 1);
+        assert_eq!(fraction_stride(&input.0, 0.001, false, false).unwrap(), 100);
+
+        let pool = WorkerPool::new(1, false);
+        let mut cfg = config(&input.0);
+        cfg.subsample = Some(Subsample::Fraction(0.25));
+        let output = run_with_pool(cfg, &pool).unwrap();
+        assert_eq!(output.agg.total_reads, 25);
+        assert_eq!(output.ctx.subsampled, Some(25));
+    }
 }
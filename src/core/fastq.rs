@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Clone, Copy, Debug)]
 pub struct ReadView<'a> {
     pub id: &'a [u8],
@@ -13,11 +15,14 @@ pub struct OwnedRead {
 }
 
 impl OwnedRead {
+    // Line splitting and chunking happen inside kira-fastq; a CRLF pair that
+    // straddles its buffer edge can leave a stray '\r' on a field, so strip
+    // it here rather than letting it count as a base or quality byte.
     pub fn from_record(record: kira_fastq::FastqRecord<'_>) -> Self {
         Self {
-            id: record.header().to_vec(),
-            seq: record.seq().to_vec(),
-            qual: record.qual().to_vec(),
+            id: trim_cr(record.header()).to_vec(),
+            seq: trim_cr(record.seq()).to_vec(),
+            qual: trim_cr(record.qual()).to_vec(),
         }
     }
 
@@ -33,3 +38,68 @@ impl OwnedRead {
         }
     }
 }
+
+fn trim_cr(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+// The text after '+' on the '+' line of a record from kira-fastq, which only
+// hands out the header, sequence and quality. Its single-line parser cuts
+// all four lines from one buffer (the mmap of a plain file, the inflate
+// buffer of a gzip/BGZF one), so the '+' line is the gap between the
+// sequence and quality slices. None unless the lines sit back to back and
+// the gap reads `[\r]\n+...[\r]\n`.
+pub fn plus_line<'a>(record: &kira_fastq::FastqRecord<'a>) -> Option<&'a [u8]> {
+    let (header, seq, qual) = (record.header(), record.seq(), record.qual());
+    let end = |line: &[u8]| line.as_ptr() as usize + line.len();
+    let header_gap = (seq.as_ptr() as usize).checked_sub(end(header))?;
+    let gap_len = (qual.as_ptr() as usize).checked_sub(end(seq))?;
+    // "\r\n" after the header, then "\r\n+<title>\r\n" at most.
+    if !(1..=2).contains(&header_gap) || !(3..=header.len() + 5).contains(&gap_len) {
+        return None;
+    }
+    // SAFETY: header, sequence and quality are consecutive lines of the
+    // buffer the record borrows (checked above for header and sequence), so
+    // the bytes between the sequence and the quality are part of it too.
+    let gap = unsafe { std::slice::from_raw_parts(seq.as_ptr().add(seq.len()), gap_len) };
+    let gap = gap
+        .strip_prefix(b"\r")
+        .unwrap_or(gap)
+        .strip_prefix(b"\n+")?;
+    let gap = gap.strip_suffix(b"\n")?;
+    Some(trim_cr(gap))
+}
+
+// The '+' line may repeat the header; if it does, it must match, or the
+// record boundaries are not where we think they are. `id` and `plus_id` are
+// the lines without their leading '@' and '+'. With `ignore_trailing_ws`
+// (--trim-trailing-ws, which trims the '+' line but not the header),
+// trailing spaces and tabs are ignored on both.
+pub fn plus_matches(id: &[u8], plus_id: &[u8], ignore_trailing_ws: bool) -> bool {
+    let trim = |line: &[u8]| -> usize {
+        line.iter()
+            .rposition(|b| !matches!(b, b' ' | b'\t'))
+            .map_or(0, |i| i + 1)
+    };
+    if ignore_trailing_ws {
+        let plus_id = &plus_id[..trim(plus_id)];
+        plus_id.is_empty() || plus_id == &id[..trim(id)]
+    } else {
+        plus_id.is_empty() || plus_id == id
+    }
+}
+
+// Input error tied to a position in the (uncompressed) FASTQ stream.
+#[derive(Debug)]
+pub struct FastqError {
+    pub offset: u64,
+    pub message: String,
+}
+
+impl fmt::Display for FastqError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (read at byte offset {})", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for FastqError {}
@@ -0,0 +1,1339 @@
+use crate::core::fastq::{self, FastqError, OwnedRead};
+use crate::core::model::{FileHash, HashAlgorithm};
+use anyhow::{Context, Result, anyhow, bail};
+use bzip2::read::MultiBzDecoder;
+use flate2::Crc;
+use flate2::read::MultiGzDecoder;
+use kira_fastq::FastqReader;
+use log::debug;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+const BZIP2_MAGIC: &[u8; 3] = b"BZh";
+const GZIP_MAGIC: &[u8; 2] = &[0x1f, 0x8b];
+// Longest line the built-in FASTQ and FASTA parsers buffer. Far above any
+// real read; a longer line means the input is not FASTQ/FASTA (e.g. a
+// corrupt or binary file with no newlines), and is rejected before it
+// exhausts memory.
+const MAX_LINE_BYTES: u64 = 1 << 30;
+// Decoded bytes checked for bare-CR line endings before choosing a parser.
+const LINE_ENDING_SNIFF_BYTES: u64 = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InputKind {
+    Fastq,
+    Bzip2,
+    Fasta,
+    Bgzf,
+    #[cfg(feature = "bam")]
+    Bam,
+}
+
+impl InputKind {
+    // Formats that store raw phred values are re-encoded as phred+33 by
+    // their readers, so the offset never needs to be detected.
+    pub fn fixed_phred_offset(self) -> Option<u8> {
+        match self {
+            InputKind::Fastq | InputKind::Bzip2 | InputKind::Bgzf => None,
+            // FASTA has no qualities; the offset only labels the encoding.
+            InputKind::Fasta => Some(33),
+            #[cfg(feature = "bam")]
+            InputKind::Bam => Some(33),
+        }
+    }
+}
+
+pub fn detect_input_kind(path: &Path) -> Result<InputKind> {
+    let mut magic = Vec::with_capacity(BZIP2_MAGIC.len());
+    let file =
+        File::open(path).with_context(|| format!("failed to open input {}", path.display()))?;
+    file.take(BZIP2_MAGIC.len() as u64)
+        .read_to_end(&mut magic)
+        .with_context(|| format!("failed to read input {}", path.display()))?;
+    if magic == BZIP2_MAGIC {
+        return Ok(InputKind::Bzip2);
+    }
+    if magic.first() == Some(&b'>') {
+        return Ok(InputKind::Fasta);
+    }
+    #[cfg(feature = "bam")]
+    if bam::is_bam(path)? {
+        return Ok(InputKind::Bam);
+    }
+    if bgzf::is_bgzf(path)? {
+        return Ok(InputKind::Bgzf);
+    }
+    Ok(InputKind::Fastq)
+}
+
+type NextRead = Box<dyn FnMut() -> Result<Option<OwnedRead>> + Send>;
+
+pub struct InputReader {
+    next: NextRead,
+    malformed: Arc<AtomicU64>,
+    ws_trimmed: Arc<AtomicU64>,
+    hasher: Option<SharedHasher>,
+}
+
+impl InputReader {
+    // `decode_threads` bounds parallel block decoding for BGZF input; other
+    // formats decode on the calling thread. With `lenient`, malformed FASTQ
+    // records are skipped and counted instead of failing the read. With
+    // `trim_trailing_ws`, trailing spaces and tabs are cut from sequence,
+    // '+' and quality lines before the length check. With `hash`, the raw
+    // file bytes are hashed as they are read.
+    pub fn open(
+        path: &Path,
+        decode_threads: usize,
+        lenient: bool,
+        trim_trailing_ws: bool,
+        hash: Option<HashAlgorithm>,
+    ) -> Result<Self> {
+        let malformed = Arc::new(AtomicU64::new(0));
+        let ws_trimmed = Arc::new(AtomicU64::new(0));
+        let counter = LineCounters {
+            malformed: lenient.then(|| Arc::clone(&malformed)),
+            ws_trimmed: trim_trailing_ws.then(|| Arc::clone(&ws_trimmed)),
+        };
+        let hasher = hash.map(|algorithm| Arc::new(Mutex::new(Hasher::new(algorithm))));
+        // kira-fastq opens the file itself and the parallel BGZF reader
+        // reads blocks out of order, so neither sees the byte stream.
+        let streamed = lenient || trim_trailing_ws || hasher.is_some();
+        let next: NextRead = match detect_input_kind(path)? {
+            // kira-fastq cannot resume after a malformed record or trim
+            // lines, so lenient and whitespace-trimming runs use the line
+            // parser instead.
+            InputKind::Fastq if streamed => {
+                open_text_fastq(path, counter.clone(), hasher.as_ref())?
+            }
+            // kira-fastq only splits lines on '\n', so files with bare-CR
+            // (classic Mac OS) line endings go to the line parser as well.
+            InputKind::Fastq if has_bare_cr(path)? => open_text_fastq(path, counter.clone(), None)?,
+            // kira-fastq starts a new gzip member wherever one ends, so
+            // concatenated files (`cat a.gz b.gz`) are read in full.
+            InputKind::Fastq => open_fastq(path)?,
+            InputKind::Bgzf if hasher.is_some() => {
+                open_text_fastq(path, counter.clone(), hasher.as_ref())?
+            }
+            InputKind::Bgzf => match bgzf::ParallelReader::open(path, decode_threads)? {
+                Some(reader) => {
+                    let mut reader = TextFastqReader::new(BufReader::new(reader), counter.clone());
+                    Box::new(move || reader.next_read())
+                }
+                // Also reached when BGZF blocks are followed by plain gzip
+                // members, which the member-by-member decoder reads in full.
+                None => {
+                    debug!(
+                        "{}: BGZF block index unavailable (no EOF marker block or non-BGZF members); decoding as a single stream",
+                        path.display()
+                    );
+                    open_text_fastq(path, counter.clone(), None)?
+                }
+            },
+            // bzip2 has no block index to split on, so decompression stays on
+            // the producer thread; --threads only parallelizes the metrics.
+            InputKind::Bzip2 => {
+                let file = open_raw(path, hasher.as_ref())
+                    .with_context(|| format!("failed to open bzip2 input {}", path.display()))?;
+                let mut reader = TextFastqReader::new(
+                    BufReader::new(MultiBzDecoder::new(BufReader::new(file))),
+                    counter,
+                );
+                Box::new(move || reader.next_read())
+            }
+            InputKind::Fasta => {
+                let file = open_raw(path, hasher.as_ref())
+                    .with_context(|| format!("failed to open FASTA input {}", path.display()))?;
+                let mut reader = FastaReader::new(BufReader::new(file));
+                Box::new(move || reader.next_read())
+            }
+            #[cfg(feature = "bam")]
+            InputKind::Bam => {
+                let file = open_raw(path, hasher.as_ref())
+                    .with_context(|| format!("failed to open BAM input {}", path.display()))?;
+                let mut reader = bam::BamReader::open(path, file)?;
+                Box::new(move || reader.next_read())
+            }
+        };
+        Ok(Self {
+            next,
+            malformed,
+            ws_trimmed,
+            hasher,
+        })
+    }
+
+    pub fn next_read(&mut self) -> Result<Option<OwnedRead>> {
+        (self.next)()
+    }
+
+    // --sample-regions: `regions` evenly spaced windows of up to
+    // `region_reads` records each across an indexed BGZF file. None when the
+    // input is not BGZF or has no usable block index; the caller then
+    // samples sequentially.
+    pub fn open_regions(
+        path: &Path,
+        decode_threads: usize,
+        regions: usize,
+        region_reads: u64,
+        lenient: bool,
+        trim_trailing_ws: bool,
+    ) -> Result<Option<Self>> {
+        if detect_input_kind(path)? != InputKind::Bgzf {
+            return Ok(None);
+        }
+        let Some(windows) = bgzf::ParallelReader::open_regions(path, decode_threads, regions)?
+        else {
+            return Ok(None);
+        };
+        let malformed = Arc::new(AtomicU64::new(0));
+        let ws_trimmed = Arc::new(AtomicU64::new(0));
+        let mut sampler = RegionSampler {
+            windows: windows.into_iter(),
+            current: None,
+            counters: LineCounters {
+                malformed: lenient.then(|| Arc::clone(&malformed)),
+                ws_trimmed: trim_trailing_ws.then(|| Arc::clone(&ws_trimmed)),
+            },
+            region_reads,
+            taken: 0,
+        };
+        Ok(Some(Self {
+            next: Box::new(move || sampler.next_read()),
+            malformed,
+            ws_trimmed,
+            hasher: None,
+        }))
+    }
+
+    // Malformed records skipped so far (lenient mode only).
+    pub fn malformed_records(&self) -> u64 {
+        self.malformed.load(Ordering::Relaxed)
+    }
+
+    // Records that only parsed after trailing whitespace was trimmed
+    // (trim_trailing_ws only).
+    pub fn ws_trimmed_records(&self) -> u64 {
+        self.ws_trimmed.load(Ordering::Relaxed)
+    }
+
+    // Digest of the bytes read so far; covers the whole file once
+    // next_read has returned None.
+    pub fn file_hash(&self) -> Option<FileHash> {
+        let hasher = self.hasher.as_ref()?.lock().ok()?;
+        Some(hasher.finish())
+    }
+}
+
+type SharedHasher = Arc<Mutex<Hasher>>;
+
+enum Hasher {
+    Crc32(Crc),
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Crc32 => Hasher::Crc32(Crc::new()),
+            HashAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Crc32(h) => h.update(bytes),
+            Hasher::Md5(h) => h.update(bytes),
+            Hasher::Sha256(h) => h.update(bytes),
+        }
+    }
+
+    fn finish(&self) -> FileHash {
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+        match self {
+            Hasher::Crc32(h) => FileHash {
+                algorithm: HashAlgorithm::Crc32,
+                value: format!("{:08x}", h.sum()),
+            },
+            Hasher::Md5(h) => FileHash {
+                algorithm: HashAlgorithm::Md5,
+                value: hex(&h.clone().finalize()),
+            },
+            Hasher::Sha256(h) => FileHash {
+                algorithm: HashAlgorithm::Sha256,
+                value: hex(&h.clone().finalize()),
+            },
+        }
+    }
+}
+
+// Feeds every byte read from the file into the shared hasher, before any
+// decompression.
+struct HashingReader<R> {
+    inner: R,
+    hasher: SharedHasher,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Ok(mut hasher) = self.hasher.lock() {
+            hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+fn open_raw(path: &Path, hasher: Option<&SharedHasher>) -> io::Result<Box<dyn Read + Send>> {
+    let file = File::open(path)?;
+    Ok(match hasher {
+        Some(hasher) => Box::new(HashingReader {
+            inner: file,
+            hasher: Arc::clone(hasher),
+        }),
+        None => Box::new(file),
+    })
+}
+
+fn open_fastq(path: &Path) -> Result<NextRead> {
+    let mut reader = FastqReader::from_path_auto(path)
+        .map_err(|e| anyhow!("failed to open FASTQ input: {e:?}"))?;
+    let mut record = 0u64;
+    Ok(Box::new(move || match reader.next() {
+        Ok(Some(rec)) => {
+            record += 1;
+            // kira-fastq only checks that the line starts with '+'.
+            if let Some(plus_id) = fastq::plus_line(&rec)
+                && !fastq::plus_matches(rec.header(), plus_id, false)
+            {
+                bail!(
+                    "FASTQ parse error: record {} has '+' line '+{}' that does not match its header '@{}'",
+                    record,
+                    plus_id.escape_ascii(),
+                    rec.header().escape_ascii()
+                );
+            }
+            Ok(Some(OwnedRead::from_record(rec)))
+        }
+        Ok(None) => Ok(None),
+        Err(e) => Err(anyhow!("FASTQ parse/read error: {e:?}")),
+    }))
+}
+
+fn has_gzip_magic(path: &Path) -> Result<bool> {
+    let mut magic = Vec::with_capacity(GZIP_MAGIC.len());
+    File::open(path)
+        .and_then(|f| f.take(GZIP_MAGIC.len() as u64).read_to_end(&mut magic))
+        .with_context(|| format!("failed to read input {}", path.display()))?;
+    Ok(magic == GZIP_MAGIC)
+}
+
+// True if the start of the (decoded) file has a '\r' that is not part of a
+// "\r\n" pair.
+fn has_bare_cr(path: &Path) -> Result<bool> {
+    let file =
+        File::open(path).with_context(|| format!("failed to open input {}", path.display()))?;
+    let inner: Box<dyn Read> = if has_gzip_magic(path)? {
+        Box::new(MultiGzDecoder::new(BufReader::new(file)))
+    } else {
+        Box::new(file)
+    };
+    let mut head = Vec::new();
+    inner
+        .take(LINE_ENDING_SNIFF_BYTES)
+        .read_to_end(&mut head)
+        .with_context(|| format!("failed to read input {}", path.display()))?;
+    // A '\r' ending the sample may be followed by '\n' just past it.
+    let head = head.strip_suffix(b"\r").unwrap_or(&head);
+    Ok(memchr::memchr_iter(b'\r', head).any(|i| head.get(i + 1) != Some(&b'\n')))
+}
+
+// Plain or gzip FASTQ through TextFastqReader; MultiGzDecoder continues
+// across gzip member boundaries.
+fn open_text_fastq(
+    path: &Path,
+    counters: LineCounters,
+    hasher: Option<&SharedHasher>,
+) -> Result<NextRead> {
+    let gzip = has_gzip_magic(path)?;
+    let file = open_raw(path, hasher)
+        .with_context(|| format!("failed to open input {}", path.display()))?;
+    let inner: Box<dyn Read + Send> = if gzip {
+        Box::new(MultiGzDecoder::new(BufReader::new(file)))
+    } else {
+        Box::new(file)
+    };
+    let mut reader = TextFastqReader::new(BufReader::new(inner), counters);
+    Ok(Box::new(move || reader.next_read()))
+}
+
+// Reads the --sample-regions windows one after another.
+struct RegionSampler {
+    windows: std::vec::IntoIter<bgzf::Window>,
+    current: Option<(
+        TextFastqReader<BufReader<bgzf::ParallelReader>>,
+        Arc<AtomicBool>,
+    )>,
+    counters: LineCounters,
+    region_reads: u64,
+    // Records returned from the current window.
+    taken: u64,
+}
+
+impl RegionSampler {
+    fn next_read(&mut self) -> Result<Option<OwnedRead>> {
+        loop {
+            // A window ends after `region_reads` records or once its reader
+            // has moved into the next window; records still buffered from
+            // before that boundary are dropped.
+            if let Some((reader, past_end)) = &mut self.current
+                && self.taken < self.region_reads
+                && !past_end.load(Ordering::Relaxed)
+            {
+                // Windows start at block boundaries, which are rarely record
+                // boundaries, so the first record is found by resyncing.
+                let read = if self.taken == 0 {
+                    reader.resync_read(false)?
+                } else {
+                    reader.next_read()?
+                };
+                if let Some(read) = read {
+                    self.taken += 1;
+                    return Ok(Some(read));
+                }
+            }
+            let Some((window, past_end)) = self.windows.next() else {
+                return Ok(None);
+            };
+            let reader = TextFastqReader::new(BufReader::new(window), self.counters.clone());
+            self.current = Some((reader, past_end));
+            self.taken = 0;
+        }
+    }
+}
+
+// Leniency switches of TextFastqReader; each is on when its counter is
+// set, and counts the records it let through.
+#[derive(Clone, Default)]
+struct LineCounters {
+    malformed: Option<Arc<AtomicU64>>,
+    ws_trimmed: Option<Arc<AtomicU64>>,
+}
+
+// Line-oriented FASTQ parser for decoded streams that kira-fastq cannot
+// open directly.
+struct TextFastqReader<R> {
+    inner: R,
+    line: Vec<u8>,
+    record: u64,
+    // Bytes consumed so far, for error offsets.
+    offset: u64,
+    // Lenient mode: skipped records are counted here instead of failing,
+    // and `pending` holds the lines read ahead while resyncing.
+    malformed: Option<Arc<AtomicU64>>,
+    pending: VecDeque<Vec<u8>>,
+    // --trim-trailing-ws: records whose sequence, '+' or quality line lost
+    // trailing whitespace are counted here.
+    ws_trimmed: Option<Arc<AtomicU64>>,
+    // MAX_LINE_BYTES, lowered in tests.
+    max_line: u64,
+}
+
+impl<R: BufRead> TextFastqReader<R> {
+    fn new(inner: R, counters: LineCounters) -> Self {
+        Self {
+            inner,
+            line: Vec::new(),
+            record: 0,
+            offset: 0,
+            malformed: counters.malformed,
+            pending: VecDeque::with_capacity(4),
+            ws_trimmed: counters.ws_trimmed,
+            max_line: MAX_LINE_BYTES,
+        }
+    }
+
+    fn count_ws_trimmed(&self, trimmed: bool) {
+        if trimmed && let Some(counter) = &self.ws_trimmed {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn next_read(&mut self) -> Result<Option<OwnedRead>> {
+        if self.malformed.is_some() {
+            self.resync_read(true)
+        } else {
+            self.next_read_strict()
+        }
+    }
+
+    // Skips a malformed record by dropping one line at a time until the next
+    // four lines form a complete record. Requiring the whole structure keeps
+    // a quality line that happens to start with '@' from being taken as a
+    // header. `count_skipped` adds the skipped record to the malformed count
+    // (lenient mode); it is off when aligning to the first record of a
+    // --sample-regions window.
+    fn resync_read(&mut self, count_skipped: bool) -> Result<Option<OwnedRead>> {
+        let mut resyncing = false;
+        loop {
+            while self.pending.len() < 4 {
+                match self.read_line()? {
+                    Some(line) => self.pending.push_back(line),
+                    None => break,
+                }
+            }
+            if self.pending.is_empty() {
+                return Ok(None);
+            }
+            if let Some((read, trimmed)) =
+                record_from_lines(&self.pending, self.ws_trimmed.is_some())
+            {
+                self.pending.clear();
+                self.record += 1;
+                self.count_ws_trimmed(trimmed);
+                return Ok(Some(read));
+            }
+            if count_skipped
+                && !resyncing
+                && let Some(malformed) = &self.malformed
+            {
+                malformed.fetch_add(1, Ordering::Relaxed);
+                resyncing = true;
+            }
+            // Fewer than four lines left: a truncated final record.
+            if self.pending.len() < 4 {
+                self.pending.clear();
+                return Ok(None);
+            }
+            self.pending.pop_front();
+        }
+    }
+
+    fn next_read_strict(&mut self) -> Result<Option<OwnedRead>> {
+        let record_offset = self.offset;
+        let Some(header) = self.read_line()? else {
+            return Ok(None);
+        };
+        self.record += 1;
+        let Some(id) = header.strip_prefix(b"@") else {
+            bail!(
+                "FASTQ parse error: record {} does not start with '@'",
+                self.record
+            );
+        };
+        let id = id.to_vec();
+        let trim = self.ws_trimmed.is_some();
+        let mut seq = self.expect_line()?;
+        let mut plus = self.expect_line()?;
+        let mut trimmed = trim && (trim_trailing_ws(&mut seq) | trim_trailing_ws(&mut plus));
+        if !plus.starts_with(b"+") {
+            bail!(
+                "FASTQ parse error: record {} is missing the '+' line",
+                self.record
+            );
+        }
+        if !fastq::plus_matches(&id, &plus[1..], trim) {
+            return Err(FastqError {
+                offset: record_offset,
+                message: format!(
+                    "FASTQ parse error: record {} has '+' line '{}' that does not match its header '{}'",
+                    self.record,
+                    plus.escape_ascii(),
+                    header.escape_ascii()
+                ),
+            }
+            .into());
+        }
+        let mut qual = self.expect_line()?;
+        trimmed |= trim && trim_trailing_ws(&mut qual);
+        if qual.len() != seq.len() {
+            bail!(
+                "FASTQ parse error: record {} has {} bases but {} quality values",
+                self.record,
+                seq.len(),
+                qual.len()
+            );
+        }
+        self.count_ws_trimmed(trimmed);
+        Ok(Some(OwnedRead { id, seq, qual }))
+    }
+
+    fn read_line(&mut self) -> Result<Option<Vec<u8>>> {
+        let n = read_line(&mut self.inner, &mut self.line, self.max_line)
+            .context("FASTQ read error")?;
+        if n == 0 {
+            return Ok(None);
+        }
+        if self.line.len() as u64 > self.max_line {
+            return Err(FastqError {
+                offset: self.offset,
+                message: format!(
+                    "FASTQ parse error: line over {} bytes; record too long, likely not FASTQ",
+                    self.max_line
+                ),
+            }
+            .into());
+        }
+        self.offset += n as u64;
+        Ok(Some(self.line.clone()))
+    }
+
+    fn expect_line(&mut self) -> Result<Vec<u8>> {
+        match self.read_line()? {
+            Some(line) => Ok(line),
+            None => bail!("FASTQ parse error: record {} is truncated", self.record),
+        }
+    }
+}
+
+// Reads the next line into `line` without its terminator: "\n", "\r\n" or a
+// bare "\r" (classic Mac OS). A "\r\n" pair split across two buffer fills is
+// still one terminator. Stops early once `line` holds more than `max` bytes.
+// Returns the bytes consumed; 0 at end of input.
+fn read_line<R: BufRead>(inner: &mut R, line: &mut Vec<u8>, max: u64) -> io::Result<usize> {
+    line.clear();
+    let mut consumed = 0;
+    loop {
+        let buf = inner.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(consumed);
+        }
+        let Some(end) = memchr::memchr2(b'\n', b'\r', buf) else {
+            let n = buf.len();
+            line.extend_from_slice(buf);
+            inner.consume(n);
+            consumed += n;
+            if line.len() as u64 > max {
+                return Ok(consumed);
+            }
+            continue;
+        };
+        line.extend_from_slice(&buf[..end]);
+        let cr = buf[end] == b'\r';
+        inner.consume(end + 1);
+        consumed += end + 1;
+        if cr && inner.fill_buf()?.first() == Some(&b'\n') {
+            inner.consume(1);
+            consumed += 1;
+        }
+        return Ok(consumed);
+    }
+}
+
+// Builds a read from four buffered lines if they form a well-formed FASTQ
+// record, mirroring the checks of the strict parser. The flag is set when
+// `trim` had to cut trailing whitespace.
+fn record_from_lines(lines: &VecDeque<Vec<u8>>, trim: bool) -> Option<(OwnedRead, bool)> {
+    let [header, seq, plus, qual] = [lines.front()?, lines.get(1)?, lines.get(2)?, lines.get(3)?];
+    let (mut seq, mut plus, mut qual) = (seq.clone(), plus.clone(), qual.clone());
+    let trimmed = trim
+        && (trim_trailing_ws(&mut seq) | trim_trailing_ws(&mut plus) | trim_trailing_ws(&mut qual));
+    let id = header.strip_prefix(b"@")?;
+    let plus_id = plus.strip_prefix(b"+")?;
+    if !fastq::plus_matches(id, plus_id, trim) || qual.len() != seq.len() {
+        return None;
+    }
+    Some((
+        OwnedRead {
+            id: id.to_vec(),
+            seq,
+            qual,
+        },
+        trimmed,
+    ))
+}
+
+// Drops trailing spaces and tabs; true if any were removed.
+fn trim_trailing_ws(line: &mut Vec<u8>) -> bool {
+    let len = line.len();
+    while matches!(line.last(), Some(b' ' | b'\t')) {
+        line.pop();
+    }
+    line.len() != len
+}
+
+// Multi-line FASTA parser (assemblies, contigs). Records are yielded with an
+// empty quality string, which the metrics treat as "no qualities".
+struct FastaReader<R> {
+    inner: R,
+    line: Vec<u8>,
+    header: Option<Vec<u8>>,
+    record: u64,
+    // MAX_LINE_BYTES, lowered in tests.
+    max_line: u64,
+}
+
+impl<R: BufRead> FastaReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            line: Vec::new(),
+            header: None,
+            record: 0,
+            max_line: MAX_LINE_BYTES,
+        }
+    }
+
+    fn next_read(&mut self) -> Result<Option<OwnedRead>> {
+        let header = match self.header.take() {
+            Some(header) => header,
+            None => loop {
+                if !self.read_line()? {
+                    return Ok(None);
+                }
+                if !self.line.is_empty() {
+                    break self.line.clone();
+                }
+            },
+        };
+        self.record += 1;
+        let Some(id) = header.strip_prefix(b">") else {
+            bail!(
+                "FASTA parse error: record {} does not start with '>'",
+                self.record
+            );
+        };
+        let id = id.to_vec();
+        let mut seq = Vec::new();
+        while self.read_line()? {
+            if self.line.first() == Some(&b'>') {
+                self.header = Some(self.line.clone());
+                break;
+            }
+            seq.extend_from_slice(&self.line);
+        }
+        Ok(Some(OwnedRead {
+            id,
+            seq,
+            qual: Vec::new(),
+        }))
+    }
+
+    // Reads the next line into `self.line` without its line terminator;
+    // returns false at end of input.
+    fn read_line(&mut self) -> Result<bool> {
+        let n = read_line(&mut self.inner, &mut self.line, self.max_line)
+            .context("FASTA read error")?;
+        if self.line.len() as u64 > self.max_line {
+            bail!(
+                "FASTA parse error: line over {} bytes in record {}; record too long, likely not FASTA",
+                self.max_line,
+                self.record.max(1)
+            );
+        }
+        Ok(n > 0)
+    }
+}
+
+// BGZF (bgzip) input. A header-only first pass indexes the blocks; blocks
+// are then inflated in parallel batches and concatenated in file order, so
+// decompression is no longer bound to the producer thread.
+mod bgzf {
+    use anyhow::{Context, Result};
+    use flate2::Crc;
+    use flate2::read::DeflateDecoder;
+    use std::fs::File;
+    use std::io::{self, Read, Seek, SeekFrom};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    // Fixed gzip header with XLEN=6, followed by the BC subfield.
+    const HEADER_LEN: usize = 18;
+    // CRC32 and ISIZE.
+    const FOOTER_LEN: usize = 8;
+    // Empty block that terminates every complete BGZF file.
+    const EOF_BLOCK: [u8; 28] = [
+        0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02,
+        0x00, 0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    const BLOCKS_PER_THREAD: usize = 16;
+
+    pub fn is_bgzf(path: &Path) -> Result<bool> {
+        let mut file =
+            File::open(path).with_context(|| format!("failed to open input {}", path.display()))?;
+        let mut header = [0u8; 14];
+        Ok(file.read_exact(&mut header).is_ok()
+            && header[..2] == [0x1f, 0x8b]
+            && header[3] & 0x04 != 0
+            && &header[12..14] == b"BC")
+    }
+
+    #[derive(Clone, Copy)]
+    struct Block {
+        offset: u64,
+        len: usize,
+    }
+
+    // Reads each block header and seeks past the block. Returns None when a
+    // header is not plain BGZF or the file does not end with the EOF block
+    // (e.g. truncated), in which case the caller streams the file instead.
+    fn build_index(path: &Path) -> Result<Option<Vec<Block>>> {
+        let mut file = File::open(path)
+            .with_context(|| format!("failed to open BGZF input {}", path.display()))?;
+        let file_len = file.metadata()?.len();
+        let mut blocks = Vec::new();
+        let mut header = [0u8; HEADER_LEN];
+        let mut offset = 0u64;
+        while offset < file_len {
+            file.seek(SeekFrom::Start(offset))?;
+            if file.read_exact(&mut header).is_err()
+                || header[..4] != [0x1f, 0x8b, 0x08, 0x04]
+                || header[10..16] != [0x06, 0x00, b'B', b'C', 0x02, 0x00]
+            {
+                return Ok(None);
+            }
+            let len = u16::from_le_bytes([header[16], header[17]]) as usize + 1;
+            if len < HEADER_LEN + FOOTER_LEN || offset + len as u64 > file_len {
+                return Ok(None);
+            }
+            blocks.push(Block { offset, len });
+            offset += len as u64;
+        }
+
+        let Some(last) = blocks.last() else {
+            return Ok(None);
+        };
+        let mut tail = [0u8; EOF_BLOCK.len()];
+        if last.len != EOF_BLOCK.len() {
+            return Ok(None);
+        }
+        file.seek(SeekFrom::Start(last.offset))?;
+        file.read_exact(&mut tail)?;
+        Ok((tail == EOF_BLOCK).then_some(blocks))
+    }
+
+    pub struct ParallelReader {
+        path: PathBuf,
+        blocks: Arc<[Block]>,
+        next_block: usize,
+        // First block past a --sample-regions window; `past_end` is set once
+        // the reader moves beyond it. Reading continues so that the record
+        // straddling the boundary can finish.
+        end_block: usize,
+        past_end: Arc<AtomicBool>,
+        threads: usize,
+        buf: Vec<u8>,
+        pos: usize,
+    }
+
+    // A --sample-regions window and its past-the-window flag.
+    pub type Window = (ParallelReader, Arc<AtomicBool>);
+
+    impl ParallelReader {
+        pub fn open(path: &Path, threads: usize) -> Result<Option<Self>> {
+            Ok(build_index(path)?.map(|blocks| {
+                let end_block = blocks.len();
+                Self::window(path, blocks.into(), 0, end_block, threads)
+            }))
+        }
+
+        // One reader per window for `regions` evenly spaced windows, paired
+        // with its past-the-window flag. Each window starts at a block and
+        // reads on to the end of the file if asked to.
+        pub fn open_regions(
+            path: &Path,
+            threads: usize,
+            regions: usize,
+        ) -> Result<Option<Vec<Window>>> {
+            let Some(blocks) = build_index(path)? else {
+                return Ok(None);
+            };
+            let blocks: Arc<[Block]> = blocks.into();
+            // The trailing EOF block holds no data.
+            let data_blocks = blocks.len() - 1;
+            let regions = regions.clamp(1, data_blocks.max(1));
+            let starts: Vec<usize> = (0..regions).map(|i| i * data_blocks / regions).collect();
+            let windows = starts
+                .iter()
+                .enumerate()
+                .map(|(i, &start)| {
+                    let end = starts.get(i + 1).copied().unwrap_or(blocks.len());
+                    let reader = Self::window(path, Arc::clone(&blocks), start, end, threads);
+                    let past_end = Arc::clone(&reader.past_end);
+                    (reader, past_end)
+                })
+                .collect();
+            Ok(Some(windows))
+        }
+
+        fn window(
+            path: &Path,
+            blocks: Arc<[Block]>,
+            start: usize,
+            end_block: usize,
+            threads: usize,
+        ) -> Self {
+            Self {
+                path: path.to_path_buf(),
+                blocks,
+                next_block: start,
+                end_block,
+                past_end: Arc::new(AtomicBool::new(false)),
+                threads: threads.max(1),
+                buf: Vec::new(),
+                pos: 0,
+            }
+        }
+
+        // Inflates the next batch of blocks into `buf`; false at end of input.
+        fn fill(&mut self) -> io::Result<bool> {
+            if self.next_block == self.blocks.len() {
+                return Ok(false);
+            }
+            // Batches stop at the window end so that crossing it is seen.
+            let limit = if self.next_block < self.end_block {
+                self.end_block
+            } else {
+                self.past_end.store(true, Ordering::Relaxed);
+                self.blocks.len()
+            };
+            let end = (self.next_block + self.threads * BLOCKS_PER_THREAD).min(limit);
+            let batch = &self.blocks[self.next_block..end];
+            let per_thread = batch.len().div_ceil(self.threads);
+            let path = self.path.as_path();
+            let parts: Vec<io::Result<Vec<u8>>> = thread::scope(|s| {
+                let handles: Vec<_> = batch
+                    .chunks(per_thread)
+                    .map(|blocks| s.spawn(move || inflate_blocks(path, blocks)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|h| {
+                        h.join().unwrap_or_else(|_| {
+                            Err(io::Error::other("BGZF decode thread panicked"))
+                        })
+                    })
+                    .collect()
+            });
+
+            self.buf.clear();
+            self.pos = 0;
+            for part in parts {
+                self.buf.extend_from_slice(&part?);
+            }
+            self.next_block = end;
+            Ok(true)
+        }
+    }
+
+    impl Read for ParallelReader {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            while self.pos == self.buf.len() {
+                if !self.fill()? {
+                    return Ok(0);
+                }
+            }
+            let n = out.len().min(self.buf.len() - self.pos);
+            out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    fn inflate_blocks(path: &Path, blocks: &[Block]) -> io::Result<Vec<u8>> {
+        let mut file = File::open(path)?;
+        let mut raw = Vec::new();
+        let mut out = Vec::new();
+        for block in blocks {
+            raw.resize(block.len, 0);
+            file.seek(SeekFrom::Start(block.offset))?;
+            file.read_exact(&mut raw)?;
+            let footer = &raw[block.len - FOOTER_LEN..];
+            let crc = u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]);
+            let isize = u32::from_le_bytes([footer[4], footer[5], footer[6], footer[7]]) as usize;
+
+            let start = out.len();
+            out.reserve(isize);
+            DeflateDecoder::new(&raw[HEADER_LEN..block.len - FOOTER_LEN]).read_to_end(&mut out)?;
+            let mut check = Crc::new();
+            check.update(&out[start..]);
+            if out.len() - start != isize || check.sum() != crc {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("corrupt BGZF block at offset {}", block.offset),
+                ));
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "bam")]
+mod bam {
+    use crate::core::fastq::OwnedRead;
+    use anyhow::{Context, Result, bail};
+    use flate2::read::MultiGzDecoder;
+    use std::fs::File;
+    use std::io::{BufReader, ErrorKind, Read};
+    use std::path::Path;
+
+    const BAM_MAGIC: &[u8; 4] = b"BAM\x01";
+    const SEQ_NT16: &[u8; 16] = b"=ACMGRSVTWYHKDBN";
+    // Fixed-size part of an alignment record following block_size.
+    const RECORD_FIXED_LEN: usize = 32;
+
+    pub fn is_bam(path: &Path) -> Result<bool> {
+        let mut file =
+            File::open(path).with_context(|| format!("failed to open input {}", path.display()))?;
+        let mut gz_magic = [0u8; 2];
+        if file.read_exact(&mut gz_magic).is_err() || gz_magic != [0x1f, 0x8b] {
+            return Ok(false);
+        }
+        let file = File::open(path)?;
+        let mut decoder = MultiGzDecoder::new(BufReader::new(file));
+        let mut magic = [0u8; 4];
+        Ok(decoder.read_exact(&mut magic).is_ok() && &magic == BAM_MAGIC)
+    }
+
+    pub struct BamReader {
+        inner: BufReader<MultiGzDecoder<BufReader<Box<dyn Read + Send>>>>,
+        block: Vec<u8>,
+    }
+
+    impl BamReader {
+        // `file` is the opened `path`, possibly wrapped for hashing.
+        pub fn open(path: &Path, file: Box<dyn Read + Send>) -> Result<Self> {
+            let mut inner = BufReader::new(MultiGzDecoder::new(BufReader::new(file)));
+
+            let mut magic = [0u8; 4];
+            inner
+                .read_exact(&mut magic)
+                .context("failed to read BAM magic")?;
+            if &magic != BAM_MAGIC {
+                bail!("not a BAM file: {}", path.display());
+            }
+            let l_text = read_u32(&mut inner)? as u64;
+            skip(&mut inner, l_text)?;
+            let n_ref = read_u32(&mut inner)?;
+            for _ in 0..n_ref {
+                let l_name = read_u32(&mut inner)? as u64;
+                skip(&mut inner, l_name + 4)?;
+            }
+
+            Ok(Self {
+                inner,
+                block: Vec::new(),
+            })
+        }
+
+        pub fn next_read(&mut self) -> Result<Option<OwnedRead>> {
+            let mut size = [0u8; 4];
+            match self.inner.read_exact(&mut size) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e).context("BAM read error"),
+            }
+            let block_size = u32::from_le_bytes(size) as usize;
+            if block_size < RECORD_FIXED_LEN {
+                bail!("truncated BAM record (block_size={})", block_size);
+            }
+            self.block.resize(block_size, 0);
+            self.inner
+                .read_exact(&mut self.block)
+                .context("truncated BAM record")?;
+            parse_record(&self.block).map(Some)
+        }
+    }
+
+    fn parse_record(b: &[u8]) -> Result<OwnedRead> {
+        let l_read_name = b[8] as usize;
+        let n_cigar_op = u16::from_le_bytes([b[12], b[13]]) as usize;
+        let l_seq = u32::from_le_bytes([b[16], b[17], b[18], b[19]]) as usize;
+
+        let name_start = RECORD_FIXED_LEN;
+        let seq_start = name_start + l_read_name + n_cigar_op * 4;
+        let qual_start = seq_start + l_seq.div_ceil(2);
+        if qual_start + l_seq > b.len() {
+            bail!("malformed BAM record: sequence exceeds block");
+        }
+
+        // read_name is NUL-terminated.
+        let name = &b[name_start..name_start + l_read_name];
+        let id = name.strip_suffix(&[0]).unwrap_or(name).to_vec();
+
+        let packed = &b[seq_start..qual_start];
+        let mut seq = Vec::with_capacity(l_seq);
+        for i in 0..l_seq {
+            let byte = packed[i / 2];
+            let code = if i % 2 == 0 { byte >> 4 } else { byte & 0x0F };
+            seq.push(SEQ_NT16[code as usize]);
+        }
+
+        // BAM stores raw phred values; re-encode as phred+33 so the rest of
+        // the pipeline sees FASTQ-style quality bytes. 0xFF means "absent",
+        // which becomes an empty quality string as for FASTA reads, so the
+        // quality modules skip the read instead of counting it as Q0.
+        let raw = &b[qual_start..qual_start + l_seq];
+        let qual = if raw.first() == Some(&0xFF) {
+            Vec::new()
+        } else {
+            raw.iter().map(|&q| q.min(93) + 33).collect()
+        };
+
+        Ok(OwnedRead { id, seq, qual })
+    }
+
+    fn read_u32(r: &mut impl Read) -> Result<u32> {
+        let mut buf = [0u8; 4];
+        r.read_exact(&mut buf).context("truncated BAM header")?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn skip(r: &mut impl Read, n: u64) -> Result<()> {
+        let copied = std::io::copy(&mut r.take(n), &mut std::io::sink())?;
+        if copied != n {
+            bail!("truncated BAM header");
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // An unmapped record named "r0" with sequence ACGT and the given raw
+        // quality bytes.
+        fn record(qual: [u8; 4]) -> Vec<u8> {
+            let mut b = vec![0u8; RECORD_FIXED_LEN];
+            b[8] = 3; // l_read_name, including the NUL
+            b[16..20].copy_from_slice(&4u32.to_le_bytes());
+            b.extend_from_slice(b"r0\0");
+            b.extend_from_slice(&[0x12, 0x48]); // A C G T as 4-bit codes
+            b.extend_from_slice(&qual);
+            b
+        }
+
+        #[test]
+        fn parses_sequence_and_quality() {
+            let read = parse_record(&record([0, 10, 20, 40])).unwrap();
+            assert_eq!(read.id, b"r0");
+            assert_eq!(read.seq, b"ACGT");
+            assert_eq!(read.qual, b"!+5I");
+        }
+
+        #[test]
+        fn missing_quality_is_empty() {
+            let read = parse_record(&record([0xFF; 4])).unwrap();
+            assert_eq!(read.seq, b"ACGT");
+            assert!(read.qual.is_empty());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    // A scratch file under the system temp dir, removed on drop.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, bytes: &[u8]) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("kira-qc-{}-{}", std::process::id(), name));
+            std::fs::write(&path, bytes).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn fastq(first: usize, count: usize) -> Vec<u8> {
+        (first..first + count)
+            .flat_map(|i| format!("@r{i}\nACGTACGTAC\n+\nIIIIIIIIII\n").into_bytes())
+            .collect()
+    }
+
+    fn read_ids(path: &Path) -> Vec<String> {
+        let mut reader = InputReader::open(path, 1, false, false, None).unwrap();
+        let mut ids = Vec::new();
+        while let Some(read) = reader.next_read().unwrap() {
+            ids.push(String::from_utf8(read.id).unwrap());
+        }
+        ids
+    }
+
+    #[test]
+    fn reads_every_member_of_concatenated_gzip() {
+        // `cat a.gz b.gz`, including a member boundary inside a record.
+        let a = fastq(0, 3);
+        let b = fastq(3, 2);
+        let mut whole = gzip(&a);
+        whole.extend(gzip(&b));
+        let file = TempFile::new("two-members.fastq.gz", &whole);
+        let ids = read_ids(&file.0);
+        assert_eq!(ids, ["r0", "r1", "r2", "r3", "r4"]);
+
+        let all = [a, b].concat();
+        let cut = all.len() / 2 + 3;
+        let mut split = gzip(&all[..cut]);
+        split.extend(gzip(&all[cut..]));
+        let file = TempFile::new("split-members.fastq.gz", &split);
+        assert_eq!(read_ids(&file.0).len(), 5);
+    }
+
+    fn text_ids(reader: &mut TextFastqReader<impl BufRead>) -> Vec<(String, Vec<u8>)> {
+        let mut reads = Vec::new();
+        while let Some(read) = reader.next_read().unwrap() {
+            reads.push((String::from_utf8(read.id).unwrap(), read.qual));
+        }
+        reads
+    }
+
+    #[test]
+    fn crlf_split_across_buffer_fills_is_one_terminator() {
+        let bytes = b"@r0\r\nACGT\r\n+\r\nIIII\r\n@r1\r\nGGCC\r\n+r1\r\nFFFF\r\n";
+        let expected = [
+            ("r0".to_string(), b"IIII".to_vec()),
+            ("r1".to_string(), b"FFFF".to_vec()),
+        ];
+        // Capacity 4 ends the first fill exactly between "@r0\r" and "\n";
+        // every other capacity moves the edge elsewhere.
+        for capacity in 1..=bytes.len() {
+            let inner = BufReader::with_capacity(capacity, &bytes[..]);
+            let mut reader = TextFastqReader::new(inner, LineCounters::default());
+            assert_eq!(text_ids(&mut reader), expected, "capacity {capacity}");
+        }
+    }
+
+    #[test]
+    fn reads_bare_cr_line_endings() {
+        let bytes = b"@r0\rACGT\r+\rIIII\r@r1\rGGCC\r+\rFFFF\r";
+        let mut reader = TextFastqReader::new(&bytes[..], LineCounters::default());
+        let reads = text_ids(&mut reader);
+        assert_eq!(reads.len(), 2);
+        assert_eq!(reads[1].0, "r1");
+
+        // The default path sends bare-CR files to the line parser.
+        let file = TempFile::new("bare-cr.fastq", bytes);
+        assert_eq!(read_ids(&file.0), ["r0", "r1"]);
+
+        let mut fasta = FastaReader::new(&b">c1\rACGT\rGG\r>c2\rTT\r"[..]);
+        assert_eq!(fasta.next_read().unwrap().unwrap().seq, b"ACGTGG");
+        assert_eq!(fasta.next_read().unwrap().unwrap().seq, b"TT");
+    }
+
+    fn first_error(path: &Path, trim_trailing_ws: bool) -> Option<String> {
+        let mut reader = InputReader::open(path, 1, false, trim_trailing_ws, None).unwrap();
+        loop {
+            match reader.next_read() {
+                Ok(Some(_)) => {}
+                Ok(None) => return None,
+                Err(e) => return Some(e.to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn plus_line_may_be_bare_or_repeat_the_id() {
+        let ok = b"@r0 x\nACGT\n+\nIIII\n@r1 y\nACGT\n+r1 y\nIIII\n@r2\r\nAC\r\n+r2\r\nII\r\n";
+        let bad = b"@r0\nACGT\n+\nIIII\n@r1\nACGT\n+r9\nIIII\n";
+        // kira-fastq for plain and gzip input, the line parser with
+        // --trim-trailing-ws.
+        for (name, bytes) in [("plus.fastq", ok.to_vec()), ("plus.fastq.gz", gzip(ok))] {
+            let file = TempFile::new(name, &bytes);
+            assert_eq!(read_ids(&file.0), ["r0 x", "r1 y", "r2"]);
+            assert_eq!(first_error(&file.0, true), None);
+        }
+        for (name, bytes) in [
+            ("plus-bad.fastq", bad.to_vec()),
+            ("plus-bad.fastq.gz", gzip(bad)),
+        ] {
+            let file = TempFile::new(name, &bytes);
+            for trim in [false, true] {
+                let err = first_error(&file.0, trim).unwrap();
+                assert!(err.contains("record 2 has '+' line '+r9'"), "{err}");
+            }
+        }
+    }
+
+    #[test]
+    fn trimmed_plus_line_matches_header_with_trailing_ws() {
+        let bytes = b"@r0 \nACGT\n+r0\nIIII\n@r1\nACGT \n+r1 \nIIII\n";
+        let file = TempFile::new("plus-ws.fastq", bytes);
+        assert_eq!(first_error(&file.0, true), None);
+    }
+
+    #[test]
+    fn lenient_skips_a_truncated_record_mid_file() {
+        // r3 lost its quality line and r5's quality starts with '@'; the file
+        // ends inside r7.
+        let mut bytes = fastq(0, 3);
+        bytes.extend_from_slice(b"@r3\nACGTACGTAC\n+\n");
+        bytes.extend(fastq(4, 1));
+        bytes.extend_from_slice(b"@r5\nACGTACGTAC\n+\n@@@@IIIIII\n");
+        bytes.extend(fastq(6, 1));
+        bytes.extend_from_slice(b"@r7\nACG");
+        for (name, bytes) in [
+            ("lenient.fastq", bytes.clone()),
+            ("lenient.fastq.gz", gzip(&bytes)),
+        ] {
+            let file = TempFile::new(name, &bytes);
+            let mut reader = InputReader::open(&file.0, 1, true, false, None).unwrap();
+            let mut ids = Vec::new();
+            while let Some(read) = reader.next_read().unwrap() {
+                ids.push(String::from_utf8(read.id).unwrap());
+            }
+            assert_eq!(ids, ["r0", "r1", "r2", "r4", "r5", "r6"]);
+            assert_eq!(reader.malformed_records(), 2);
+
+            // Without --lenient the same file fails.
+            assert!(first_error(&file.0, false).is_some());
+        }
+    }
+
+    #[test]
+    fn rejects_line_without_newline_past_cap() {
+        let mut bytes = b"@r0\n".to_vec();
+        bytes.extend(std::iter::repeat_n(b'A', 64));
+        let mut reader = TextFastqReader::new(&bytes[..], LineCounters::default());
+        reader.max_line = 16;
+        let err = reader.next_read().unwrap_err().to_string();
+        assert!(err.contains("record too long"), "{err}");
+
+        // A line exactly at the cap is still read.
+        let mut reader =
+            TextFastqReader::new(&b"@r0\nACGT\n+\nIIII\n"[..], LineCounters::default());
+        reader.max_line = 5;
+        assert_eq!(reader.next_read().unwrap().unwrap().seq, b"ACGT");
+    }
+
+    #[test]
+    fn rejects_fasta_line_without_newline_past_cap() {
+        let mut bytes = b">contig\n".to_vec();
+        bytes.extend(std::iter::repeat_n(b'A', 64));
+        let mut reader = FastaReader::new(&bytes[..]);
+        reader.max_line = 16;
+        let err = reader.next_read().unwrap_err().to_string();
+        assert!(err.contains("record too long"), "{err}");
+    }
+}
@@ -10,6 +10,15 @@ pub const ADAPTERS: [&str; 5] = [
     "CGCCTTGGCCGTACAGCAG",                // SOLiD Small RNA Adapter
 ];
 
+// Display names of ADAPTERS, in the same order.
+pub const ADAPTER_NAMES: [&str; 5] = [
+    "Illumina Universal Adapter",
+    "Illumina Small RNA 3' Adapter",
+    "Illumina Small RNA 5' Adapter",
+    "Nextera Transposase Sequence",
+    "SOLiD Small RNA Adapter",
+];
+
 const PREFIXES: [&[u8]; 5] = [
     b"AGATCGGA",
     b"TGGAATTC",
@@ -28,35 +37,85 @@ pub fn adapter_matcher() -> &'static AhoCorasick {
     })
 }
 
-pub fn scan(seq: &[u8], counts: &mut [[u64; ADAPTERS.len()]]) {
+// Counts each adapter once per read, at its first match, so the cumulative
+// percentages in finalize cannot pass 100%. Returns the earliest match start,
+// i.e. where 3' trimming would cut.
+pub fn scan(seq: &[u8], counts: &mut [[u64; ADAPTERS.len()]]) -> Option<usize> {
     if seq.is_empty() {
-        return;
+        return None;
     }
     if !prefilter(seq) {
-        return;
+        return None;
     }
     let ac = adapter_matcher();
+    let mut first: Option<usize> = None;
+    let mut seen = [false; ADAPTERS.len()];
+    // Matches come in order of their start, so the first one per adapter is
+    // its earliest.
     for mat in ac.find_iter(seq) {
         let pos = mat.start();
-        if pos < counts.len() {
-            let idx = mat.pattern().as_usize();
+        first = Some(first.map_or(pos, |f| f.min(pos)));
+        let idx = mat.pattern().as_usize();
+        if !seen[idx] && pos < counts.len() {
             counts[pos][idx] += 1;
         }
+        seen[idx] = true;
     }
+    first
 }
 
-pub fn scan_any(seq: &[u8], hits: &mut [bool; ADAPTERS.len()]) {
+// Like scan, returns the earliest match start.
+pub fn scan_any(seq: &[u8], hits: &mut [bool; ADAPTERS.len()]) -> Option<usize> {
     if seq.is_empty() {
-        return;
+        return None;
     }
     if !prefilter(seq) {
-        return;
+        return None;
     }
     let ac = adapter_matcher();
+    let mut first: Option<usize> = None;
     for mat in ac.find_iter(seq) {
+        first = Some(first.map_or(mat.start(), |f| f.min(mat.start())));
         let idx = mat.pattern().as_usize();
         hits[idx] = true;
     }
+    first
+}
+
+// Long mode --adapter-positions: a match starting in the first, or ending in
+// the last, POSITION_EDGE_PCT percent of the read counts as Start or End;
+// anything else is Middle, which usually marks a chimeric junction.
+pub const POSITION_BINS: [&str; 3] = ["Start", "Middle", "End"];
+const POSITION_EDGE_PCT: usize = 10;
+
+// Like scan_any, but records which relative-position bins each adapter was
+// found in.
+pub fn scan_any_binned(
+    seq: &[u8],
+    hits: &mut [[bool; POSITION_BINS.len()]; ADAPTERS.len()],
+) -> Option<usize> {
+    if seq.is_empty() {
+        return None;
+    }
+    if !prefilter(seq) {
+        return None;
+    }
+    let ac = adapter_matcher();
+    let len = seq.len();
+    let mut first: Option<usize> = None;
+    for mat in ac.find_iter(seq) {
+        first = Some(first.map_or(mat.start(), |f| f.min(mat.start())));
+        let idx = mat.pattern().as_usize();
+        let bin = if mat.start() * 100 < len * POSITION_EDGE_PCT {
+            0
+        } else if (len - mat.end()) * 100 < len * POSITION_EDGE_PCT {
+            2
+        } else {
+            1
+        };
+        hits[idx][bin] = true;
+    }
+    first
 }
 
 fn prefilter(seq: &[u8]) -> bool {
@@ -73,3 +132,10 @@ pub struct AdapterRow {
     pub position: usize,
     pub values: [f64; ADAPTERS.len()],
 }
+
+// Percentage of reads with each adapter in one relative-position bin.
+#[derive(Clone, Debug)]
+pub struct AdapterPositionRow {
+    pub bin: &'static str,
+    pub values: [f64; ADAPTERS.len()],
+}
@@ -0,0 +1,14 @@
+// Whole-run base totals. Within a strand A and T (and G and C) occur at
+// near-equal rates in unbiased libraries, so a large gap between either pair
+// points at strand-biased library prep.
+pub struct BaseComposition {
+    pub a: u64,
+    pub c: u64,
+    pub g: u64,
+    pub t: u64,
+    // (A+T)/(G+C); 0 when there are no G/C bases.
+    pub at_gc_ratio: f64,
+    // |A-T| and |G-C| as a percentage of all bases.
+    pub at_imbalance_pct: f64,
+    pub gc_imbalance_pct: f64,
+}
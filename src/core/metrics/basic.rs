@@ -3,7 +3,27 @@ pub struct BasicStats {
     pub encoding: &'static str,
     pub total_sequences: u64,
     pub filtered_sequences: u64,
+    // Out-of-range quality bytes (see --strict-phred) and the byte offset of
+    // the first read holding one.
+    pub invalid_qual_bytes: u64,
+    pub first_invalid_qual_record: Option<u64>,
+    // Records skipped by --lenient parsing.
+    pub malformed_records: u64,
+    // Records whose lines had trailing whitespace cut (--trim-trailing-ws).
+    pub ws_trimmed_records: u64,
     pub min_len: u32,
     pub max_len: u32,
     pub gc_percent: u32,
+    pub ambiguous_percent: f64,
+    // Lowercase share of all bases, with --track-softmask.
+    pub softmasked_percent: Option<f64>,
+    // total_bases / --genome-size, when a genome size is given.
+    pub estimated_coverage: Option<f64>,
+    // "<algorithm> <hex digest>" of the input file, with --hash.
+    pub file_hash: Option<String>,
+    // Distinct values from Illumina read IDs (sorted); empty for other
+    // header formats. Lanes read "<flowcell>:<lane>".
+    pub instruments: Vec<String>,
+    pub flowcells: Vec<String>,
+    pub lanes: Vec<String>,
 }
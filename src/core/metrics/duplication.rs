@@ -1,8 +1,22 @@
+use crate::simd;
+use serde::{Deserialize, Serialize};
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashMap};
 
-// Chosen to balance accuracy and memory; ~200k entries keeps top duplicates without unbounded growth.
-const DUP_K: usize = 200_000;
+// Stale heap items allowed per live entry before the heap is rebuilt.
+const HEAP_COMPACT_FACTOR: usize = 4;
+// Evictions, as a share of all reads, past which the sketch counts as
+// saturated: most distinct sequences then pass through it without being
+// counted.
+pub const SATURATION_EVICTIONS: f64 = 0.25;
+
+// A saturated duplication sketch, see SATURATION_EVICTIONS.
+#[derive(Clone, Copy, Debug)]
+pub struct DupSaturation {
+    pub evictions: u64,
+    // False when --memory is already at its largest sketch.
+    pub larger_sketch: bool,
+}
 
 #[derive(Clone, Debug)]
 pub struct DuplicationRow {
@@ -10,6 +24,17 @@ pub struct DuplicationRow {
     pub relative: f64,
 }
 
+// The most frequent sequence in the duplication sketch.
+#[derive(Clone, Debug)]
+pub struct TopSequence {
+    pub count: u64,
+    // Share of all reads.
+    pub percent: f64,
+    // Set when the overrepresented sequences sketch holds the same
+    // sequence.
+    pub sequence: Option<String>,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum DupLevel {
     One,
@@ -35,26 +60,35 @@ impl DupLevel {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Entry {
     pub key: u64,
     pub count: u64,
     pub error: u64,
 }
 
-#[derive(Clone, Debug)]
+// Only the capacity, entries and eviction count are persisted; the key
+// index and min-heap are rebuilt.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "(usize, Vec<Entry>, u64)", into = "(usize, Vec<Entry>, u64)")]
 pub struct SpaceSaving {
+    // MemoryProfile::dup_capacity.
+    capacity: usize,
+    // Entries replaced to make room for a new key.
+    evictions: u64,
     map: HashMap<u64, usize>,
     entries: Vec<Entry>,
     heap: BinaryHeap<(Reverse<u64>, u64, usize)>,
 }
 
 impl SpaceSaving {
-    pub fn new() -> Self {
+    pub fn new(capacity: usize) -> Self {
         Self {
-            map: HashMap::with_capacity(DUP_K),
-            entries: Vec::with_capacity(DUP_K),
-            heap: BinaryHeap::with_capacity(DUP_K),
+            capacity,
+            evictions: 0,
+            map: HashMap::with_capacity(capacity),
+            entries: Vec::with_capacity(capacity),
+            heap: BinaryHeap::with_capacity(capacity),
         }
     }
 
@@ -63,10 +97,11 @@ impl SpaceSaving {
             let e = &mut self.entries[idx];
             e.count += weight;
             self.heap.push((Reverse(e.count), e.key, idx));
+            self.compact_heap();
             return;
         }
 
-        if self.entries.len() < DUP_K {
+        if self.entries.len() < self.capacity {
             let idx = self.entries.len();
             self.entries.push(Entry {
                 key,
@@ -78,9 +113,13 @@ impl SpaceSaving {
             return;
         }
 
-        let (min_idx, min_count) = self.min_entry();
+        let Some((min_idx, min_count)) = self.min_entry() else {
+            // Only possible with no entries, i.e. a zero capacity.
+            return;
+        };
         let removed = self.entries[min_idx].key;
         self.map.remove(&removed);
+        self.evictions += 1;
         self.entries[min_idx] = Entry {
             key,
             count: min_count + weight,
@@ -91,6 +130,7 @@ impl SpaceSaving {
     }
 
     pub fn merge(&mut self, other: &SpaceSaving) {
+        self.evictions += other.evictions;
         let mut items = other.entries.clone();
         items.sort_by_key(|e| e.key);
         for e in items {
@@ -102,17 +142,74 @@ impl SpaceSaving {
         &self.entries
     }
 
-    fn min_entry(&mut self) -> (usize, u64) {
-        loop {
-            if let Some((Reverse(count), key, idx)) = self.heap.pop() {
-                let e = &self.entries[idx];
-                if e.key == key && e.count == count {
-                    return (idx, count);
-                }
-            } else {
-                return (0, self.entries[0].count);
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    fn min_entry(&mut self) -> Option<(usize, u64)> {
+        while let Some((Reverse(count), key, idx)) = self.heap.pop() {
+            let e = &self.entries[idx];
+            if e.key == key && e.count == count {
+                return Some((idx, count));
             }
         }
+        // Every live entry keeps a current heap item, so an exhausted heap means
+        // the two went out of sync; rebuild rather than guess.
+        self.rebuild_heap();
+        self.heap.pop().map(|(Reverse(count), _, idx)| (idx, count))
+    }
+
+    // Increments leave the entry's previous heap item behind as stale. Rebuild
+    // from the live entries once stale items dominate, so the heap stays within
+    // HEAP_COMPACT_FACTOR times the entry count however skewed the input.
+    fn compact_heap(&mut self) {
+        if self.heap.len() > HEAP_COMPACT_FACTOR * self.entries.len() {
+            self.rebuild_heap();
+        }
+    }
+
+    fn rebuild_heap(&mut self) {
+        self.heap = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(idx, e)| (Reverse(e.count), e.key, idx))
+            .collect();
+    }
+}
+
+impl From<(usize, Vec<Entry>, u64)> for SpaceSaving {
+    fn from((capacity, entries, evictions): (usize, Vec<Entry>, u64)) -> Self {
+        let mut s = Self::new(capacity);
+        s.evictions = evictions;
+        for (idx, e) in entries.iter().enumerate() {
+            s.map.insert(e.key, idx);
+            s.heap.push((Reverse(e.count), e.key, idx));
+        }
+        s.entries = entries;
+        s
+    }
+}
+
+impl From<SpaceSaving> for (usize, Vec<Entry>, u64) {
+    fn from(s: SpaceSaving) -> Self {
+        (s.capacity, s.entries, s.evictions)
+    }
+}
+
+// The lexicographically smaller (ASCII-uppercased) of `seq` and its reverse
+// complement, which is built in `buf`; hashing this collapses the two
+// strands of a sequence into one key.
+pub fn canonical_strand<'a>(seq: &'a [u8], buf: &'a mut Vec<u8>) -> &'a [u8] {
+    simd::reverse_complement(seq, buf);
+    if seq
+        .iter()
+        .map(|b| b.to_ascii_uppercase())
+        .le(buf.iter().copied())
+    {
+        seq
+    } else {
+        buf
     }
 }
 
@@ -126,3 +223,94 @@ pub fn hash_seq(seq: &[u8]) -> u64 {
     }
     h
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heavy_keys_survive_many_distinct_keys_and_heap_stays_bounded() {
+        let mut s = SpaceSaving::new(16);
+        for round in 0..20_000u64 {
+            // Keys 1-4 recur every round; every other key is seen once.
+            s.add(1 + round % 4, 1);
+            s.add(1_000 + round, 1);
+            assert!(s.heap.len() <= HEAP_COMPACT_FACTOR * s.entries.len() + 1);
+        }
+        assert_eq!(s.entries().len(), 16);
+        for key in 1..=4 {
+            let e = s.entries().iter().find(|e| e.key == key).unwrap();
+            // Space-saving never underestimates, and count - error bounds the
+            // true count from below.
+            assert!(e.count >= 5_000 && e.count - e.error <= 5_000);
+        }
+        assert!(s.evictions() > 0);
+    }
+
+    #[test]
+    fn min_entry_copes_with_no_entries_and_a_lost_heap() {
+        let mut empty = SpaceSaving::new(0);
+        empty.add(1, 1);
+        assert!(empty.entries().is_empty());
+        assert_eq!(empty.min_entry(), None);
+
+        let mut s = SpaceSaving::new(4);
+        for (key, weight) in [(1, 5), (2, 3), (3, 9), (4, 7)] {
+            s.add(key, weight);
+        }
+        s.heap.clear();
+        assert_eq!(s.min_entry(), Some((1, 3)));
+    }
+
+    #[test]
+    fn read_and_reverse_complement_share_a_bucket() {
+        // Long enough for the SIMD blocks as well as the scalar tail, with
+        // lowercase bases and IUPAC codes.
+        let read = b"ACGTTGCAacgtRYKMBVDHSWNacgGATTACAttgCCRRYYKKMMBBVVDDHHSSWWNNacgtN".to_vec();
+        let rc: Vec<u8> = read
+            .iter()
+            .rev()
+            .map(|&b| match b.to_ascii_uppercase() {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                b'T' => b'A',
+                b'R' => b'Y',
+                b'Y' => b'R',
+                b'K' => b'M',
+                b'M' => b'K',
+                b'B' => b'V',
+                b'V' => b'B',
+                b'D' => b'H',
+                b'H' => b'D',
+                u => u,
+            })
+            .collect();
+
+        let (mut buf_a, mut buf_b) = (Vec::new(), Vec::new());
+        let a = canonical_strand(&read, &mut buf_a);
+        let b = canonical_strand(&rc, &mut buf_b);
+        assert_eq!(hash_seq(a), hash_seq(b));
+
+        let mut hits = SpaceSaving::new(4);
+        hits.add(hash_seq(a), 1);
+        hits.add(hash_seq(b), 1);
+        assert_eq!(hits.entries().len(), 1);
+        assert_eq!(hits.entries()[0].count, 2);
+
+        // Every byte value, in and past the SIMD blocks: non-letters come back
+        // unchanged, and complementing twice only uppercases letters.
+        let all: Vec<u8> = (0..=255u8).chain(0..=255u8).chain(0..=40u8).collect();
+        let (mut once, mut twice) = (Vec::new(), Vec::new());
+        simd::reverse_complement(&all, &mut once);
+        simd::reverse_complement(&once, &mut twice);
+        for (&b, &c) in all.iter().zip(once.iter().rev()) {
+            if !b.is_ascii_alphabetic() {
+                assert_eq!(c, b, "byte {b:#04x}");
+            }
+        }
+        for (&b, &t) in all.iter().zip(&twice) {
+            assert_eq!(t, b.to_ascii_uppercase(), "byte {b:#04x}");
+        }
+    }
+}
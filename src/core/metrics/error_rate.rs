@@ -0,0 +1,5 @@
+pub struct ErrorRateRow {
+    pub base: usize,
+    // Mean of 10^(-Q/10) over the position's quality histogram.
+    pub error_rate: f64,
+}
@@ -0,0 +1,4 @@
+pub struct GcSkewRow {
+    pub base: usize,
+    pub skew: f64,
+}
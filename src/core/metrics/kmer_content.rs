@@ -2,6 +2,7 @@
 mod real {
     use crate::core::metrics::UpdateTimings;
     use crate::simd;
+    use serde::{Deserialize, Serialize};
     use std::cmp::Reverse;
     use std::collections::{BinaryHeap, HashMap};
     use std::time::Instant;
@@ -9,10 +10,11 @@ mod real {
     pub const K: usize = 7;
     pub const BINS: usize = 10;
     const CMS_DEPTH: usize = 4;
-    const CMS_WIDTH: usize = 1 << 18;
-    // Per-bin heavy hitters; sized for stability while keeping memory bounded.
-    const HH_K: usize = 2000;
-    const MAX_REPORT: usize = 50;
+    // Counters saturate rather than wrap; an estimate at this value is only
+    // a lower bound.
+    pub const CMS_SATURATED: u32 = u32::MAX;
+    // Stale heap items allowed per live entry before the heap is rebuilt.
+    const HEAP_COMPACT_FACTOR: usize = 4;
 
     #[derive(Clone, Debug)]
     pub struct KmerRow {
@@ -23,22 +25,25 @@ mod real {
         pub max_pos: u32,
     }
 
-    #[derive(Clone, Debug)]
+    #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct Cms {
+        // Row width, a power of two (MemoryProfile::kmer_cms_width).
+        width: usize,
         data: Vec<u32>,
     }
 
     impl Cms {
-        pub fn new() -> Self {
+        pub fn new(width: usize) -> Self {
             Self {
-                data: vec![0u32; CMS_DEPTH * CMS_WIDTH],
+                width,
+                data: vec![0u32; CMS_DEPTH * width],
             }
         }
 
         pub fn add(&mut self, key: u64, weight: u32) {
             for d in 0..CMS_DEPTH {
                 let idx = self.index(key, d);
-                let slot = &mut self.data[d * CMS_WIDTH + idx];
+                let slot = &mut self.data[d * self.width + idx];
                 *slot = slot.saturating_add(weight);
             }
         }
@@ -47,12 +52,12 @@ mod real {
             let mut min = u32::MAX;
             for d in 0..CMS_DEPTH {
                 let idx = self.index(key, d);
-                let v = self.data[d * CMS_WIDTH + idx];
+                let v = self.data[d * self.width + idx];
                 if v < min {
                     min = v;
                 }
             }
-            if min == u32::MAX { 0 } else { min }
+            min
         }
 
         pub fn merge(&mut self, other: &Cms) {
@@ -67,29 +72,35 @@ mod real {
             x ^= x >> 33;
             x = x.wrapping_mul(0xff51afd7ed558ccd);
             x ^= x >> 33;
-            (x as usize) & (CMS_WIDTH - 1)
+            (x as usize) & (self.width - 1)
         }
     }
 
-    #[derive(Clone, Debug)]
-    struct Entry {
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct Entry {
         key: u64,
         count: u64,
     }
 
-    #[derive(Clone, Debug)]
+    // Only the capacity and entries are persisted; the key index and
+    // min-heap are rebuilt.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(from = "(usize, Vec<Entry>)", into = "(usize, Vec<Entry>)")]
     pub struct SpaceSaving {
+        // Per-bin heavy hitters (MemoryProfile::kmer_hh_capacity).
+        capacity: usize,
         map: HashMap<u64, usize>,
         entries: Vec<Entry>,
         heap: BinaryHeap<(Reverse<u64>, u64, usize)>,
     }
 
     impl SpaceSaving {
-        pub fn new() -> Self {
+        pub fn new(capacity: usize) -> Self {
             Self {
-                map: HashMap::with_capacity(HH_K),
-                entries: Vec::with_capacity(HH_K),
-                heap: BinaryHeap::with_capacity(HH_K),
+                capacity,
+                map: HashMap::with_capacity(capacity),
+                entries: Vec::with_capacity(capacity),
+                heap: BinaryHeap::with_capacity(capacity),
             }
         }
 
@@ -98,10 +109,11 @@ mod real {
                 let e = &mut self.entries[idx];
                 e.count += weight;
                 self.heap.push((Reverse(e.count), e.key, idx));
+                self.compact_heap();
                 return;
             }
 
-            if self.entries.len() < HH_K {
+            if self.entries.len() < self.capacity {
                 let idx = self.entries.len();
                 self.entries.push(Entry { key, count: weight });
                 self.map.insert(key, idx);
@@ -109,7 +121,10 @@ mod real {
                 return;
             }
 
-            let (min_idx, min_count) = self.min_entry();
+            let Some((min_idx, min_count)) = self.min_entry() else {
+                // Only possible with no entries, i.e. a zero capacity.
+                return;
+            };
             let removed = self.entries[min_idx].key;
             self.map.remove(&removed);
             self.entries[min_idx] = Entry {
@@ -132,17 +147,50 @@ mod real {
             self.entries.iter().map(|e| e.key).collect()
         }
 
-        fn min_entry(&mut self) -> (usize, u64) {
-            loop {
-                if let Some((Reverse(count), key, idx)) = self.heap.pop() {
-                    let e = &self.entries[idx];
-                    if e.key == key && e.count == count {
-                        return (idx, count);
-                    }
-                } else {
-                    return (0, self.entries[0].count);
+        fn min_entry(&mut self) -> Option<(usize, u64)> {
+            while let Some((Reverse(count), key, idx)) = self.heap.pop() {
+                let e = &self.entries[idx];
+                if e.key == key && e.count == count {
+                    return Some((idx, count));
                 }
             }
+            // Heap and entries out of sync; rebuild instead of guessing.
+            self.rebuild_heap();
+            self.heap.pop().map(|(Reverse(count), _, idx)| (idx, count))
+        }
+
+        // Drops stale heap items, as in duplication::SpaceSaving.
+        fn compact_heap(&mut self) {
+            if self.heap.len() > HEAP_COMPACT_FACTOR * self.entries.len() {
+                self.rebuild_heap();
+            }
+        }
+
+        fn rebuild_heap(&mut self) {
+            self.heap = self
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(idx, e)| (Reverse(e.count), e.key, idx))
+                .collect();
+        }
+    }
+
+    impl From<(usize, Vec<Entry>)> for SpaceSaving {
+        fn from((capacity, entries): (usize, Vec<Entry>)) -> Self {
+            let mut s = Self::new(capacity);
+            for (idx, e) in entries.iter().enumerate() {
+                s.map.insert(e.key, idx);
+                s.heap.push((Reverse(e.count), e.key, idx));
+            }
+            s.entries = entries;
+            s
+        }
+    }
+
+    impl From<SpaceSaving> for (usize, Vec<Entry>) {
+        fn from(s: SpaceSaving) -> Self {
+            (s.capacity, s.entries)
         }
     }
 
@@ -206,15 +254,15 @@ mod real {
         10f64.powf(-exp)
     }
 
-    pub fn select_top(rows: &mut Vec<KmerRow>) {
+    // Ties on obs/exp are broken by sequence so the table (and the cut at
+    // `max_rows`) does not depend on the order keys were collected in.
+    pub fn select_top(rows: &mut Vec<KmerRow>, max_rows: usize) {
         rows.sort_by(|a, b| {
             b.obs_exp
-                .partial_cmp(&a.obs_exp)
-                .unwrap_or(std::cmp::Ordering::Equal)
+                .total_cmp(&a.obs_exp)
+                .then_with(|| a.sequence.cmp(&b.sequence))
         });
-        if rows.len() > MAX_REPORT {
-            rows.truncate(MAX_REPORT);
-        }
+        rows.truncate(max_rows);
     }
 
     #[inline(always)]
@@ -223,6 +271,14 @@ mod real {
         ((t * len) + 99) / 100
     }
 
+    // Block codes hold the first base in the lowest bits; reverse the seven
+    // 2-bit codes to get the encode_kmer order.
+    #[inline(always)]
+    fn block_key(codes: u64) -> u16 {
+        let r = (codes as u16).reverse_bits() >> 2;
+        ((r >> 1) & 0x5555) | ((r & 0x5555) << 1)
+    }
+
     pub fn update_kmers(
         seq: &[u8],
         len: usize,
@@ -308,12 +364,10 @@ mod real {
             }
 
             let mut w = vbits;
+            // Bit i survives only if bits i..i+K are all valid: runs of 2, 4, 7.
             w &= w >> 1;
             w &= w >> 2;
             w &= w >> 3;
-            w &= w >> 4;
-            w &= w >> 5;
-            w &= w >> 6;
             let max_start = combined_len - K;
             if max_start < 31 {
                 w &= (1u32 << (max_start + 1)) - 1;
@@ -335,7 +389,7 @@ mod real {
                         next_threshold = next_bin_threshold(len, bin);
                     }
                     t.kmer_binning += t_bin.elapsed();
-                    let key = ((stream_bits >> (2 * i)) & mask) as u16;
+                    let key = block_key((stream_bits >> (2 * i)) & mask);
                     batch_keys[batch_len] = key;
                     batch_bins[batch_len] = bin as u8;
                     batch_len += 1;
@@ -365,7 +419,7 @@ mod real {
                         bin += 1;
                         next_threshold = next_bin_threshold(len, bin);
                     }
-                    let key = ((stream_bits >> (2 * i)) & mask) as u16;
+                    let key = block_key((stream_bits >> (2 * i)) & mask);
                     batch_keys[batch_len] = key;
                     batch_bins[batch_len] = bin as u8;
                     batch_len += 1;
@@ -404,7 +458,12 @@ mod real {
             pos += 16;
         }
 
-        let mut rolling = carry_bits;
+        // Carried bases are packed first-base-lowest; the rolling hash wants
+        // the first base in the high bits, like encode_kmer.
+        let mut rolling = 0u64;
+        for j in 0..carry_len {
+            rolling = (rolling << 2) | ((carry_bits >> (2 * j)) & 0x3);
+        }
         let mut valid_run = carry_len;
         let t_tail: Option<Instant> = timing.as_deref_mut().map(|_| Instant::now());
         while pos < len {
@@ -477,6 +536,118 @@ mod real {
             t.kmer += t0.elapsed();
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn cms_counters_saturate_instead_of_wrapping() {
+            let key = encode_kmer(b"ACGTACG").unwrap();
+            let mut cms = Cms::new(1 << 10);
+            cms.add(key, u32::MAX - 1);
+            assert_eq!(cms.estimate(key), u32::MAX - 1);
+            cms.add(key, 5);
+            assert_eq!(cms.estimate(key), CMS_SATURATED);
+
+            let mut other = Cms::new(1 << 10);
+            other.add(key, u32::MAX / 2 + 1);
+            let mut merged = other.clone();
+            merged.merge(&other);
+            assert_eq!(merged.estimate(key), CMS_SATURATED);
+        }
+
+        fn random_read(state: &mut u64, len: usize) -> Vec<u8> {
+            (0..len)
+                .map(|_| {
+                    *state ^= *state << 13;
+                    *state ^= *state >> 7;
+                    *state ^= *state << 17;
+                    b"ACGT"[(*state >> 32) as usize & 3]
+                })
+                .collect()
+        }
+
+        fn fresh_sketches(
+            width: usize,
+            capacity: usize,
+        ) -> (Vec<Cms>, Vec<SpaceSaving>, [u64; BINS], u64) {
+            let cms = (0..BINS).map(|_| Cms::new(width)).collect();
+            let hh = (0..BINS).map(|_| SpaceSaving::new(capacity)).collect();
+            (cms, hh, [0u64; BINS], 0)
+        }
+
+        #[test]
+        fn block_path_counts_every_window_with_encode_kmer_keys() {
+            // 150 valid bases: nine 16-base blocks and a 6-base tail.
+            let mut state = 0x0123_4567_89AB_CDEFu64;
+            let seq = random_read(&mut state, 150);
+            let (mut cms, mut hh, mut bin_counts, mut total) = fresh_sketches(1 << 20, 64);
+            update_kmers(
+                &seq,
+                seq.len(),
+                &mut cms,
+                &mut hh,
+                &mut bin_counts,
+                &mut total,
+                None,
+            );
+            assert_eq!(total, 144);
+            let mut counts = std::collections::HashMap::new();
+            for window in seq.windows(K) {
+                *counts.entry(encode_kmer(window).unwrap()).or_insert(0u32) += 1;
+            }
+            for (&key, &count) in &counts {
+                let seen: u32 = cms.iter().map(|c| c.estimate(key)).sum();
+                assert!(seen >= count, "key {key:#06x}: {seen} < {count}");
+            }
+        }
+
+        #[test]
+        fn update_kmers_matches_a_naive_count() {
+            let mut state = 0x9E37_79B9_7F4A_7C15u64;
+            for len in K..=200 {
+                let mut seq = random_read(&mut state, len);
+                // Ns at block boundaries and mid-block exercise the carry.
+                for &p in &[5usize, 16, 31, 40, 41, 97] {
+                    if p < len && len % 3 == 0 {
+                        seq[p] = b'N';
+                    }
+                }
+                let (mut cms, mut hh, mut bin_counts, mut total) = fresh_sketches(1 << 20, 64);
+                update_kmers(
+                    &seq,
+                    len,
+                    &mut cms,
+                    &mut hh,
+                    &mut bin_counts,
+                    &mut total,
+                    None,
+                );
+
+                let mut expected = vec![std::collections::HashMap::new(); BINS];
+                let mut expected_bins = [0u64; BINS];
+                for start in 0..=len - K {
+                    let Some(key) = encode_kmer(&seq[start..start + K]) else {
+                        continue;
+                    };
+                    let mut bin = 0;
+                    while bin + 1 < BINS && start + 1 >= next_bin_threshold(len, bin) {
+                        bin += 1;
+                    }
+                    *expected[bin].entry(key).or_insert(0u32) += 1;
+                    expected_bins[bin] += 1;
+                }
+                assert_eq!(bin_counts, expected_bins, "len {len}");
+                assert_eq!(total, expected_bins.iter().sum::<u64>(), "len {len}");
+                for (bin, counts) in expected.iter().enumerate() {
+                    for (&key, &count) in counts {
+                        assert_eq!(cms[bin].estimate(key), count, "len {len} bin {bin}");
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[cfg(not(feature = "no-kmer"))]
@@ -484,6 +655,8 @@ pub use real::*;
 
 #[cfg(feature = "no-kmer")]
 mod stub {
+    use serde::{Deserialize, Serialize};
+
     pub const K: usize = 7;
     pub const BINS: usize = 10;
 
@@ -496,11 +669,11 @@ mod stub {
         pub max_pos: u32,
     }
 
-    #[derive(Clone, Debug)]
+    #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct Cms;
 
     impl Cms {
-        pub fn new() -> Self {
+        pub fn new(_width: usize) -> Self {
             Self
         }
         pub fn add(&mut self, _key: u64, _weight: u32) {}
@@ -510,11 +683,11 @@ mod stub {
         pub fn merge(&mut self, _other: &Cms) {}
     }
 
-    #[derive(Clone, Debug)]
+    #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct SpaceSaving;
 
     impl SpaceSaving {
-        pub fn new() -> Self {
+        pub fn new(_capacity: usize) -> Self {
             Self
         }
         pub fn add(&mut self, _key: u64, _weight: u64) {}
@@ -540,7 +713,7 @@ mod stub {
         1.0
     }
 
-    pub fn select_top(_rows: &mut Vec<KmerRow>) {}
+    pub fn select_top(_rows: &mut Vec<KmerRow>, _max_rows: usize) {}
 
     pub fn update_kmers(
         _seq: &[u8],
@@ -0,0 +1,21 @@
+use crate::core::model::MAX_Q;
+
+// Rows follow the long-read length bins; columns group mean quality into
+// 5-unit bins (0-4, 5-9, ..., 90-93).
+pub const LEN_BINS: usize = 8;
+pub const QUAL_BIN_WIDTH: usize = 5;
+pub const QUAL_BINS: usize = MAX_Q / QUAL_BIN_WIDTH + 1;
+pub const CELLS: usize = LEN_BINS * QUAL_BINS;
+
+pub struct LengthQualityRow {
+    pub length_bin: &'static str,
+    pub counts: [u64; QUAL_BINS],
+}
+
+impl LengthQualityRow {
+    pub const COLUMNS: usize = QUAL_BINS;
+}
+
+pub fn cell(len_bin: usize, mean_q_bin: usize) -> usize {
+    len_bin * QUAL_BINS + mean_q_bin / QUAL_BIN_WIDTH
+}
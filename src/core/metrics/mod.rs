@@ -1,15 +1,19 @@
 use crate::core::fastq::ReadView;
-use crate::core::model::{
-    Encoding, FinalizeContext, MAX_Q, Mode, QualHist, Status, quantile_from_hist,
-};
+use crate::core::model::{FinalizeContext, MAX_Q, Mode, QualHist, Status, quantile_from_hist};
 use crate::simd;
+use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 
 mod adapter_content;
+mod base_composition;
 mod basic;
 mod duplication;
+mod error_rate;
+mod gc_skew;
 mod kmer_content;
 mod length_dist;
+mod length_quality;
+mod optical_dup;
 mod overrepresented;
 mod per_base_content;
 mod per_base_n;
@@ -17,32 +21,157 @@ mod per_base_qual;
 mod per_seq_gc;
 mod per_seq_n;
 mod per_seq_qual;
+mod qual_reservoir;
+mod read_id;
+mod sequence_complexity;
+mod serde_arrays;
+mod trim_preview;
 
-pub use adapter_content::{ADAPTERS, AdapterRow};
+pub use adapter_content::{ADAPTER_NAMES, ADAPTERS, AdapterPositionRow, AdapterRow, POSITION_BINS};
+pub use base_composition::BaseComposition;
 pub use basic::BasicStats;
-pub use duplication::{DupLevel, DuplicationRow, SpaceSaving as DupSpaceSaving};
+pub use duplication::{
+    DupLevel, DupSaturation, DuplicationRow, SpaceSaving as DupSpaceSaving, TopSequence,
+};
+pub use error_rate::ErrorRateRow;
+pub use gc_skew::GcSkewRow;
 pub use kmer_content::KmerRow;
 pub use length_dist::LengthDistRow;
-pub use overrepresented::{OverrepRow, SpaceSavingSeq};
-pub use per_base_content::PerBaseContentRow;
+pub use length_quality::{LengthQualityRow, QUAL_BIN_WIDTH};
+pub use optical_dup::OpticalDupSummary;
+pub use overrepresented::{OverrepRow, SOURCE_ADAPTERS, SpaceSavingSeq, classify_source};
+pub use per_base_content::{ContentBias, PerBaseContentRow, deviation_status};
 pub use per_base_n::PerBaseNRow;
-pub use per_base_qual::PerBaseQualRow;
-pub use per_seq_gc::PerSeqGcRow;
-pub use per_seq_n::PerSeqNRow;
-pub use per_seq_qual::PerSeqQualRow;
+pub use per_base_qual::{MEDIAN_FAIL, MEDIAN_WARN, PerBaseQualLow, PerBaseQualRow};
+pub use per_seq_gc::{GcDeviation, GcReference, GcResolution, PerSeqGcRow};
+pub use per_seq_n::{N_RUN_END, N_RUN_INTERNAL, N_RUN_START, NRunSummary, PerSeqNRow};
+pub use per_seq_qual::{PerSeqQualRow, PerSeqQualStats};
+pub use read_id::sample_name_from_id;
+pub use sequence_complexity::{ComplexityRow, LOW_ENTROPY_BITS};
+pub use trim_preview::{TrimPreview, TrimRow};
 
-#[derive(Clone, Debug)]
+// Default cap on per-position tracking in short-read mode.
+pub const DEFAULT_MAX_READ_LEN: usize = 1000;
+// Default number of bases stored per overrepresented-sequence candidate.
+pub const DEFAULT_OVERREP_SEQ_LEN: usize = 150;
+// Default |A-T| or |G-C| share of all bases at which Base Composition warns.
+pub const DEFAULT_STRAND_BIAS_PCT: f64 = 10.0;
+// Default share of all reads at which a sequence is listed as
+// overrepresented (and the module fails); it warns at half of it.
+pub const DEFAULT_OVERREP_THRESHOLD_PCT: f64 = 0.1;
+// Default number of k-mer content rows reported.
+pub const DEFAULT_MAX_KMER_ROWS: usize = 50;
+// Sketch sizes behind --memory. High is the original sizing; the smaller
+// presets trade accuracy of the duplication, overrepresented and k-mer
+// estimates for a smaller per-worker footprint.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MemoryProfile {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl MemoryProfile {
+    // Distinct read hashes tracked for duplication levels.
+    pub fn dup_capacity(self) -> usize {
+        match self {
+            MemoryProfile::Low => 10_000,
+            MemoryProfile::Medium => 50_000,
+            MemoryProfile::High => 200_000,
+        }
+    }
+
+    // Distinct sequences tracked for overrepresented sequences.
+    pub fn overrep_capacity(self) -> usize {
+        match self {
+            MemoryProfile::Low => 10_000,
+            MemoryProfile::Medium => 50_000,
+            MemoryProfile::High => 200_000,
+        }
+    }
+
+    // Heavy-hitter k-mers tracked per position bin.
+    pub fn kmer_hh_capacity(self) -> usize {
+        match self {
+            MemoryProfile::Low => 500,
+            MemoryProfile::Medium => 1000,
+            MemoryProfile::High => 2000,
+        }
+    }
+
+    // Count-min sketch row width per position bin (a power of two).
+    pub fn kmer_cms_width(self) -> usize {
+        match self {
+            MemoryProfile::Low => 1 << 14,
+            MemoryProfile::Medium => 1 << 16,
+            MemoryProfile::High => 1 << 18,
+        }
+    }
+}
+
+// Modules whose accumulation is turned off with --skip. They are left out
+// of every report rather than shown as PASS.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SkipModules {
+    pub duplication: bool,
+    pub overrepresented: bool,
+    pub adapter_content: bool,
+    pub kmer_content: bool,
+}
+
+impl SkipModules {
+    pub fn union(self, other: SkipModules) -> SkipModules {
+        SkipModules {
+            duplication: self.duplication || other.duplication,
+            overrepresented: self.overrepresented || other.overrepresented,
+            adapter_content: self.adapter_content || other.adapter_content,
+            kmer_content: self.kmer_content || other.kmer_content,
+        }
+    }
+
+    // Whether the module with this `ModuleStatus::key` is skipped.
+    pub fn skips(self, key: &str) -> bool {
+        match key {
+            "duplication" => self.duplication,
+            "overrepresented" => self.overrepresented,
+            "adapter_content" | "trim_preview" => self.adapter_content,
+            "kmer_content" => self.kmer_content,
+            _ => false,
+        }
+    }
+}
+
+// Basic Statistics warns below this estimated coverage (--genome-size).
+pub const LOW_COVERAGE: f64 = 10.0;
+
+// Long-mode Adapter Content warns when any adapter is found mid-read in more
+// than this percentage of reads (--adapter-positions).
+pub const INTERNAL_ADAPTER_WARN_PCT: f64 = 1.0;
+
+// Short-mode qualities drawn from at most this many distinct values are
+// treated as binned (e.g. NovaSeq's 2/12/23/37, HiSeq X's eight levels).
+pub const BINNED_QUAL_MAX_LEVELS: usize = 8;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BaseCounts {
     pub a: u64,
     pub c: u64,
     pub g: u64,
     pub t: u64,
     pub n: u64,
+    // IUPAC ambiguity codes and anything else outside ACGTN.
+    pub other: u64,
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct UpdateTimings {
-    pub metrics_core: Duration,
+    // Whole-read base counts and read totals.
+    pub base_counting: Duration,
+    // Per-position base and quality tables (short mode).
+    pub per_position: Duration,
+    // Per-read length, GC, N, mean quality and complexity histograms.
+    pub length_gc: Duration,
     pub adapters: Duration,
     pub heavyhitters: Duration,
     pub kmer: Duration,
@@ -62,6 +191,7 @@ impl BaseCounts {
             g: 0,
             t: 0,
             n: 0,
+            other: 0,
         }
     }
 
@@ -71,32 +201,120 @@ impl BaseCounts {
         self.g += other.g;
         self.t += other.t;
         self.n += other.n;
+        self.other += other.other;
     }
 }
 
-#[derive(Clone, Debug)]
+// Per-run settings that decide what an Agg accumulates; every Agg merged
+// into another must have been built from the same config.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AggConfig {
+    pub mode: Mode,
+    pub max_read_len: usize,
+    pub overrep_max_len: usize,
+    pub overrep_prefix_len: Option<usize>,
+    // Short mode: pixel distance for the optical duplicate estimate.
+    pub optical_dup_distance: Option<u32>,
+    // Long mode: the length x mean quality map and adapter positions.
+    pub length_quality_map: bool,
+    pub adapter_positions: bool,
+    pub gc_resolution: GcResolution,
+    pub exact_quantiles: bool,
+    pub seed: u64,
+    pub track_softmask: bool,
+    pub collapse_revcomp: bool,
+    pub skip: SkipModules,
+    pub memory: MemoryProfile,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Agg {
     pub mode: Mode,
+    pub max_read_len: usize,
+    pub overrep_max_len: usize,
+    // Key overrepresented sequences on only this many leading bases, so reads
+    // differing past it count as one sequence; None keys the whole read.
+    pub overrep_prefix_len: Option<usize>,
+    // Key duplication and overrepresented sequences on the smaller of each
+    // read and its reverse complement (--collapse-revcomp).
+    pub collapse_revcomp: bool,
+    #[serde(skip)]
+    strand_buf: Vec<u8>,
+    pub skip: SkipModules,
+    pub reads_truncated: u64,
+    // Reads shorter than --min-length; with --exclude-filtered they are
+    // counted here only.
+    pub filtered_reads: u64,
+    // Quality bytes outside the phred range of the offset, and the 1-based
+    // input record number of the first read holding one (earliest chunk
+    // after merging).
+    pub invalid_qual_bytes: u64,
+    pub first_invalid_qual_record: Option<u64>,
+    // Records dropped by --lenient parsing.
+    pub malformed_records: u64,
+    // Records fixed up by --trim-trailing-ws.
+    pub ws_trimmed_records: u64,
+    // Lowercase (soft-masked) bases; None unless --track-softmask is set.
+    pub softmasked_bases: Option<u64>,
+    pub read_ids: read_id::ReadIdMeta,
     pub total_reads: u64,
     pub total_bases: u64,
     pub gc_bases: u64,
+    pub a_bases: u64,
+    pub c_bases: u64,
+    pub g_bases: u64,
+    pub t_bases: u64,
     pub n_bases: u64,
+    pub other_bases: u64,
     pub min_len: u32,
     pub max_len: u32,
+    #[serde(with = "serde_arrays::vec")]
     pub per_pos_qual: Vec<QualHist>,
     pub per_pos_base: Vec<BaseCounts>,
     pub per_seq_mean_q_hist: Vec<u64>,
+    #[serde(with = "serde_arrays")]
     pub per_seq_gc_hist: [u64; 101],
+    // Per sequence GC at GcResolution::steps() + 1 bins (long mode); empty
+    // unless --gc-resolution is finer than 1%.
+    pub per_seq_gc_fine: Vec<u64>,
+    pub complexity_hist: Vec<u64>,
     pub length_hist: Vec<u64>,
     pub reads_mean_q_lt_20: u64,
+    pub reads_without_qual: u64,
     pub dup_space: DupSpaceSaving,
+    // Short mode with --optical-dup-distance.
+    pub optical_dups: Option<optical_dup::OpticalDups>,
     pub overrep_space: SpaceSavingSeq,
     pub adapter_counts: Vec<[u64; ADAPTERS.len()]>,
+    #[serde(with = "serde_arrays")]
     pub per_seq_n_hist: [u64; 101],
+    // N runs and their bases by location (start / internal / end of read).
+    pub n_runs: [u64; 3],
+    pub n_run_bases: [u64; 3],
     pub reads_n_gt10: u64,
     pub reads_n_gt20: u64,
     pub adapter_reads_any: [u64; ADAPTERS.len()],
+    // Reads with each adapter in each POSITION_BINS bin (long mode); empty
+    // unless --adapter-positions is set.
+    pub adapter_pos_reads: Vec<[u64; POSITION_BINS.len()]>,
+    // Short mode: reads by bases removed when cut at the first adapter
+    // match (capped at max_read_len), and the exact removed total.
+    pub trim_hist: Vec<u64>,
+    pub trim_bases_removed: u64,
     pub long_len_bins: [u64; 8],
+    // long_len_bins after cutting each read at its first adapter match;
+    // reads cut to nothing are left out. Stays zero with --skip adapter.
+    pub long_trimmed_len_bins: [u64; 8],
+    // Flattened length bin x mean quality bin counts (long mode); empty
+    // unless --length-quality-map is set.
+    pub len_qual_map: Vec<u64>,
+    // Per-position quality samples (short mode); empty unless
+    // --exact-quantiles is set.
+    pub exact_quantiles: bool,
+    pub qual_reservoirs: Vec<qual_reservoir::QualReservoir>,
+    // Mixed into every sampling priority; see --seed.
+    pub seed: u64,
+    pub memory: MemoryProfile,
     pub kmer_cms: Vec<kmer_content::Cms>,
     pub kmer_hh: Vec<kmer_content::SpaceSaving>,
     pub kmer_bin_counts: [u64; kmer_content::BINS],
@@ -104,39 +322,103 @@ pub struct Agg {
 }
 
 impl Agg {
-    pub fn new(mode: Mode) -> Self {
+    pub fn new(cfg: &AggConfig) -> Self {
+        let AggConfig {
+            mode,
+            max_read_len,
+            overrep_max_len,
+            overrep_prefix_len,
+            optical_dup_distance,
+            length_quality_map,
+            adapter_positions,
+            gc_resolution,
+            exact_quantiles,
+            seed,
+            track_softmask,
+            collapse_revcomp,
+            skip,
+            memory,
+        } = *cfg;
         Self {
             mode,
+            max_read_len,
+            overrep_max_len,
+            overrep_prefix_len,
+            collapse_revcomp,
+            strand_buf: Vec::new(),
+            skip,
+            reads_truncated: 0,
+            filtered_reads: 0,
+            invalid_qual_bytes: 0,
+            first_invalid_qual_record: None,
+            malformed_records: 0,
+            ws_trimmed_records: 0,
+            softmasked_bases: track_softmask.then_some(0),
+            read_ids: read_id::ReadIdMeta::default(),
             total_reads: 0,
             total_bases: 0,
             gc_bases: 0,
+            a_bases: 0,
+            c_bases: 0,
+            g_bases: 0,
+            t_bases: 0,
             n_bases: 0,
+            other_bases: 0,
             min_len: u32::MAX,
             max_len: 0,
             per_pos_qual: Vec::new(),
             per_pos_base: Vec::new(),
             per_seq_mean_q_hist: vec![0u64; MAX_Q + 1],
             per_seq_gc_hist: [0u64; 101],
+            per_seq_gc_fine: if mode == Mode::Long && gc_resolution != GcResolution::Percent {
+                vec![0u64; gc_resolution.steps() + 1]
+            } else {
+                Vec::new()
+            },
+            complexity_hist: vec![0u64; sequence_complexity::BINS],
             length_hist: Vec::new(),
             reads_mean_q_lt_20: 0,
-            dup_space: DupSpaceSaving::new(),
-            overrep_space: SpaceSavingSeq::new(),
+            reads_without_qual: 0,
+            dup_space: DupSpaceSaving::new(memory.dup_capacity()),
+            optical_dups: optical_dup_distance
+                .filter(|_| mode == Mode::Short)
+                .map(optical_dup::OpticalDups::new),
+            overrep_space: SpaceSavingSeq::new(memory.overrep_capacity()),
             adapter_counts: Vec::new(),
             per_seq_n_hist: [0u64; 101],
+            n_runs: [0u64; 3],
+            n_run_bases: [0u64; 3],
             reads_n_gt10: 0,
             reads_n_gt20: 0,
             adapter_reads_any: [0u64; ADAPTERS.len()],
+            adapter_pos_reads: if mode == Mode::Long && adapter_positions {
+                vec![[0u64; POSITION_BINS.len()]; ADAPTERS.len()]
+            } else {
+                Vec::new()
+            },
+            trim_hist: Vec::new(),
+            trim_bases_removed: 0,
             long_len_bins: [0u64; 8],
+            long_trimmed_len_bins: [0u64; 8],
+            len_qual_map: if mode == Mode::Long && length_quality_map {
+                vec![0u64; length_quality::CELLS]
+            } else {
+                Vec::new()
+            },
+            exact_quantiles,
+            seed,
+            qual_reservoirs: Vec::new(),
+            memory,
             kmer_cms: if mode == Mode::Short {
                 (0..kmer_content::BINS)
-                    .map(|_| kmer_content::Cms::new())
+                    .map(|_| kmer_content::Cms::new(memory.kmer_cms_width()))
                     .collect()
             } else {
                 Vec::new()
             },
             kmer_hh: if mode == Mode::Short {
                 (0..kmer_content::BINS)
-                    .map(|_| kmer_content::SpaceSaving::new())
+                    .map(|_| kmer_content::SpaceSaving::new(memory.kmer_hh_capacity()))
                     .collect()
             } else {
                 Vec::new()
@@ -165,10 +447,22 @@ impl Agg {
         phred_offset: u8,
         mut timing: Option<&mut UpdateTimings>,
     ) {
+        self.read_ids.update(read.id);
+        // Every other count folds case with `& 0xDF`, so lowercase bases get
+        // their own pass.
+        if let Some(softmasked) = &mut self.softmasked_bases {
+            *softmasked += read.seq.iter().filter(|b| b.is_ascii_lowercase()).count() as u64;
+        }
         let len = read.seq.len();
         if len == 0 {
             return;
         }
+        // Reads longer than the cap still count everywhere except the
+        // per-position vectors.
+        let pos_len = len.min(self.max_read_len);
+        // FASTA records carry no qualities; they feed every metric except
+        // the quality ones.
+        let has_qual = !read.qual.is_empty();
 
         let (gc, n_count) = if let Some(t) = timing.as_deref_mut() {
             let t0 = Instant::now();
@@ -181,12 +475,17 @@ impl Agg {
                 self.min_len = len as u32;
             }
 
-            let (_a, c, g, _t, n) = simd::count_bases(read.seq);
+            let (a, c, g, t_count, n) = simd::count_bases(read.seq);
             let gc = c as u64 + g as u64;
             let n_count = n as u64;
             self.gc_bases += gc;
+            self.a_bases += a as u64;
+            self.c_bases += c as u64;
+            self.g_bases += g as u64;
+            self.t_bases += t_count as u64;
             self.n_bases += n_count;
-            t.metrics_core += t0.elapsed();
+            self.other_bases += len as u64 - (a + c + g + t_count + n) as u64;
+            t.base_counting += t0.elapsed();
             (gc, n_count)
         } else {
             self.total_reads += 1;
@@ -198,69 +497,134 @@ impl Agg {
                 self.min_len = len as u32;
             }
 
-            let (_a, c, g, _t, n) = simd::count_bases(read.seq);
+            let (a, c, g, t_count, n) = simd::count_bases(read.seq);
             let gc = c as u64 + g as u64;
             let n_count = n as u64;
             self.gc_bases += gc;
+            self.a_bases += a as u64;
+            self.c_bases += c as u64;
+            self.g_bases += g as u64;
+            self.t_bases += t_count as u64;
             self.n_bases += n_count;
+            self.other_bases += len as u64 - (a + c + g + t_count + n) as u64;
             (gc, n_count)
         };
 
+        if n_count > 0 {
+            let (runs, bases) = (&mut self.n_runs, &mut self.n_run_bases);
+            simd::find_n_runs(read.seq, &mut |start, run_len| {
+                let loc = per_seq_n::n_run_location(start, run_len, len);
+                runs[loc] += 1;
+                bases[loc] += run_len as u64;
+            });
+        }
+
         match self.mode {
             Mode::Short => {
                 if let Some(t) = timing.as_deref_mut() {
                     let t0 = Instant::now();
-                    if self.per_pos_qual.len() < len {
-                        self.per_pos_qual.resize(len, [0u64; MAX_Q + 1]);
+                    if self.per_pos_qual.len() < pos_len {
+                        self.per_pos_qual.resize(pos_len, [0u64; MAX_Q + 1]);
                     }
-                    if self.per_pos_base.len() < len {
-                        self.per_pos_base.resize(len, BaseCounts::zero());
+                    if self.per_pos_base.len() < pos_len {
+                        self.per_pos_base.resize(pos_len, BaseCounts::zero());
                     }
+                    if len > pos_len {
+                        self.reads_truncated += 1;
+                    }
+                    t.per_position += t0.elapsed();
+
+                    let t0 = Instant::now();
                     if self.length_hist.len() <= len {
                         self.length_hist.resize(len + 1, 0);
                     }
                     self.length_hist[len] += 1;
-                    t.metrics_core += t0.elapsed();
+                    t.length_gc += t0.elapsed();
 
                     let t1 = Instant::now();
-                    let key = duplication::hash_seq(read.seq);
-                    self.dup_space.add(key, 1);
-                    let key2 = overrepresented::hash_seq(read.seq);
-                    self.overrep_space.add(key2, read.seq, 1);
+                    if !self.skip.duplication {
+                        let seq = if self.collapse_revcomp {
+                            duplication::canonical_strand(read.seq, &mut self.strand_buf)
+                        } else {
+                            read.seq
+                        };
+                        let key = duplication::hash_seq(seq);
+                        self.dup_space.add(key, 1);
+                        if let Some(optical) = &mut self.optical_dups {
+                            optical.add(key, read.id);
+                        }
+                    }
+                    if !self.skip.overrepresented {
+                        let seq = self.overrep_seq(read.seq);
+                        let seq = if self.collapse_revcomp {
+                            duplication::canonical_strand(seq, &mut self.strand_buf)
+                        } else {
+                            seq
+                        };
+                        let key2 = overrepresented::hash_seq(seq);
+                        self.overrep_space.add(key2, seq, 1, self.overrep_max_len);
+                    }
                     t.heavyhitters += t1.elapsed();
 
                     let t2 = Instant::now();
-                    if self.adapter_counts.len() < len {
-                        self.adapter_counts.resize(len, [0u64; ADAPTERS.len()]);
+                    if !self.skip.adapter_content {
+                        if self.adapter_counts.len() < pos_len {
+                            self.adapter_counts.resize(pos_len, [0u64; ADAPTERS.len()]);
+                        }
+                        let cut = adapter_content::scan(read.seq, &mut self.adapter_counts);
+                        self.add_trim(len, cut);
                     }
-                    adapter_content::scan(read.seq, &mut self.adapter_counts);
                     t.adapters += t2.elapsed();
                 } else {
-                    if self.per_pos_qual.len() < len {
-                        self.per_pos_qual.resize(len, [0u64; MAX_Q + 1]);
+                    if self.per_pos_qual.len() < pos_len {
+                        self.per_pos_qual.resize(pos_len, [0u64; MAX_Q + 1]);
                     }
-                    if self.per_pos_base.len() < len {
-                        self.per_pos_base.resize(len, BaseCounts::zero());
+                    if self.per_pos_base.len() < pos_len {
+                        self.per_pos_base.resize(pos_len, BaseCounts::zero());
+                    }
+                    if len > pos_len {
+                        self.reads_truncated += 1;
                     }
                     if self.length_hist.len() <= len {
                         self.length_hist.resize(len + 1, 0);
                     }
                     self.length_hist[len] += 1;
 
-                    let key = duplication::hash_seq(read.seq);
-                    self.dup_space.add(key, 1);
+                    if !self.skip.duplication {
+                        let seq = if self.collapse_revcomp {
+                            duplication::canonical_strand(read.seq, &mut self.strand_buf)
+                        } else {
+                            read.seq
+                        };
+                        let key = duplication::hash_seq(seq);
+                        self.dup_space.add(key, 1);
+                        if let Some(optical) = &mut self.optical_dups {
+                            optical.add(key, read.id);
+                        }
+                    }
 
-                    let key2 = overrepresented::hash_seq(read.seq);
-                    self.overrep_space.add(key2, read.seq, 1);
+                    if !self.skip.overrepresented {
+                        let seq = self.overrep_seq(read.seq);
+                        let seq = if self.collapse_revcomp {
+                            duplication::canonical_strand(seq, &mut self.strand_buf)
+                        } else {
+                            seq
+                        };
+                        let key2 = overrepresented::hash_seq(seq);
+                        self.overrep_space.add(key2, seq, 1, self.overrep_max_len);
+                    }
 
-                    if self.adapter_counts.len() < len {
-                        self.adapter_counts.resize(len, [0u64; ADAPTERS.len()]);
+                    if !self.skip.adapter_content {
+                        if self.adapter_counts.len() < pos_len {
+                            self.adapter_counts.resize(pos_len, [0u64; ADAPTERS.len()]);
+                        }
+                        let cut = adapter_content::scan(read.seq, &mut self.adapter_counts);
+                        self.add_trim(len, cut);
                     }
-                    adapter_content::scan(read.seq, &mut self.adapter_counts);
                 }
 
                 #[cfg(not(feature = "no-kmer"))]
-                if len >= kmer_content::K {
+                if len >= kmer_content::K && !self.skip.kmer_content {
                     kmer_content::update_kmers(
                         read.seq,
                         len,
@@ -286,15 +650,11 @@ impl Agg {
                     } else if n_percent > 10 {
                         self.reads_n_gt10 += 1;
                     }
-                    t.metrics_core += t0.elapsed();
+                    t.length_gc += t0.elapsed();
 
                     let t1 = Instant::now();
-                    let mut hits = [false; ADAPTERS.len()];
-                    adapter_content::scan_any(read.seq, &mut hits);
-                    for i in 0..ADAPTERS.len() {
-                        if hits[i] {
-                            self.adapter_reads_any[i] += 1;
-                        }
+                    if !self.skip.adapter_content {
+                        self.add_long_adapters(read.seq);
                     }
                     t.adapters += t1.elapsed();
                 } else {
@@ -310,12 +670,8 @@ impl Agg {
                         self.reads_n_gt10 += 1;
                     }
 
-                    let mut hits = [false; ADAPTERS.len()];
-                    adapter_content::scan_any(read.seq, &mut hits);
-                    for i in 0..ADAPTERS.len() {
-                        if hits[i] {
-                            self.adapter_reads_any[i] += 1;
-                        }
+                    if !self.skip.adapter_content {
+                        self.add_long_adapters(read.seq);
                     }
                 }
             }
@@ -323,17 +679,24 @@ impl Agg {
 
         if let Some(t) = timing.as_deref_mut() {
             let t0 = Instant::now();
-            let sum_q = simd::sum_qual(read.qual, phred_offset) as u64;
-            let mean_q = (sum_q + (len as u64 / 2)) / len as u64;
-            if mean_q < 20 {
-                self.reads_mean_q_lt_20 += 1;
-            }
-            let mean_q_bin = if mean_q as usize > MAX_Q {
-                MAX_Q
+            if has_qual {
+                // Keep the exact mean for the Q20 threshold and round only
+                // when binning, so reads averaging e.g. 19.6 still count as
+                // below 20.
+                let sum_q = simd::sum_qual(read.qual, phred_offset) as u64;
+                let mean_q = sum_q as f64 / len as f64;
+                if mean_q < 20.0 {
+                    self.reads_mean_q_lt_20 += 1;
+                }
+                let mean_q_bin = (mean_q.round() as usize).min(MAX_Q);
+                self.per_seq_mean_q_hist[mean_q_bin] += 1;
+                if !self.len_qual_map.is_empty() {
+                    let cell = length_quality::cell(long_len_bin(len as u64), mean_q_bin);
+                    self.len_qual_map[cell] += 1;
+                }
             } else {
-                mean_q as usize
-            };
-            self.per_seq_mean_q_hist[mean_q_bin] += 1;
+                self.reads_without_qual += 1;
+            }
 
             let gc_percent = ((gc * 100) + (len as u64 / 2)) / len as u64;
             let gc_bin = if gc_percent > 100 {
@@ -342,9 +705,23 @@ impl Agg {
                 gc_percent as usize
             };
             self.per_seq_gc_hist[gc_bin] += 1;
+            if !self.per_seq_gc_fine.is_empty() {
+                let steps = (self.per_seq_gc_fine.len() - 1) as u64;
+                let fine_bin = ((gc * steps) + (len as u64 / 2)) / len as u64;
+                self.per_seq_gc_fine[fine_bin.min(steps) as usize] += 1;
+            }
+
+            let entropy = sequence_complexity::read_entropy(read.seq);
+            self.complexity_hist[sequence_complexity::entropy_bin(entropy)] += 1;
+
+            t.length_gc += t0.elapsed();
 
+            let t0 = Instant::now();
+            if self.exact_quantiles && has_qual {
+                self.sample_qualities(read, pos_len, phred_offset);
+            }
             if self.mode == Mode::Short {
-                for i in 0..len {
+                for i in 0..pos_len {
                     let b = read.seq[i];
                     let upper = b & 0xDF;
                     let base = &mut self.per_pos_base[i];
@@ -354,9 +731,12 @@ impl Agg {
                         b'G' => base.g += 1,
                         b'T' => base.t += 1,
                         b'N' => base.n += 1,
-                        _ => {}
+                        _ => base.other += 1,
                     }
 
+                    if !has_qual {
+                        continue;
+                    }
                     let q_raw = if read.qual[i] >= phred_offset {
                         read.qual[i] - phred_offset
                     } else {
@@ -370,19 +750,26 @@ impl Agg {
                     self.per_pos_qual[i][q_bin] += 1;
                 }
             }
-            t.metrics_core += t0.elapsed();
+            t.per_position += t0.elapsed();
         } else {
-            let sum_q = simd::sum_qual(read.qual, phred_offset) as u64;
-            let mean_q = (sum_q + (len as u64 / 2)) / len as u64;
-            if mean_q < 20 {
-                self.reads_mean_q_lt_20 += 1;
-            }
-            let mean_q_bin = if mean_q as usize > MAX_Q {
-                MAX_Q
+            if has_qual {
+                // Keep the exact mean for the Q20 threshold and round only
+                // when binning, so reads averaging e.g. 19.6 still count as
+                // below 20.
+                let sum_q = simd::sum_qual(read.qual, phred_offset) as u64;
+                let mean_q = sum_q as f64 / len as f64;
+                if mean_q < 20.0 {
+                    self.reads_mean_q_lt_20 += 1;
+                }
+                let mean_q_bin = (mean_q.round() as usize).min(MAX_Q);
+                self.per_seq_mean_q_hist[mean_q_bin] += 1;
+                if !self.len_qual_map.is_empty() {
+                    let cell = length_quality::cell(long_len_bin(len as u64), mean_q_bin);
+                    self.len_qual_map[cell] += 1;
+                }
             } else {
-                mean_q as usize
-            };
-            self.per_seq_mean_q_hist[mean_q_bin] += 1;
+                self.reads_without_qual += 1;
+            }
 
             let gc_percent = ((gc * 100) + (len as u64 / 2)) / len as u64;
             let gc_bin = if gc_percent > 100 {
@@ -391,9 +778,20 @@ impl Agg {
                 gc_percent as usize
             };
             self.per_seq_gc_hist[gc_bin] += 1;
+            if !self.per_seq_gc_fine.is_empty() {
+                let steps = (self.per_seq_gc_fine.len() - 1) as u64;
+                let fine_bin = ((gc * steps) + (len as u64 / 2)) / len as u64;
+                self.per_seq_gc_fine[fine_bin.min(steps) as usize] += 1;
+            }
 
+            let entropy = sequence_complexity::read_entropy(read.seq);
+            self.complexity_hist[sequence_complexity::entropy_bin(entropy)] += 1;
+
+            if self.exact_quantiles && has_qual {
+                self.sample_qualities(read, pos_len, phred_offset);
+            }
             if self.mode == Mode::Short {
-                for i in 0..len {
+                for i in 0..pos_len {
                     let b = read.seq[i];
                     let upper = b & 0xDF;
                     let base = &mut self.per_pos_base[i];
@@ -403,9 +801,12 @@ impl Agg {
                         b'G' => base.g += 1,
                         b'T' => base.t += 1,
                         b'N' => base.n += 1,
-                        _ => {}
+                        _ => base.other += 1,
                     }
 
+                    if !has_qual {
+                        continue;
+                    }
                     let q_raw = if read.qual[i] >= phred_offset {
                         read.qual[i] - phred_offset
                     } else {
@@ -422,11 +823,138 @@ impl Agg {
         }
     }
 
+    fn add_trim(&mut self, len: usize, cut: Option<usize>) {
+        let Some(start) = cut else {
+            return;
+        };
+        let removed = len - start;
+        let bin = removed.min(self.max_read_len);
+        if self.trim_hist.len() <= bin {
+            self.trim_hist.resize(bin + 1, 0);
+        }
+        self.trim_hist[bin] += 1;
+        self.trim_bases_removed += removed as u64;
+    }
+
+    fn add_long_adapters(&mut self, seq: &[u8]) {
+        let first = if self.adapter_pos_reads.is_empty() {
+            let mut hits = [false; ADAPTERS.len()];
+            let first = adapter_content::scan_any(seq, &mut hits);
+            for (reads, hit) in self.adapter_reads_any.iter_mut().zip(hits) {
+                if hit {
+                    *reads += 1;
+                }
+            }
+            first
+        } else {
+            self.add_long_adapter_positions(seq)
+        };
+        let trimmed = first.unwrap_or(seq.len());
+        if trimmed > 0 {
+            self.long_trimmed_len_bins[long_len_bin(trimmed as u64)] += 1;
+        }
+    }
+
+    fn add_long_adapter_positions(&mut self, seq: &[u8]) -> Option<usize> {
+        let mut hits = [[false; POSITION_BINS.len()]; ADAPTERS.len()];
+        let first = adapter_content::scan_any_binned(seq, &mut hits);
+        for (i, row) in hits.iter().enumerate() {
+            if row.contains(&true) {
+                self.adapter_reads_any[i] += 1;
+            }
+            for (reads, &hit) in self.adapter_pos_reads[i].iter_mut().zip(row) {
+                if hit {
+                    *reads += 1;
+                }
+            }
+        }
+        first
+    }
+
+    fn sample_qualities(&mut self, read: &ReadView<'_>, pos_len: usize, phred_offset: u8) {
+        if self.qual_reservoirs.len() < pos_len {
+            self.qual_reservoirs
+                .resize_with(pos_len, qual_reservoir::QualReservoir::default);
+        }
+        let read_hash =
+            duplication::hash_seq(read.id) ^ duplication::hash_seq(read.seq) ^ self.seed;
+        for (i, &b) in read.qual[..pos_len].iter().enumerate() {
+            let q = b.saturating_sub(phred_offset).min(MAX_Q as u8);
+            self.qual_reservoirs[i].add(qual_reservoir::priority(read_hash, i), q);
+        }
+    }
+
+    // The part of a read that overrepresented sequences are keyed and
+    // stored on.
+    fn overrep_seq<'a>(&self, seq: &'a [u8]) -> &'a [u8] {
+        match self.overrep_prefix_len {
+            Some(n) => &seq[..seq.len().min(n)],
+            None => seq,
+        }
+    }
+
+    // Some when the duplication sketch evicted more than
+    // duplication::SATURATION_EVICTIONS of all reads.
+    pub fn dup_saturation(&self) -> Option<DupSaturation> {
+        let evictions = self.dup_space.evictions();
+        let saturated = !self.skip.duplication
+            && evictions as f64 > self.total_reads as f64 * duplication::SATURATION_EVICTIONS;
+        saturated.then_some(DupSaturation {
+            evictions,
+            larger_sketch: self.memory != MemoryProfile::High,
+        })
+    }
+
+    // Short mode with a median read length above SHORT_MODE_MAX_MEDIAN, or
+    // long mode with one below LONG_MODE_MIN_MEDIAN, is almost always the
+    // wrong --mode; returns the mode that fits.
+    pub fn suggested_mode(&self) -> Option<Mode> {
+        if self.total_reads == 0 {
+            return None;
+        }
+        // The median (nearest rank) is the length of read number `half`.
+        let half = self.total_reads.div_ceil(2);
+        match self.mode {
+            Mode::Short => {
+                let at_most_max: u64 = self
+                    .length_hist
+                    .iter()
+                    .take(SHORT_MODE_MAX_MEDIAN + 1)
+                    .sum();
+                (at_most_max < half).then_some(Mode::Long)
+            }
+            Mode::Long => {
+                let below_min: u64 = self.long_len_bins[..long_len_bin(LONG_MODE_MIN_MEDIAN)]
+                    .iter()
+                    .sum();
+                (below_min >= half).then_some(Mode::Short)
+            }
+        }
+    }
+
     pub fn merge(&mut self, other: &Agg) {
+        // A module skipped in either side is incomplete in the merge.
+        self.skip = self.skip.union(other.skip);
+        self.filtered_reads += other.filtered_reads;
+        self.invalid_qual_bytes += other.invalid_qual_bytes;
+        self.malformed_records += other.malformed_records;
+        self.ws_trimmed_records += other.ws_trimmed_records;
+        if let (Some(a), Some(b)) = (&mut self.softmasked_bases, other.softmasked_bases) {
+            *a += b;
+        }
+        self.read_ids.merge(&other.read_ids);
+        if self.first_invalid_qual_record.is_none() {
+            self.first_invalid_qual_record = other.first_invalid_qual_record;
+        }
         self.total_reads += other.total_reads;
         self.total_bases += other.total_bases;
         self.gc_bases += other.gc_bases;
+        self.a_bases += other.a_bases;
+        self.c_bases += other.c_bases;
+        self.g_bases += other.g_bases;
+        self.t_bases += other.t_bases;
         self.n_bases += other.n_bases;
+        self.other_bases += other.other_bases;
         if other.min_len < self.min_len {
             self.min_len = other.min_len;
         }
@@ -447,6 +975,15 @@ impl Agg {
                 target[q] += hist[q];
             }
         }
+        if self.qual_reservoirs.len() < other.qual_reservoirs.len() {
+            self.qual_reservoirs.resize_with(
+                other.qual_reservoirs.len(),
+                qual_reservoir::QualReservoir::default,
+            );
+        }
+        for (i, res) in other.qual_reservoirs.iter().enumerate() {
+            self.qual_reservoirs[i].merge(res);
+        }
         for (i, bc) in other.per_pos_base.iter().enumerate() {
             self.per_pos_base[i].add_assign(bc);
         }
@@ -456,10 +993,25 @@ impl Agg {
         for i in 0..self.per_seq_gc_hist.len() {
             self.per_seq_gc_hist[i] += other.per_seq_gc_hist[i];
         }
+        if self.per_seq_gc_fine.len() < other.per_seq_gc_fine.len() {
+            self.per_seq_gc_fine.resize(other.per_seq_gc_fine.len(), 0);
+        }
+        for (i, &c) in other.per_seq_gc_fine.iter().enumerate() {
+            self.per_seq_gc_fine[i] += c;
+        }
         for i in 0..self.per_seq_n_hist.len() {
             self.per_seq_n_hist[i] += other.per_seq_n_hist[i];
         }
+        for i in 0..self.n_runs.len() {
+            self.n_runs[i] += other.n_runs[i];
+            self.n_run_bases[i] += other.n_run_bases[i];
+        }
+        for i in 0..self.complexity_hist.len() {
+            self.complexity_hist[i] += other.complexity_hist[i];
+        }
         self.reads_mean_q_lt_20 += other.reads_mean_q_lt_20;
+        self.reads_without_qual += other.reads_without_qual;
+        self.reads_truncated += other.reads_truncated;
         self.reads_n_gt10 += other.reads_n_gt10;
         self.reads_n_gt20 += other.reads_n_gt20;
 
@@ -472,6 +1024,11 @@ impl Agg {
                     self.length_hist[i] += other.length_hist[i];
                 }
                 self.dup_space.merge(&other.dup_space);
+                if let (Some(optical), Some(other_optical)) =
+                    (&mut self.optical_dups, &other.optical_dups)
+                {
+                    optical.merge(other_optical);
+                }
                 self.overrep_space.merge(&other.overrep_space);
                 if self.adapter_counts.len() < other.adapter_counts.len() {
                     self.adapter_counts
@@ -482,6 +1039,13 @@ impl Agg {
                         self.adapter_counts[i][j] += row[j];
                     }
                 }
+                if self.trim_hist.len() < other.trim_hist.len() {
+                    self.trim_hist.resize(other.trim_hist.len(), 0);
+                }
+                for (i, &c) in other.trim_hist.iter().enumerate() {
+                    self.trim_hist[i] += c;
+                }
+                self.trim_bases_removed += other.trim_bases_removed;
                 #[cfg(not(feature = "no-kmer"))]
                 {
                     for b in 0..kmer_content::BINS {
@@ -495,10 +1059,30 @@ impl Agg {
             Mode::Long => {
                 for i in 0..self.long_len_bins.len() {
                     self.long_len_bins[i] += other.long_len_bins[i];
+                    self.long_trimmed_len_bins[i] += other.long_trimmed_len_bins[i];
+                }
+                if self.len_qual_map.len() < other.len_qual_map.len() {
+                    self.len_qual_map.resize(other.len_qual_map.len(), 0);
+                }
+                for (i, &c) in other.len_qual_map.iter().enumerate() {
+                    self.len_qual_map[i] += c;
                 }
                 for i in 0..ADAPTERS.len() {
                     self.adapter_reads_any[i] += other.adapter_reads_any[i];
                 }
+                if self.adapter_pos_reads.len() < other.adapter_pos_reads.len() {
+                    self.adapter_pos_reads
+                        .resize(other.adapter_pos_reads.len(), [0u64; POSITION_BINS.len()]);
+                }
+                for (mine, row) in self
+                    .adapter_pos_reads
+                    .iter_mut()
+                    .zip(&other.adapter_pos_reads)
+                {
+                    for (a, b) in mine.iter_mut().zip(row) {
+                        *a += b;
+                    }
+                }
             }
         }
     }
@@ -514,25 +1098,43 @@ impl Agg {
         } else {
             self.max_len
         };
-        let gc_percent = if self.total_bases == 0 {
-            0
-        } else {
-            ((self.gc_bases * 100) + (self.total_bases / 2)) / self.total_bases
-        } as u32;
-
-        let encoding_str = match ctx.encoding {
-            Encoding::Sanger => "Sanger / Illumina 1.9",
-            Encoding::Illumina15 => "Illumina 1.5",
-        };
+        // Like FastQC, %GC is over called bases: Ns are left out of the
+        // denominator so N-heavy reads do not drag it down.
+        let called_bases = self.total_bases.saturating_sub(self.n_bases);
+        let gc_percent = ((self.gc_bases * 100) + (called_bases / 2))
+            .checked_div(called_bases)
+            .unwrap_or(0) as u32;
 
         let basic = BasicStats {
             file_type: "Conventional base calls",
-            encoding: encoding_str,
+            encoding: ctx.encoding.label(),
             total_sequences: self.total_reads,
-            filtered_sequences: 0,
+            filtered_sequences: self.filtered_reads,
+            invalid_qual_bytes: self.invalid_qual_bytes,
+            first_invalid_qual_record: self.first_invalid_qual_record,
+            malformed_records: self.malformed_records,
+            ws_trimmed_records: self.ws_trimmed_records,
             min_len,
             max_len,
             gc_percent,
+            ambiguous_percent: if self.total_bases == 0 {
+                0.0
+            } else {
+                self.other_bases as f64 * 100.0 / self.total_bases as f64
+            },
+            softmasked_percent: self
+                .softmasked_bases
+                .map(|n| n as f64 * 100.0 / self.total_bases.max(1) as f64),
+            estimated_coverage: ctx
+                .genome_size
+                .map(|size| self.total_bases as f64 / size as f64),
+            file_hash: ctx
+                .file_hash
+                .as_ref()
+                .map(|h| format!("{} {}", h.algorithm.as_str(), h.value)),
+            instruments: read_id::sorted(&self.read_ids.instruments),
+            flowcells: read_id::sorted(&self.read_ids.flowcells),
+            lanes: read_id::sorted(&self.read_ids.lanes),
         };
 
         let mut per_base_qual = Vec::new();
@@ -551,11 +1153,20 @@ impl Agg {
                 } else {
                     sum as f64 / total as f64
                 };
-                let median = quantile_from_hist(hist, 0.5);
-                let lq = quantile_from_hist(hist, 0.25);
-                let uq = quantile_from_hist(hist, 0.75);
-                let p10 = quantile_from_hist(hist, 0.10);
-                let p90 = quantile_from_hist(hist, 0.90);
+                let sample = self
+                    .qual_reservoirs
+                    .get(i)
+                    .filter(|r| !r.is_empty())
+                    .map(|r| r.sorted());
+                let quantile = |q: f64| match &sample {
+                    Some(sorted) => qual_reservoir::quantile_sorted(sorted, q),
+                    None => quantile_from_hist(hist, q),
+                };
+                let median = quantile(0.5);
+                let lq = quantile(0.25);
+                let uq = quantile(0.75);
+                let p10 = quantile(0.10);
+                let p90 = quantile(0.90);
                 per_base_qual.push(PerBaseQualRow {
                     base: i + 1,
                     mean,
@@ -568,6 +1179,33 @@ impl Agg {
             }
         }
 
+        let binned_quality_levels = if ctx.mode == Mode::Short {
+            binned_levels(&self.per_pos_qual)
+        } else {
+            Vec::new()
+        };
+
+        // Phred -> error probability is non-linear, so average the
+        // probabilities over the histogram rather than converting the mean Q.
+        let mut error_rate = Vec::new();
+        if ctx.mode == Mode::Short {
+            for (i, hist) in self.per_pos_qual.iter().enumerate() {
+                let total: u64 = hist.iter().sum();
+                if total == 0 {
+                    continue;
+                }
+                let sum: f64 = hist
+                    .iter()
+                    .enumerate()
+                    .map(|(q, &c)| c as f64 * 10f64.powf(-(q as f64) / 10.0))
+                    .sum();
+                error_rate.push(ErrorRateRow {
+                    base: i + 1,
+                    error_rate: sum / total as f64,
+                });
+            }
+        }
+
         let mut per_seq_qual = Vec::new();
         for (q, &count) in self.per_seq_mean_q_hist.iter().enumerate() {
             if count > 0 {
@@ -578,14 +1216,31 @@ impl Agg {
             }
         }
 
+        let per_seq_qual_mode = mode_from_hist(&self.per_seq_mean_q_hist);
+        let per_seq_qual_stats = per_seq_qual::stats_from_hist(&self.per_seq_mean_q_hist);
+        let per_seq_qual_low_pct = if self.total_reads > 0 {
+            self.reads_mean_q_lt_20 as f64 / self.total_reads as f64 * 100.0
+        } else {
+            0.0
+        };
+
         let mut per_base_content = Vec::new();
         let mut max_deviation: f64 = 0.0;
+        let mut content_bias = None;
         if ctx.mode == Mode::Short {
-            per_base_content.reserve(self.per_pos_base.len());
-            for (i, bc) in self.per_pos_base.iter().enumerate() {
-                let denom = bc.a + bc.c + bc.g + bc.t;
-                let (g, a, t, c) = if denom == 0 {
-                    (0.0, 0.0, 0.0, 0.0)
+            let window = ctx.content_bias_window;
+            let mut head_deviation: f64 = 0.0;
+            let mut tail_deviation: f64 = 0.0;
+            let groups = per_base_content::base_groups(self.per_pos_base.len());
+            per_base_content.reserve(groups.len());
+            for (start, end) in groups {
+                let mut bc = BaseCounts::zero();
+                for pos in &self.per_pos_base[start - 1..end] {
+                    bc.add_assign(pos);
+                }
+                let denom = bc.a + bc.c + bc.g + bc.t + bc.other;
+                let (g, a, t, c, other) = if denom == 0 {
+                    (0.0, 0.0, 0.0, 0.0, 0.0)
                 } else {
                     let d = denom as f64;
                     (
@@ -593,6 +1248,7 @@ impl Agg {
                         bc.a as f64 * 100.0 / d,
                         bc.t as f64 * 100.0 / d,
                         bc.c as f64 * 100.0 / d,
+                        bc.other as f64 * 100.0 / d,
                     )
                 };
                 if denom > 0 {
@@ -606,28 +1262,57 @@ impl Agg {
                         if d > max_deviation {
                             max_deviation = d;
                         }
+                        match window {
+                            Some(n) if start <= n => head_deviation = head_deviation.max(d),
+                            Some(_) => tail_deviation = tail_deviation.max(d),
+                            None => {}
+                        }
                     }
                 }
                 per_base_content.push(PerBaseContentRow {
-                    base: i + 1,
+                    base: per_base_content::group_label(start, end),
+                    start,
                     g,
                     a,
                     t,
                     c,
+                    other,
                 });
             }
+            content_bias = window.map(|window| ContentBias {
+                window,
+                head_deviation,
+                tail_deviation,
+            });
         }
 
         let mut per_seq_gc = Vec::new();
-        for (gc, &count) in self.per_seq_gc_hist.iter().enumerate() {
-            if count > 0 {
-                per_seq_gc.push(PerSeqGcRow {
-                    gc: gc as u8,
-                    count,
-                });
+        if self.per_seq_gc_fine.is_empty() {
+            for (gc, &count) in self.per_seq_gc_hist.iter().enumerate() {
+                if count > 0 {
+                    per_seq_gc.push(PerSeqGcRow {
+                        gc: gc as f64,
+                        count,
+                    });
+                }
+            }
+        } else {
+            let steps = (self.per_seq_gc_fine.len() - 1) as f64;
+            for (bin, &count) in self.per_seq_gc_fine.iter().enumerate() {
+                if count > 0 {
+                    per_seq_gc.push(PerSeqGcRow {
+                        gc: bin as f64 * 100.0 / steps,
+                        count,
+                    });
+                }
             }
         }
 
+        let gc_deviation = ctx
+            .gc_reference
+            .as_ref()
+            .and_then(|r| per_seq_gc::gc_deviation(&self.per_seq_gc_hist, r));
+
         let mut per_base_n = Vec::new();
         let mut max_n_percent: f64 = 0.0;
         if ctx.mode == Mode::Short {
@@ -649,6 +1334,21 @@ impl Agg {
             }
         }
 
+        // (G-C)/(G+C) per position; positions without any G or C read as 0.
+        let mut gc_skew = Vec::new();
+        if ctx.mode == Mode::Short {
+            gc_skew.reserve(self.per_pos_base.len());
+            for (i, bc) in self.per_pos_base.iter().enumerate() {
+                let gc = bc.g + bc.c;
+                let skew = if gc == 0 {
+                    0.0
+                } else {
+                    (bc.g as f64 - bc.c as f64) / gc as f64
+                };
+                gc_skew.push(GcSkewRow { base: i + 1, skew });
+            }
+        }
+
         let mut length_dist = Vec::new();
         let mut long_length = None;
         if ctx.mode == Mode::Short {
@@ -660,6 +1360,7 @@ impl Agg {
         } else {
             long_length = Some(build_long_length(
                 &self.long_len_bins,
+                (!self.skip.adapter_content).then_some(self.long_trimmed_len_bins),
                 self.total_reads,
                 self.total_bases,
                 min_len,
@@ -667,23 +1368,37 @@ impl Agg {
             ));
         }
 
+        let length_quality = self
+            .len_qual_map
+            .chunks_exact(length_quality::QUAL_BINS)
+            .zip(LONG_LEN_LABELS)
+            .map(|(row, label)| {
+                let mut counts = [0u64; length_quality::QUAL_BINS];
+                counts.copy_from_slice(row);
+                LengthQualityRow {
+                    length_bin: label,
+                    counts,
+                }
+            })
+            .collect::<Vec<_>>();
+
         let mut per_base_qual_status = Status::Pass;
         let mut per_seq_qual_status = Status::Pass;
         if ctx.mode == Mode::Short {
             for row in &per_base_qual {
-                if row.median < 20 {
+                if row.median < per_base_qual::MEDIAN_FAIL {
                     per_base_qual_status = Status::Fail;
                     break;
                 }
-                if row.median < 25 {
+                if row.median < per_base_qual::MEDIAN_WARN {
                     per_base_qual_status = Status::Warn;
                 }
             }
+            // FastQC semantics: judge the most frequently observed mean quality.
             if self.total_reads > 0 {
-                let low = self.reads_mean_q_lt_20 as f64 / self.total_reads as f64 * 100.0;
-                if low > 20.0 {
+                if per_seq_qual_mode < 20 {
                     per_seq_qual_status = Status::Fail;
-                } else if low > 10.0 {
+                } else if per_seq_qual_mode < 27 {
                     per_seq_qual_status = Status::Warn;
                 }
             }
@@ -696,14 +1411,33 @@ impl Agg {
             }
         }
 
-        let per_base_content_status = if ctx.mode == Mode::Short {
-            if max_deviation > 20.0 {
-                Status::Fail
-            } else if max_deviation > 10.0 {
-                Status::Warn
-            } else {
-                Status::Pass
+        let qual_absent = self.total_reads > 0 && self.reads_without_qual == self.total_reads;
+        if qual_absent {
+            per_base_qual.clear();
+            per_base_qual_status = Status::NotApplicable;
+            per_seq_qual_status = Status::NotApplicable;
+        }
+        let per_base_qual_low = if ctx.mode == Mode::Short {
+            PerBaseQualLow {
+                below_warn: per_base_qual::positions_below(
+                    &per_base_qual,
+                    per_base_qual::MEDIAN_WARN,
+                ),
+                below_fail: per_base_qual::positions_below(
+                    &per_base_qual,
+                    per_base_qual::MEDIAN_FAIL,
+                ),
             }
+        } else {
+            PerBaseQualLow::default()
+        };
+        let per_base_qual_trend = match ctx.qual_trend_window {
+            Some(window) => per_base_qual::smoothed_means(&per_base_qual, window),
+            None => Vec::new(),
+        };
+
+        let per_base_content_status = if ctx.mode == Mode::Short {
+            deviation_status(max_deviation)
         } else {
             Status::Pass
         };
@@ -742,9 +1476,37 @@ impl Agg {
             }
         }
 
+        let mut sequence_complexity = Vec::new();
+        let mut low_complexity_reads: u64 = 0;
+        for (bin, &count) in self.complexity_hist.iter().enumerate() {
+            sequence_complexity.push(ComplexityRow {
+                entropy: sequence_complexity::bin_entropy(bin),
+                count,
+            });
+            if bin < sequence_complexity::low_entropy_bin() {
+                low_complexity_reads += count;
+            }
+        }
+        let low_complexity_pct = if self.total_reads == 0 {
+            0.0
+        } else {
+            low_complexity_reads as f64 * 100.0 / self.total_reads as f64
+        };
+        let sequence_complexity_status = if low_complexity_pct > 20.0 {
+            Status::Fail
+        } else if low_complexity_pct > 5.0 {
+            Status::Warn
+        } else {
+            Status::Pass
+        };
+
         let mut dup_counts = [0u64; 7];
         let mut tracked_total: u64 = 0;
+        let mut distinct_tracked: u64 = 0;
         for e in self.dup_space.entries() {
+            if e.count > 0 {
+                distinct_tracked += 1;
+            }
             tracked_total += e.count;
             let idx = if e.count >= 7 {
                 6
@@ -758,12 +1520,41 @@ impl Agg {
         }
         let unique_extra = self.total_reads.saturating_sub(tracked_total);
         dup_counts[0] += unique_extra;
+        // FastQC's "Total Deduplicated Percentage": reads left if every
+        // duplicated sequence were collapsed to a single copy.
+        let deduplicated_pct = if self.total_reads == 0 {
+            0.0
+        } else {
+            (distinct_tracked + unique_extra) as f64 * 100.0 / self.total_reads as f64
+        };
+        // Both sketches key reads by the same hash of the full sequence, so
+        // the top sequence can be looked up in the overrepresented sketch
+        // unless that one only sees read prefixes.
+        let top_sequence = self
+            .dup_space
+            .entries()
+            .iter()
+            .max_by_key(|e| e.count)
+            .filter(|e| e.count > 0 && !self.skip.duplication)
+            .map(|top| TopSequence {
+                count: top.count,
+                percent: top.count as f64 * 100.0 / self.total_reads.max(1) as f64,
+                sequence: self
+                    .overrep_space
+                    .entries()
+                    .iter()
+                    .find(|e| self.overrep_prefix_len.is_none() && e.key == top.key)
+                    .map(|e| e.display_seq()),
+            });
 
         let mut duplication = Vec::new();
         let mut overrep = Vec::new();
         let mut adapter_rows = Vec::new();
+        let mut adapter_positions = Vec::new();
+        let mut internal_adapter = false;
         let mut kmer_rows = Vec::new();
         let mut kmer_status = Status::Pass;
+        let mut kmer_saturated = false;
         let total_reads = self.total_reads.max(1);
         let mut duplication_status = Status::Pass;
         let mut overrep_status = Status::Pass;
@@ -787,7 +1578,7 @@ impl Agg {
                 });
             }
 
-            let duplicated_reads = total_reads.saturating_sub(dup_counts[0]);
+            let duplicated_reads = self.total_reads.saturating_sub(dup_counts[0]);
             let duplicated_pct = duplicated_reads as f64 * 100.0 / total_reads as f64;
             duplication_status = if duplicated_pct > 80.0 {
                 Status::Fail
@@ -799,21 +1590,31 @@ impl Agg {
 
             let mut warn_hit = false;
             for e in self.overrep_space.entries() {
-                if e.count == 0 {
+                // Skip estimates that are mostly eviction error: the
+                // guaranteed count (count - error) is below the error bound.
+                if e.count == 0 || e.error > e.count - e.error {
                     continue;
                 }
                 let pct = e.count as f64 * 100.0 / total_reads as f64;
-                if pct >= 0.1 {
-                    let seq = String::from_utf8_lossy(&e.seq).to_string();
-                    let source = overrepresented::classify_source(&e.seq);
+                if pct >= ctx.overrep_threshold_pct {
+                    let seq = e.display_seq();
+                    let mut source = overrepresented::classify_source(&e.seq);
+                    // A collapsed entry keeps whichever strand sorts first,
+                    // which need not be the one the adapter list holds.
+                    if self.collapse_revcomp && source == overrepresented::NO_HIT {
+                        let mut rc = Vec::new();
+                        simd::reverse_complement(&e.seq, &mut rc);
+                        source = overrepresented::classify_source(&rc);
+                    }
                     overrep.push(OverrepRow {
                         sequence: seq,
                         count: e.count,
+                        error: e.error,
                         percent: pct,
                         source,
                     });
                     overrep_status = Status::Fail;
-                } else if pct >= 0.05 {
+                } else if pct >= ctx.overrep_threshold_pct / 2.0 {
                     warn_hit = true;
                 }
             }
@@ -825,11 +1626,19 @@ impl Agg {
                     .cmp(&a.count)
                     .then_with(|| a.sequence.cmp(&b.sequence))
             });
+            if let Some(max_rows) = ctx.max_overrep_rows {
+                overrep.truncate(max_rows);
+            }
 
+            // adapter_counts holds each read's first match start per adapter;
+            // like FastQC, report the cumulative share of reads with adapter
+            // at or before each position, so the curve only ever rises.
+            let mut cumulative = [0u64; ADAPTERS.len()];
             for (i, row) in self.adapter_counts.iter().enumerate() {
                 let mut values = [0.0f64; ADAPTERS.len()];
                 for j in 0..ADAPTERS.len() {
-                    let pct = row[j] as f64 * 100.0 / total_reads as f64;
+                    cumulative[j] += row[j];
+                    let pct = cumulative[j] as f64 * 100.0 / total_reads as f64;
                     values[j] = pct;
                     if pct > 10.0 {
                         adapter_status = Status::Fail;
@@ -854,7 +1663,11 @@ impl Agg {
                 for key in keys {
                     let mut total_est: u64 = 0;
                     for b in 0..kmer_content::BINS {
-                        total_est += self.kmer_cms[b].estimate(key) as u64;
+                        let est = self.kmer_cms[b].estimate(key);
+                        if est == kmer_content::CMS_SATURATED {
+                            kmer_saturated = true;
+                        }
+                        total_est += est as u64;
                     }
                     if total_est == 0 {
                         continue;
@@ -895,7 +1708,12 @@ impl Agg {
                         });
                     }
                 }
-                kmer_content::select_top(&mut kmer_rows);
+                kmer_content::select_top(&mut kmer_rows, ctx.max_kmer_rows);
+                // Saturated counters understate enrichment, so a clean result
+                // can't be trusted.
+                if kmer_saturated && kmer_status == Status::Pass {
+                    kmer_status = Status::Warn;
+                }
             }
         } else {
             let mut values = [0.0f64; ADAPTERS.len()];
@@ -912,37 +1730,172 @@ impl Agg {
                 position: 1,
                 values,
             });
+            if !self.adapter_pos_reads.is_empty() {
+                for (b, &bin) in POSITION_BINS.iter().enumerate() {
+                    let values = std::array::from_fn(|i| {
+                        self.adapter_pos_reads[i][b] as f64 * 100.0 / total_reads as f64
+                    });
+                    adapter_positions.push(AdapterPositionRow { bin, values });
+                }
+                // Adapters inside reads point to chimeras rather than
+                // read-through, so flag them even when the totals pass.
+                internal_adapter = adapter_positions[1]
+                    .values
+                    .iter()
+                    .any(|&pct| pct > INTERNAL_ADAPTER_WARN_PCT);
+                if internal_adapter && adapter_status == Status::Pass {
+                    adapter_status = Status::Warn;
+                }
+            }
+        }
+
+        // A dominant overrepresented sequence with no known source is often an
+        // adapter missing from ADAPTERS; flag it on the adapter module too.
+        let uncatalogued_adapter = overrep
+            .iter()
+            .any(|r| r.source == overrepresented::NO_HIT && r.percent > 1.0);
+        if uncatalogued_adapter && adapter_status == Status::Pass {
+            adapter_status = Status::Warn;
         }
 
+        let bases_retained = self.total_bases.saturating_sub(self.trim_bases_removed);
+        let trim_preview = TrimPreview {
+            reads_trimmed: self.trim_hist.iter().sum(),
+            bases_removed: self.trim_bases_removed,
+            bases_retained,
+            mean_trimmed_length: if self.total_reads == 0 {
+                0.0
+            } else {
+                bases_retained as f64 / self.total_reads as f64
+            },
+            rows: self
+                .trim_hist
+                .iter()
+                .enumerate()
+                .filter(|&(_, &reads)| reads > 0)
+                .map(|(removed, &reads)| TrimRow { removed, reads })
+                .collect(),
+        };
+
+        let pct_of_bases = |v: u64| {
+            if self.total_bases == 0 {
+                0.0
+            } else {
+                v as f64 * 100.0 / self.total_bases as f64
+            }
+        };
+        let base_composition = BaseComposition {
+            a: self.a_bases,
+            c: self.c_bases,
+            g: self.g_bases,
+            t: self.t_bases,
+            at_gc_ratio: if self.gc_bases == 0 {
+                0.0
+            } else {
+                (self.a_bases + self.t_bases) as f64 / self.gc_bases as f64
+            },
+            at_imbalance_pct: pct_of_bases(self.a_bases.abs_diff(self.t_bases)),
+            gc_imbalance_pct: pct_of_bases(self.g_bases.abs_diff(self.c_bases)),
+        };
+        let base_composition_status = if base_composition.at_imbalance_pct > ctx.strand_bias_pct
+            || base_composition.gc_imbalance_pct > ctx.strand_bias_pct
+        {
+            Status::Warn
+        } else {
+            Status::Pass
+        };
+
         let statuses = Statuses {
-            basic: Status::Pass,
+            basic: match basic.estimated_coverage {
+                _ if basic.invalid_qual_bytes > 0
+                    || basic.malformed_records > 0
+                    || basic.flowcells.len() > 1 =>
+                {
+                    Status::Warn
+                }
+                Some(coverage) if coverage < LOW_COVERAGE => Status::Warn,
+                _ => Status::Pass,
+            },
             per_base_qual: per_base_qual_status,
             per_seq_qual: per_seq_qual_status,
+            error_rate: if qual_absent {
+                Status::NotApplicable
+            } else {
+                Status::Pass
+            },
             per_base_content: per_base_content_status,
-            per_seq_gc: Status::Pass,
+            per_seq_gc: match &gc_deviation {
+                Some(d) if d.deviation_pct > per_seq_gc::GC_DEVIATION_FAIL_PCT => Status::Fail,
+                Some(d) if d.deviation_pct > per_seq_gc::GC_DEVIATION_WARN_PCT => Status::Warn,
+                _ => Status::Pass,
+            },
             per_base_n: per_base_n_status,
+            gc_skew: Status::Pass,
+            trim_preview: Status::Pass,
+            length_quality: if qual_absent {
+                Status::NotApplicable
+            } else {
+                Status::Pass
+            },
             length_dist: Status::Pass,
             duplication: duplication_status,
             overrepresented: overrep_status,
             adapter_content: adapter_status,
             per_seq_n: per_seq_n_status,
             kmer_content: kmer_status,
+            sequence_complexity: sequence_complexity_status,
+            base_composition: base_composition_status,
         };
 
         FinalMetrics {
             basic,
             per_base_qual,
+            per_base_qual_trend,
+            per_base_qual_low,
+            binned_quality_levels,
             per_seq_qual,
+            per_seq_qual_mode,
+            per_seq_qual_low_pct,
+            per_seq_qual_stats,
+            n_runs: NRunSummary {
+                runs: self.n_runs,
+                bases: self.n_run_bases,
+            },
             per_base_content,
+            content_bias,
             per_seq_gc,
+            gc_deviation,
             per_base_n,
+            gc_skew,
+            error_rate,
             length_dist,
             duplication,
+            deduplicated_pct,
+            top_sequence,
+            dup_saturation: self.dup_saturation(),
+            optical_dups: self
+                .optical_dups
+                .as_ref()
+                .filter(|o| !self.skip.duplication && o.sampled_reads > 0)
+                .map(|o| o.summary()),
             overrepresented: overrep,
             adapter_content: adapter_rows,
+            trim_preview,
+            base_composition,
             per_seq_n,
             long_length,
+            length_quality,
             kmer_rows,
+            kmer_saturated,
+            uncatalogued_adapter,
+            adapter_positions,
+            internal_adapter,
+            sequence_complexity,
+            low_complexity_pct,
+            max_read_len: self.max_read_len,
+            reads_truncated: self.reads_truncated,
+            suggested_mode: self.suggested_mode(),
+            skipped: self.skip,
             statuses,
         }
     }
@@ -955,34 +1908,196 @@ pub struct Statuses {
     pub per_base_content: Status,
     pub per_seq_gc: Status,
     pub per_base_n: Status,
+    pub gc_skew: Status,
+    pub error_rate: Status,
+    pub trim_preview: Status,
+    pub length_quality: Status,
     pub length_dist: Status,
     pub duplication: Status,
     pub overrepresented: Status,
     pub adapter_content: Status,
     pub per_seq_n: Status,
     pub kmer_content: Status,
+    pub sequence_complexity: Status,
+    pub base_composition: Status,
 }
 
 pub struct FinalMetrics {
     pub basic: BasicStats,
     pub per_base_qual: Vec<PerBaseQualRow>,
+    // Smoothed per_base_qual means (--qual-trend-window); empty when off.
+    pub per_base_qual_trend: Vec<f64>,
+    pub per_base_qual_low: PerBaseQualLow,
+    // Quality values seen across all positions when there are few enough of
+    // them to look binned; empty otherwise.
+    pub binned_quality_levels: Vec<u8>,
     pub per_seq_qual: Vec<PerSeqQualRow>,
+    pub per_seq_qual_mode: u8,
+    pub per_seq_qual_low_pct: f64,
+    pub per_seq_qual_stats: PerSeqQualStats,
+    pub n_runs: NRunSummary,
     pub per_base_content: Vec<PerBaseContentRow>,
+    // Short mode with --content-bias-window only.
+    pub content_bias: Option<ContentBias>,
     pub per_seq_gc: Vec<PerSeqGcRow>,
+    // Distance from the expected GC; None without --expected-gc or
+    // --reference-gc.
+    pub gc_deviation: Option<GcDeviation>,
     pub per_base_n: Vec<PerBaseNRow>,
+    pub gc_skew: Vec<GcSkewRow>,
+    pub error_rate: Vec<ErrorRateRow>,
     pub length_dist: Vec<LengthDistRow>,
     pub duplication: Vec<DuplicationRow>,
+    pub deduplicated_pct: f64,
+    // None when duplication is skipped or no reads were seen.
+    pub top_sequence: Option<TopSequence>,
+    // Set when the duplication sketch churned enough that its levels
+    // understate duplication.
+    pub dup_saturation: Option<DupSaturation>,
+    // None unless --optical-dup-distance is set and some sampled reads had
+    // Illumina coordinates.
+    pub optical_dups: Option<OpticalDupSummary>,
     pub overrepresented: Vec<OverrepRow>,
     pub adapter_content: Vec<AdapterRow>,
+    pub trim_preview: TrimPreview,
+    pub base_composition: BaseComposition,
     pub per_seq_n: Vec<PerSeqNRow>,
     pub long_length: Option<LongLengthSummary>,
+    // Empty unless the aggregate was built with --length-quality-map.
+    pub length_quality: Vec<LengthQualityRow>,
     pub kmer_rows: Vec<KmerRow>,
+    // Some k-mer count hit the u32 ceiling of the count-min sketch.
+    pub kmer_saturated: bool,
+    // An overrepresented "No Hit" sequence exceeds 1% of reads.
+    pub uncatalogued_adapter: bool,
+    // Long mode, --adapter-positions: one row per POSITION_BINS bin.
+    pub adapter_positions: Vec<AdapterPositionRow>,
+    // Some adapter sits mid-read in more than INTERNAL_ADAPTER_WARN_PCT% of
+    // reads.
+    pub internal_adapter: bool,
+    pub sequence_complexity: Vec<ComplexityRow>,
+    pub low_complexity_pct: f64,
+    pub max_read_len: usize,
+    pub reads_truncated: u64,
+    // The other mode, when the median read length says --mode is wrong
+    // (see Agg::suggested_mode).
+    pub suggested_mode: Option<Mode>,
+    pub skipped: SkipModules,
     pub statuses: Statuses,
 }
 
+// Distinct quality values with any count across `hists`, if there are at
+// most BINNED_QUAL_MAX_LEVELS of them.
+fn binned_levels(hists: &[QualHist]) -> Vec<u8> {
+    let mut seen = [false; MAX_Q + 1];
+    for hist in hists {
+        for (q, &c) in hist.iter().enumerate() {
+            seen[q] |= c > 0;
+        }
+    }
+    let levels: Vec<u8> = (0..=MAX_Q).filter(|&q| seen[q]).map(|q| q as u8).collect();
+    if levels.is_empty() || levels.len() > BINNED_QUAL_MAX_LEVELS {
+        return Vec::new();
+    }
+    levels
+}
+
+pub struct ModuleStatus {
+    // Short machine-readable key (e.g. "per_base_quality").
+    pub key: &'static str,
+    // FastQC module title as written to summary.txt.
+    pub title: &'static str,
+    pub status: Status,
+}
+
+impl FinalMetrics {
+    // Modules reported for `mode`, in report order.
+    pub fn module_statuses(&self, mode: Mode) -> impl Iterator<Item = ModuleStatus> {
+        let s = &self.statuses;
+        let module = |key, title, status| ModuleStatus { key, title, status };
+        let mut modules = vec![module("basic", "Basic Statistics", s.basic)];
+        match mode {
+            Mode::Short => {
+                modules.extend([
+                    module(
+                        "per_base_quality",
+                        "Per base sequence quality",
+                        s.per_base_qual,
+                    ),
+                    module("error_rate", "Per base error rate", s.error_rate),
+                    module(
+                        "per_seq_quality",
+                        "Per sequence quality scores",
+                        s.per_seq_qual,
+                    ),
+                    module(
+                        "per_base_content",
+                        "Per base sequence content",
+                        s.per_base_content,
+                    ),
+                    module("per_seq_gc", "Per sequence GC content", s.per_seq_gc),
+                    module("per_base_n", "Per base N content", s.per_base_n),
+                    module("gc_skew", "Per base GC skew", s.gc_skew),
+                    module("length_dist", "Sequence Length Distribution", s.length_dist),
+                    module("duplication", "Sequence Duplication Levels", s.duplication),
+                    module(
+                        "overrepresented",
+                        "Overrepresented sequences",
+                        s.overrepresented,
+                    ),
+                    module("adapter_content", "Adapter Content", s.adapter_content),
+                    module("trim_preview", "Adapter Trimming Preview", s.trim_preview),
+                    module(
+                        "sequence_complexity",
+                        "Sequence Complexity",
+                        s.sequence_complexity,
+                    ),
+                    module("base_composition", "Base Composition", s.base_composition),
+                ]);
+                #[cfg(not(feature = "no-kmer"))]
+                modules.push(module("kmer_content", "Kmer Content", s.kmer_content));
+            }
+            Mode::Long => {
+                modules.push(module(
+                    "length_dist",
+                    "Sequence Length Distribution",
+                    s.length_dist,
+                ));
+                modules.push(module(
+                    "per_seq_quality",
+                    "Per sequence quality scores",
+                    s.per_seq_qual,
+                ));
+                if !self.length_quality.is_empty() {
+                    modules.push(module(
+                        "length_quality",
+                        "Length vs Quality",
+                        s.length_quality,
+                    ));
+                }
+                modules.extend([
+                    module("per_seq_gc", "Per sequence GC content", s.per_seq_gc),
+                    module("per_seq_n", "Per sequence N content", s.per_seq_n),
+                    module("adapter_content", "Adapter Content", s.adapter_content),
+                    module(
+                        "sequence_complexity",
+                        "Sequence Complexity",
+                        s.sequence_complexity,
+                    ),
+                    module("base_composition", "Base Composition", s.base_composition),
+                ]);
+            }
+        }
+        let skipped = self.skipped;
+        modules.into_iter().filter(move |m| !skipped.skips(m.key))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct LongLengthSummary {
     pub bins: [u64; 8],
+    // Read lengths after adapter trimming; None with --skip adapter.
+    pub trimmed_bins: Option<[u64; 8]>,
     pub labels: [&'static str; 8],
     pub mean: f64,
     pub n50: u64,
@@ -991,6 +2106,31 @@ pub struct LongLengthSummary {
     pub max: u32,
 }
 
+fn mode_from_hist(hist: &[u64]) -> u8 {
+    let mut best = 0usize;
+    for (i, &v) in hist.iter().enumerate() {
+        if v > hist[best] {
+            best = i;
+        }
+    }
+    best as u8
+}
+
+const SHORT_MODE_MAX_MEDIAN: usize = 1_000;
+// Must stay a long_len_bin boundary.
+const LONG_MODE_MIN_MEDIAN: u64 = 100;
+
+const LONG_LEN_LABELS: [&str; 8] = [
+    "1-9",
+    "10-99",
+    "100-999",
+    "1k-9k",
+    "10k-99k",
+    "100k-999k",
+    "1M-9M",
+    "10M+",
+];
+
 fn long_len_bin(len: u64) -> usize {
     match len {
         0..=9 => 0,
@@ -1006,21 +2146,12 @@ fn long_len_bin(len: u64) -> usize {
 
 fn build_long_length(
     bins: &[u64; 8],
+    trimmed_bins: Option<[u64; 8]>,
     total_reads: u64,
     total_bases: u64,
     min: u32,
     max: u32,
 ) -> LongLengthSummary {
-    let labels = [
-        "1-9",
-        "10-99",
-        "100-999",
-        "1k-9k",
-        "10k-99k",
-        "100k-999k",
-        "1M-9M",
-        "10M+",
-    ];
     let mean = if total_reads == 0 {
         0.0
     } else {
@@ -1030,7 +2161,8 @@ fn build_long_length(
     let n90 = approx_nxx(bins, total_bases, 0.9);
     LongLengthSummary {
         bins: *bins,
-        labels,
+        trimmed_bins,
+        labels: LONG_LEN_LABELS,
         mean,
         n50,
         n90,
@@ -1052,3 +2184,246 @@ fn approx_nxx(bins: &[u64; 8], total_bases: u64, frac: f64) -> u64 {
     }
     0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::model::Encoding;
+
+    fn config(mode: Mode) -> AggConfig {
+        AggConfig {
+            mode,
+            max_read_len: 1000,
+            overrep_max_len: 100,
+            overrep_prefix_len: None,
+            optical_dup_distance: None,
+            length_quality_map: false,
+            adapter_positions: false,
+            gc_resolution: GcResolution::Percent,
+            exact_quantiles: false,
+            seed: 0,
+            track_softmask: false,
+            collapse_revcomp: false,
+            skip: SkipModules::default(),
+            memory: MemoryProfile::Low,
+        }
+    }
+
+    fn ctx(mode: Mode) -> FinalizeContext {
+        FinalizeContext {
+            phred_offset: 33,
+            encoding: Encoding::Illumina18,
+            file_name: "test.fastq".to_string(),
+            sample_name: "test".to_string(),
+            mode,
+            subsampled: None,
+            truncated: None,
+            title: None,
+            description: None,
+            genome_size: None,
+            strand_bias_pct: DEFAULT_STRAND_BIAS_PCT,
+            content_bias_window: None,
+            qual_trend_window: None,
+            overrep_threshold_pct: DEFAULT_OVERREP_THRESHOLD_PCT,
+            max_overrep_rows: None,
+            max_kmer_rows: DEFAULT_MAX_KMER_ROWS,
+            gc_reference: None,
+            file_hash: None,
+        }
+    }
+
+    // Adds `count` copies of `seq` with every base at quality `q`.
+    fn add_reads(agg: &mut Agg, count: usize, seq: &[u8], q: u8) {
+        let qual = vec![q + 33; seq.len()];
+        for _ in 0..count {
+            let read = ReadView {
+                id: b"r",
+                seq,
+                qual: &qual,
+            };
+            agg.update_read(&read, 33);
+        }
+    }
+
+    #[test]
+    fn per_seq_quality_status_follows_the_modal_mean_quality() {
+        let seq = b"ACGTTGCAACGTTGCAACGTTGCAACGTTGCAACGTTGCAACGTTGCAAC";
+
+        // 40% of reads below Q20, but most reads sit at Q35.
+        let mut agg = Agg::new(&config(Mode::Short));
+        add_reads(&mut agg, 60, seq, 35);
+        add_reads(&mut agg, 40, seq, 10);
+        let metrics = agg.finalize(&ctx(Mode::Short));
+        assert_eq!(metrics.per_seq_qual_mode, 35);
+        assert_eq!(metrics.per_seq_qual_low_pct, 40.0);
+        assert_eq!(metrics.statuses.per_seq_qual, Status::Pass);
+
+        // A second peak at Q38 does not rescue a Q15 majority.
+        let mut agg = Agg::new(&config(Mode::Short));
+        add_reads(&mut agg, 55, seq, 15);
+        add_reads(&mut agg, 45, seq, 38);
+        let metrics = agg.finalize(&ctx(Mode::Short));
+        assert_eq!(metrics.per_seq_qual_mode, 15);
+        assert_eq!(metrics.statuses.per_seq_qual, Status::Fail);
+
+        // Modal Q24: between the fail and warn cutoffs.
+        let mut agg = Agg::new(&config(Mode::Short));
+        add_reads(&mut agg, 30, seq, 24);
+        add_reads(&mut agg, 20, seq, 36);
+        add_reads(&mut agg, 20, seq, 37);
+        let metrics = agg.finalize(&ctx(Mode::Short));
+        assert_eq!(metrics.per_seq_qual_mode, 24);
+        assert_eq!(metrics.statuses.per_seq_qual, Status::Warn);
+    }
+
+    #[test]
+    fn unknown_overrepresented_sequence_warns_on_adapter_content() {
+        const SYNTHETIC: &[u8] = b"TTAGGCATCCGATCAGTCCATGGTTAACCGGTACGTAGCA";
+        assert_eq!(
+            overrepresented::classify_source(SYNTHETIC),
+            overrepresented::NO_HIT
+        );
+
+        let mut state = 99u32;
+        let mut random_read = || -> Vec<u8> {
+            (0..SYNTHETIC.len())
+                .map(|_| {
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    b"ACGT"[(state >> 16) as usize % 4]
+                })
+                .collect()
+        };
+        let mut agg = Agg::new(&config(Mode::Short));
+        for _ in 0..200 {
+            add_reads(&mut agg, 1, &random_read(), 30);
+        }
+        let metrics = agg.finalize(&ctx(Mode::Short));
+        assert!(!metrics.uncatalogued_adapter);
+        assert_eq!(metrics.statuses.adapter_content, Status::Pass);
+
+        add_reads(&mut agg, 20, SYNTHETIC, 30);
+        let metrics = agg.finalize(&ctx(Mode::Short));
+        assert!(metrics.uncatalogued_adapter);
+        assert_eq!(metrics.statuses.adapter_content, Status::Warn);
+        let row = &metrics.overrepresented[0];
+        assert_eq!(row.sequence.as_bytes(), SYNTHETIC);
+        assert_eq!(row.source, overrepresented::NO_HIT);
+    }
+
+    #[test]
+    fn repeated_adapter_counts_once_per_read() {
+        let adapter = adapter_content::ADAPTERS[0].as_bytes();
+        let mut read = b"ACGTTGCA".to_vec();
+        for _ in 0..5 {
+            read.extend_from_slice(adapter);
+        }
+        let mut agg = Agg::new(&config(Mode::Short));
+        add_reads(&mut agg, 10, &read, 30);
+        let metrics = agg.finalize(&ctx(Mode::Short));
+        let values: Vec<f64> = metrics
+            .adapter_content
+            .iter()
+            .map(|row| row.values[0])
+            .collect();
+        assert!(values.iter().all(|&v| v <= 100.0));
+        assert_eq!(values.last(), Some(&100.0));
+        assert_eq!(values[7], 0.0);
+        assert_eq!(values[8], 100.0);
+    }
+
+    #[test]
+    fn per_read_mean_quality_rounds_only_when_binned() {
+        let mut agg = Agg::new(&config(Mode::Short));
+        for (count, qual) in [(6, b"45554"), (4, b"<=<=<")] {
+            for _ in 0..count {
+                let read = ReadView {
+                    id: b"r",
+                    seq: b"ACGTA",
+                    qual,
+                };
+                agg.update_read(&read, 33);
+            }
+        }
+        let metrics = agg.finalize(&ctx(Mode::Short));
+        // Means of 19.6 and 27.4 land in bins 20 and 27, but the 19.6 reads
+        // still count as below Q20.
+        let hist: Vec<(u8, u64)> = metrics
+            .per_seq_qual
+            .iter()
+            .filter(|row| row.count > 0)
+            .map(|row| (row.mean_q, row.count))
+            .collect();
+        assert_eq!(hist, [(20, 6), (27, 4)]);
+        assert_eq!(metrics.per_seq_qual_mode, 20);
+        assert_eq!(metrics.per_seq_qual_low_pct, 60.0);
+    }
+
+    #[test]
+    fn exact_quantiles_agree_with_the_histogram_on_integer_qualities() {
+        let mut hist_agg = Agg::new(&config(Mode::Short));
+        let mut exact_agg = Agg::new(&AggConfig {
+            exact_quantiles: true,
+            ..config(Mode::Short)
+        });
+        let seq = [b'A'; 30];
+        for i in 0..500usize {
+            let id = format!("r{i}");
+            let qual: Vec<u8> = (0..30)
+                .map(|pos| 33 + ((i * 7 + pos * 3) % 41) as u8)
+                .collect();
+            let read = ReadView {
+                id: id.as_bytes(),
+                seq: &seq,
+                qual: &qual,
+            };
+            hist_agg.update_read(&read, 33);
+            exact_agg.update_read(&read, 33);
+        }
+        let hist = hist_agg.finalize(&ctx(Mode::Short)).per_base_qual;
+        let exact = exact_agg.finalize(&ctx(Mode::Short)).per_base_qual;
+        assert_eq!(hist.len(), exact.len());
+        for (h, e) in hist.iter().zip(&exact) {
+            assert_eq!(
+                (h.p10, h.lower_quartile, h.median, h.upper_quartile, h.p90),
+                (e.p10, e.lower_quartile, e.median, e.upper_quartile, e.p90),
+                "base {}",
+                h.base
+            );
+        }
+    }
+
+    #[cfg(not(feature = "no-kmer"))]
+    #[test]
+    fn saturated_kmer_counter_flags_the_module() {
+        let mut agg = Agg::new(&config(Mode::Short));
+        add_reads(&mut agg, 10, b"ACGTTGCAACGTTGCAACGTTGCA", 30);
+        assert!(!agg.finalize(&ctx(Mode::Short)).kmer_saturated);
+
+        // One k-mer counted past u32::MAX in every position bin, as on a
+        // multi-billion-read file: no bin stands out, but the counts are
+        // only lower bounds.
+        let key = kmer_content::encode_kmer(b"ACGTTGC").unwrap();
+        for bin in 0..kmer_content::BINS {
+            agg.kmer_cms[bin].add(key, u32::MAX);
+            agg.kmer_cms[bin].add(key, 1);
+            agg.kmer_hh[bin].add(key, u32::MAX as u64 + 1);
+            agg.kmer_bin_counts[bin] += u32::MAX as u64 + 1;
+        }
+        agg.kmer_total += kmer_content::BINS as u64 * (u32::MAX as u64 + 1);
+        let metrics = agg.finalize(&ctx(Mode::Short));
+        assert!(metrics.kmer_saturated);
+        assert_ne!(metrics.statuses.kmer_content, Status::Pass);
+    }
+
+    #[test]
+    fn gc_percent_leaves_n_out_of_the_denominator() {
+        let mut agg = Agg::new(&config(Mode::Short));
+        add_reads(&mut agg, 1, b"GGGGNNNN", 30);
+        assert_eq!(agg.finalize(&ctx(Mode::Short)).basic.gc_percent, 100);
+
+        let mut agg = Agg::new(&config(Mode::Short));
+        add_reads(&mut agg, 1, b"GCATNNNN", 30);
+        add_reads(&mut agg, 1, b"NNNNNNNN", 30);
+        assert_eq!(agg.finalize(&ctx(Mode::Short)).basic.gc_percent, 50);
+    }
+}
@@ -0,0 +1,138 @@
+use super::read_id::IlluminaName;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Sequences are sampled by hash (1 in SAMPLE_MOD), so every copy of a
+// sampled sequence is seen and the sample stays unbiased across chunks.
+const SAMPLE_MOD: u64 = 16;
+// Sampled sequences tracked at most; later new sequences are ignored.
+const MAX_SEQUENCES: usize = 1 << 20;
+// Coordinates kept per sequence; copies past this still count as duplicates
+// but are not checked for optical distance.
+const MAX_COORDS: usize = 256;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Coord {
+    // duplication::hash_seq of the `<instrument>:<run>:<flowcell>:<lane>:<tile>`
+    // name prefix.
+    tile: u64,
+    x: u32,
+    y: u32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Copies {
+    count: u64,
+    coords: Vec<Coord>,
+}
+
+// Optical duplicate estimate for --optical-dup-distance: copies of one
+// sequence on the same tile within `distance` pixels of another copy are
+// counted as optical (cluster-calling) duplicates, the rest as library (PCR)
+// duplicates.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpticalDups {
+    pub distance: u32,
+    sequences: HashMap<u64, Copies>,
+    // Sampled reads, and those skipped for lacking parseable coordinates.
+    pub sampled_reads: u64,
+    pub unparsed_reads: u64,
+}
+
+pub struct OpticalDupSummary {
+    pub distance: u32,
+    pub sampled_reads: u64,
+    // Sampled reads that are a second or later copy of their sequence.
+    pub duplicate_reads: u64,
+    // Estimated optical share of duplicate_reads, from the copies whose
+    // coordinates were kept.
+    pub optical_reads: f64,
+}
+
+impl OpticalDups {
+    pub fn new(distance: u32) -> Self {
+        Self {
+            distance,
+            sequences: HashMap::new(),
+            sampled_reads: 0,
+            unparsed_reads: 0,
+        }
+    }
+
+    // `seq_hash` is duplication::hash_seq of the read sequence.
+    pub fn add(&mut self, seq_hash: u64, id: &[u8]) {
+        if !seq_hash.is_multiple_of(SAMPLE_MOD) {
+            return;
+        }
+        let Some(name) = IlluminaName::parse(id) else {
+            self.unparsed_reads += 1;
+            return;
+        };
+        let coord = Coord {
+            tile: super::duplication::hash_seq(name.tile_id),
+            x: name.x,
+            y: name.y,
+        };
+        self.insert(seq_hash, 1, std::iter::once(coord));
+    }
+
+    pub fn merge(&mut self, other: &OpticalDups) {
+        self.unparsed_reads += other.unparsed_reads;
+        let mut keys: Vec<u64> = other.sequences.keys().copied().collect();
+        keys.sort_unstable();
+        for key in keys {
+            let copies = &other.sequences[&key];
+            self.insert(key, copies.count, copies.coords.iter().cloned());
+        }
+    }
+
+    fn insert(&mut self, key: u64, count: u64, coords: impl Iterator<Item = Coord>) {
+        if self.sequences.len() >= MAX_SEQUENCES && !self.sequences.contains_key(&key) {
+            return;
+        }
+        self.sampled_reads += count;
+        let copies = self.sequences.entry(key).or_default();
+        copies.count += count;
+        let room = MAX_COORDS.saturating_sub(copies.coords.len());
+        copies.coords.extend(coords.take(room));
+    }
+
+    pub fn summary(&self) -> OpticalDupSummary {
+        let limit = u64::from(self.distance);
+        let mut duplicate_reads = 0u64;
+        let mut checked_dups = 0u64;
+        let mut optical = 0u64;
+        for copies in self.sequences.values() {
+            if copies.count < 2 {
+                continue;
+            }
+            duplicate_reads += copies.count - 1;
+            let mut coords: Vec<&Coord> = copies.coords.iter().collect();
+            coords.sort_unstable_by_key(|c| (c.tile, c.x, c.y));
+            checked_dups += coords.len().saturating_sub(1) as u64;
+            // A copy is optical when an earlier copy (in tile, x, y order)
+            // lies within the distance on both axes, as in Picard.
+            for (i, c) in coords.iter().enumerate().skip(1) {
+                let near = coords[..i]
+                    .iter()
+                    .rev()
+                    .take_while(|p| p.tile == c.tile && u64::from(c.x - p.x) <= limit)
+                    .any(|p| u64::from(c.y.abs_diff(p.y)) <= limit);
+                if near {
+                    optical += 1;
+                }
+            }
+        }
+        let optical_reads = if checked_dups == 0 {
+            0.0
+        } else {
+            optical as f64 * duplicate_reads as f64 / checked_dups as f64
+        };
+        OpticalDupSummary {
+            distance: self.distance,
+            sampled_reads: self.sampled_reads,
+            duplicate_reads,
+            optical_reads,
+        }
+    }
+}
@@ -1,71 +1,121 @@
+use serde::{Deserialize, Serialize};
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashMap};
 
-// Chosen to capture frequent contaminants without unbounded memory.
-const OVERREP_K: usize = 200_000;
-const MAX_SEQ_LEN: usize = 150;
+// Stale heap items allowed per live entry before the heap is rebuilt.
+const HEAP_COMPACT_FACTOR: usize = 4;
 
 #[derive(Clone, Debug)]
 pub struct OverrepRow {
     pub sequence: String,
     pub count: u64,
+    // SpaceSaving overestimate bound: the true count lies in
+    // [count - error, count].
+    pub error: u64,
     pub percent: f64,
     pub source: &'static str,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Entry {
     pub key: u64,
     pub count: u64,
     pub error: u64,
+    // Full read length; when it exceeds seq.len(), seq holds the read's
+    // head followed by its tail (see trim_seq).
+    pub len: usize,
     pub seq: Vec<u8>,
 }
 
-#[derive(Clone, Debug)]
+impl Entry {
+    // The stored sequence, with "..." marking where the middle of a read
+    // longer than the stored limit was dropped.
+    pub fn display_seq(&self) -> String {
+        if self.len <= self.seq.len() {
+            return String::from_utf8_lossy(&self.seq).to_string();
+        }
+        let (head, tail) = self.seq.split_at(self.seq.len().div_ceil(2));
+        format!(
+            "{}...{}",
+            String::from_utf8_lossy(head),
+            String::from_utf8_lossy(tail)
+        )
+    }
+}
+
+// Only the capacity and entries are persisted; the key index and min-heap
+// are rebuilt.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "(usize, Vec<Entry>)", into = "(usize, Vec<Entry>)")]
 pub struct SpaceSavingSeq {
+    // MemoryProfile::overrep_capacity.
+    capacity: usize,
     map: HashMap<u64, usize>,
     entries: Vec<Entry>,
     heap: BinaryHeap<(Reverse<u64>, u64, usize)>,
 }
 
 impl SpaceSavingSeq {
-    pub fn new() -> Self {
+    pub fn new(capacity: usize) -> Self {
         Self {
-            map: HashMap::with_capacity(OVERREP_K),
-            entries: Vec::with_capacity(OVERREP_K),
-            heap: BinaryHeap::with_capacity(OVERREP_K),
+            capacity,
+            map: HashMap::with_capacity(capacity),
+            entries: Vec::with_capacity(capacity),
+            heap: BinaryHeap::with_capacity(capacity),
         }
     }
 
-    pub fn add(&mut self, key: u64, seq: &[u8], weight: u64) {
+    // The key must hash all of `seq`; only the stored copy is cut to
+    // max_seq_len.
+    pub fn add(&mut self, key: u64, seq: &[u8], weight: u64, max_seq_len: usize) {
+        self.insert(key, weight, 0, seq.len(), || trim_seq(seq, max_seq_len));
+    }
+
+    // `error` is the overestimate already carried by `weight` (non-zero when
+    // merging entries from another sketch).
+    fn insert(
+        &mut self,
+        key: u64,
+        weight: u64,
+        error: u64,
+        len: usize,
+        seq: impl FnOnce() -> Vec<u8>,
+    ) {
         if let Some(&idx) = self.map.get(&key) {
             let e = &mut self.entries[idx];
             e.count += weight;
+            e.error += error;
             self.heap.push((Reverse(e.count), e.key, idx));
+            self.compact_heap();
             return;
         }
 
-        if self.entries.len() < OVERREP_K {
+        if self.entries.len() < self.capacity {
             let idx = self.entries.len();
             self.entries.push(Entry {
                 key,
                 count: weight,
-                error: 0,
-                seq: trim_seq(seq),
+                error,
+                len,
+                seq: seq(),
             });
             self.map.insert(key, idx);
             self.heap.push((Reverse(weight), key, idx));
             return;
         }
 
-        let (min_idx, min_count) = self.min_entry();
+        let Some((min_idx, min_count)) = self.min_entry() else {
+            // Only possible with no entries, i.e. a zero capacity.
+            return;
+        };
         let removed = self.entries[min_idx].key;
         self.map.remove(&removed);
         self.entries[min_idx] = Entry {
             key,
             count: min_count + weight,
-            error: min_count,
-            seq: trim_seq(seq),
+            error: min_count + error,
+            len,
+            seq: seq(),
         };
         self.map.insert(key, min_idx);
         self.heap.push((Reverse(min_count + weight), key, min_idx));
@@ -75,7 +125,7 @@ impl SpaceSavingSeq {
         let mut items = other.entries.clone();
         items.sort_by_key(|e| e.key);
         for e in items {
-            self.add(e.key, &e.seq, e.count);
+            self.insert(e.key, e.count, e.error, e.len, || e.seq.clone());
         }
     }
 
@@ -83,25 +133,65 @@ impl SpaceSavingSeq {
         &self.entries
     }
 
-    fn min_entry(&mut self) -> (usize, u64) {
-        loop {
-            if let Some((Reverse(count), key, idx)) = self.heap.pop() {
-                let e = &self.entries[idx];
-                if e.key == key && e.count == count {
-                    return (idx, count);
-                }
-            } else {
-                return (0, self.entries[0].count);
+    fn min_entry(&mut self) -> Option<(usize, u64)> {
+        while let Some((Reverse(count), key, idx)) = self.heap.pop() {
+            let e = &self.entries[idx];
+            if e.key == key && e.count == count {
+                return Some((idx, count));
             }
         }
+        // Heap and entries out of sync; rebuild instead of guessing.
+        self.rebuild_heap();
+        self.heap.pop().map(|(Reverse(count), _, idx)| (idx, count))
+    }
+
+    // Drops stale heap items, as in duplication::SpaceSaving.
+    fn compact_heap(&mut self) {
+        if self.heap.len() > HEAP_COMPACT_FACTOR * self.entries.len() {
+            self.rebuild_heap();
+        }
+    }
+
+    fn rebuild_heap(&mut self) {
+        self.heap = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(idx, e)| (Reverse(e.count), e.key, idx))
+            .collect();
     }
 }
 
-fn trim_seq(seq: &[u8]) -> Vec<u8> {
-    if seq.len() <= MAX_SEQ_LEN {
+impl From<(usize, Vec<Entry>)> for SpaceSavingSeq {
+    fn from((capacity, entries): (usize, Vec<Entry>)) -> Self {
+        let mut s = Self::new(capacity);
+        for (idx, e) in entries.iter().enumerate() {
+            s.map.insert(e.key, idx);
+            s.heap.push((Reverse(e.count), e.key, idx));
+        }
+        s.entries = entries;
+        s
+    }
+}
+
+impl From<SpaceSavingSeq> for (usize, Vec<Entry>) {
+    fn from(s: SpaceSavingSeq) -> Self {
+        (s.capacity, s.entries)
+    }
+}
+
+// Reads longer than max_len keep their first ceil(max_len/2) and last
+// floor(max_len/2) bases, so both ends of the read stay visible.
+fn trim_seq(seq: &[u8], max_len: usize) -> Vec<u8> {
+    if seq.len() <= max_len {
         return seq.to_vec();
     }
-    seq[..MAX_SEQ_LEN].to_vec()
+    let head = max_len.div_ceil(2);
+    let tail = max_len / 2;
+    let mut out = Vec::with_capacity(max_len);
+    out.extend_from_slice(&seq[..head]);
+    out.extend_from_slice(&seq[seq.len() - tail..]);
+    out
 }
 
 pub fn hash_seq(seq: &[u8]) -> u64 {
@@ -115,6 +205,18 @@ pub fn hash_seq(seq: &[u8]) -> u64 {
     h
 }
 
+pub const NO_HIT: &str = "No Hit";
+
+// Adapter fragments that make classify_source report "Adapter", with the
+// name of the adapter each comes from.
+pub const SOURCE_ADAPTERS: [(&str, &[u8]); 5] = [
+    ("Illumina Universal Adapter prefix", b"AGATCGGAAGAG"),
+    ("Small RNA 3'", b"TGGAATTCTCGG"),
+    ("Small RNA 5'", b"ATCTCGTATGCC"),
+    ("Nextera", b"CTGTCTCTTATA"),
+    ("SOLiD", b"CGCCTTGGCCGT"),
+];
+
 pub fn classify_source(seq: &[u8]) -> &'static str {
     if is_poly(seq, b'A') {
         return "Poly-A";
@@ -125,7 +227,7 @@ pub fn classify_source(seq: &[u8]) -> &'static str {
     if contains_adapter(seq) {
         return "Adapter";
     }
-    "No Hit"
+    NO_HIT
 }
 
 fn is_poly(seq: &[u8], base: u8) -> bool {
@@ -142,14 +244,7 @@ fn is_poly(seq: &[u8], base: u8) -> bool {
 }
 
 fn contains_adapter(seq: &[u8]) -> bool {
-    const ADAPTERS: [&[u8]; 5] = [
-        b"AGATCGGAAGAG", // Illumina Universal Adapter prefix
-        b"TGGAATTCTCGG", // Small RNA 3'
-        b"ATCTCGTATGCC", // Small RNA 5'
-        b"CTGTCTCTTATA", // Nextera
-        b"CGCCTTGGCCGT", // SOLiD
-    ];
-    for a in ADAPTERS {
+    for (_, a) in SOURCE_ADAPTERS {
         if find_subseq(seq, a) {
             return true;
         }
@@ -175,3 +270,44 @@ fn find_subseq(hay: &[u8], needle: &[u8]) -> bool {
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heavy_sequences_survive_many_distinct_sequences() {
+        let mut s = SpaceSavingSeq::new(16);
+        let heavy: Vec<Vec<u8>> = ["AAAAC", "CCCCG", "GGGGT", "TTTTA"]
+            .iter()
+            .map(|seq| seq.as_bytes().to_vec())
+            .collect();
+        for round in 0..20_000usize {
+            let seq = &heavy[round % 4];
+            s.add(hash_seq(seq), seq, 1, 100);
+            let seq = format!("ACGT{round}").into_bytes();
+            s.add(hash_seq(&seq), &seq, 1, 100);
+            assert!(s.heap.len() <= HEAP_COMPACT_FACTOR * s.entries.len() + 1);
+        }
+        assert_eq!(s.entries().len(), 16);
+        for seq in &heavy {
+            let e = s.entries().iter().find(|e| &e.seq == seq).unwrap();
+            assert!(e.count >= 5_000 && e.count - e.error <= 5_000);
+        }
+    }
+
+    #[test]
+    fn min_entry_copes_with_no_entries_and_a_lost_heap() {
+        let mut empty = SpaceSavingSeq::new(0);
+        empty.add(1, b"ACGT", 1, 100);
+        assert!(empty.entries().is_empty());
+        assert_eq!(empty.min_entry(), None);
+
+        let mut s = SpaceSavingSeq::new(3);
+        for (key, weight) in [(1, 5), (2, 3), (3, 9)] {
+            s.add(key, b"ACGT", weight, 100);
+        }
+        s.heap.clear();
+        assert_eq!(s.min_entry(), Some((1, 3)));
+    }
+}
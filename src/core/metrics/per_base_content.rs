@@ -1,7 +1,76 @@
+use crate::core::model::Status;
+
+// Profiles up to this many positions are reported base by base; longer ones
+// are grouped like FastQC's linear base groups.
+pub const PER_BASE_BIN_THRESHOLD: usize = 500;
+// Leading positions that always keep single-base resolution.
+const UNBINNED_POSITIONS: usize = 50;
+// Upper bound on groups after the unbinned prefix.
+const MAX_BINNED_GROUPS: usize = 75;
+
 pub struct PerBaseContentRow {
-    pub base: usize,
+    // Position ("12") or, once binned, an inclusive range ("51-60").
+    pub base: String,
+    // First position covered by the row, used as the plot x value.
+    pub start: usize,
     pub g: f64,
     pub a: f64,
     pub t: f64,
     pub c: f64,
+    pub other: f64,
+}
+
+// 1-based inclusive position ranges covering `len` positions. The group
+// width after the first 50 positions is the smallest of 2, 5, 10, 20, 50,
+// 100, ... that keeps the remainder within MAX_BINNED_GROUPS rows.
+pub fn base_groups(len: usize) -> Vec<(usize, usize)> {
+    if len <= PER_BASE_BIN_THRESHOLD {
+        return (1..=len).map(|p| (p, p)).collect();
+    }
+    let rest = len - UNBINNED_POSITIONS;
+    let mut scale = 1;
+    let interval = 'found: loop {
+        for step in [2, 5, 10] {
+            let interval = step * scale;
+            if rest.div_ceil(interval) <= MAX_BINNED_GROUPS {
+                break 'found interval;
+            }
+        }
+        scale *= 10;
+    };
+    let mut groups: Vec<(usize, usize)> = (1..=UNBINNED_POSITIONS).map(|p| (p, p)).collect();
+    let mut start = UNBINNED_POSITIONS + 1;
+    while start <= len {
+        let end = (start + interval - 1).min(len);
+        groups.push((start, end));
+        start = end + 1;
+    }
+    groups
+}
+
+// --content-bias-window: the largest deviation from 25% within the leading
+// window (random-hexamer priming) and after it, rated separately.
+pub struct ContentBias {
+    pub window: usize,
+    pub head_deviation: f64,
+    pub tail_deviation: f64,
+}
+
+// FastQC's thresholds on the largest |base% - 25| at any position.
+pub fn deviation_status(max_deviation: f64) -> Status {
+    if max_deviation > 20.0 {
+        Status::Fail
+    } else if max_deviation > 10.0 {
+        Status::Warn
+    } else {
+        Status::Pass
+    }
+}
+
+pub fn group_label(start: usize, end: usize) -> String {
+    if start == end {
+        start.to_string()
+    } else {
+        format!("{}-{}", start, end)
+    }
 }
@@ -7,3 +7,46 @@ pub struct PerBaseQualRow {
     pub p10: u8,
     pub p90: u8,
 }
+
+// Centered moving average of the row means over `window` rows (shrinking at
+// the ends), for the smoothed trend line of the per base quality plot.
+pub fn smoothed_means(rows: &[PerBaseQualRow], window: usize) -> Vec<f64> {
+    let half = window / 2;
+    let mut prefix = Vec::with_capacity(rows.len() + 1);
+    prefix.push(0.0);
+    for row in rows {
+        prefix.push(prefix[prefix.len() - 1] + row.mean);
+    }
+    (0..rows.len())
+        .map(|i| {
+            let start = i.saturating_sub(half);
+            let end = (i + window - half).min(rows.len());
+            (prefix[end] - prefix[start]) / (end - start) as f64
+        })
+        .collect()
+}
+
+// Positions behind a WARN / FAIL per base quality status, as returned by
+// positions_below for each threshold.
+#[derive(Clone, Debug, Default)]
+pub struct PerBaseQualLow {
+    pub below_warn: Vec<(usize, usize)>,
+    pub below_fail: Vec<(usize, usize)>,
+}
+
+// Median quality below which a position makes the module warn / fail.
+pub const MEDIAN_WARN: u8 = 25;
+pub const MEDIAN_FAIL: u8 = 20;
+
+// Runs of consecutive positions whose median is below `threshold`, as
+// inclusive (first, last) base numbers.
+pub fn positions_below(rows: &[PerBaseQualRow], threshold: u8) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for row in rows.iter().filter(|r| r.median < threshold) {
+        match runs.last_mut() {
+            Some((_, last)) if *last + 1 == row.base => *last = row.base,
+            _ => runs.push((row.base, row.base)),
+        }
+    }
+    runs
+}
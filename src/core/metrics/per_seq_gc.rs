@@ -1,4 +1,142 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
 pub struct PerSeqGcRow {
-    pub gc: u8,
+    // GC percent the bin is centred on (reads are rounded to the nearest
+    // bin); whole numbers unless a finer GcResolution was used.
+    pub gc: f64,
     pub count: u64,
 }
+
+// Bin width of the per sequence GC histogram. Whole percent matches FastQC;
+// the finer widths are for the broad GC spread of long-read metagenomes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GcResolution {
+    #[default]
+    Percent,
+    HalfPercent,
+    TenthPercent,
+}
+
+impl GcResolution {
+    // Number of bins spanning 0-100% (one more bin holds exactly 100%).
+    pub fn steps(self) -> usize {
+        match self {
+            GcResolution::Percent => 100,
+            GcResolution::HalfPercent => 200,
+            GcResolution::TenthPercent => 1000,
+        }
+    }
+}
+
+// Expected per sequence GC for --expected-gc / --reference-gc. The module
+// status then rates how far the reads stray from this rather than from a
+// normal fitted to the reads themselves.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum GcReference {
+    // Normal centred on this GC percent, with the spread of the reads.
+    Mean(f64),
+    // Relative weight of each whole GC percent 0-100.
+    Distribution(Vec<f64>),
+}
+
+impl GcReference {
+    // Parses `<gc percent> <weight>` lines (tab or space separated); blank
+    // lines and `#` comments are skipped and missing percents weigh 0.
+    pub fn parse_distribution(text: &str) -> Result<Self> {
+        let mut weights = vec![0.0; 101];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let (Some(gc), Some(weight), None) = (fields.next(), fields.next(), fields.next())
+            else {
+                bail!("line {}: expected `<gc percent> <weight>`", i + 1);
+            };
+            let gc: f64 = gc
+                .parse()
+                .with_context(|| format!("line {}: invalid GC percent {:?}", i + 1, gc))?;
+            let weight: f64 = weight
+                .parse()
+                .with_context(|| format!("line {}: invalid weight {:?}", i + 1, weight))?;
+            if !(0.0..=100.0).contains(&gc) || !weight.is_finite() || weight < 0.0 {
+                bail!(
+                    "line {}: GC must be in [0, 100] and the weight finite and >= 0",
+                    i + 1
+                );
+            }
+            weights[gc.round() as usize] += weight;
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            bail!("no positive weights");
+        }
+        Ok(GcReference::Distribution(weights))
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            GcReference::Mean(gc) => format!("a normal centred on {:.1}% GC", gc),
+            GcReference::Distribution(_) => "the --reference-gc distribution".to_string(),
+        }
+    }
+
+    // Share of reads expected in each whole-percent bin; `hist` supplies the
+    // spread for a Mean reference.
+    fn shares(&self, hist: &[u64; 101]) -> Vec<f64> {
+        let weights = match self {
+            GcReference::Distribution(w) => w.clone(),
+            GcReference::Mean(mean) => {
+                let total: u64 = hist.iter().sum();
+                let observed_mean = hist
+                    .iter()
+                    .enumerate()
+                    .map(|(gc, &c)| gc as f64 * c as f64)
+                    .sum::<f64>()
+                    / total as f64;
+                let var = hist
+                    .iter()
+                    .enumerate()
+                    .map(|(gc, &c)| (gc as f64 - observed_mean).powi(2) * c as f64)
+                    .sum::<f64>()
+                    / total as f64;
+                // A single-bin histogram has no spread; keep the curve a
+                // bin wide.
+                let sd = var.sqrt().max(0.5);
+                (0..=100)
+                    .map(|gc| (-0.5 * ((gc as f64 - mean) / sd).powi(2)).exp())
+                    .collect()
+            }
+        };
+        let sum: f64 = weights.iter().sum();
+        weights.iter().map(|w| w / sum).collect()
+    }
+}
+
+pub struct GcDeviation {
+    pub reference: String,
+    // Sum of |observed - expected| reads over all bins, as a percent of
+    // reads (FastQC's measure: warn above 15, fail above 30).
+    pub deviation_pct: f64,
+}
+
+pub const GC_DEVIATION_WARN_PCT: f64 = 15.0;
+pub const GC_DEVIATION_FAIL_PCT: f64 = 30.0;
+
+pub fn gc_deviation(hist: &[u64; 101], reference: &GcReference) -> Option<GcDeviation> {
+    let total: u64 = hist.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let deviation: f64 = reference
+        .shares(hist)
+        .iter()
+        .zip(hist)
+        .map(|(share, &c)| (c as f64 - share * total as f64).abs())
+        .sum();
+    Some(GcDeviation {
+        reference: reference.label(),
+        deviation_pct: deviation * 100.0 / total as f64,
+    })
+}
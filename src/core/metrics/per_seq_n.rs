@@ -2,3 +2,25 @@ pub struct PerSeqNRow {
     pub n_percent: u8,
     pub count: u64,
 }
+
+// Maximal N runs by where they sit in the read; a run covering the whole
+// read counts as Start.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NRunSummary {
+    pub runs: [u64; 3],
+    pub bases: [u64; 3],
+}
+
+pub const N_RUN_START: usize = 0;
+pub const N_RUN_INTERNAL: usize = 1;
+pub const N_RUN_END: usize = 2;
+
+pub fn n_run_location(start: usize, len: usize, read_len: usize) -> usize {
+    if start == 0 {
+        N_RUN_START
+    } else if start + len == read_len {
+        N_RUN_END
+    } else {
+        N_RUN_INTERNAL
+    }
+}
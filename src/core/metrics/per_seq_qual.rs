@@ -1,4 +1,79 @@
+use crate::core::model::quantile_from_hist;
+
 pub struct PerSeqQualRow {
     pub mean_q: u8,
     pub count: u64,
 }
+
+// Moments of the per-read mean quality distribution. A bimodal run can share
+// its mode with a clean one but shows a wider SD and a strong negative skew.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PerSeqQualStats {
+    pub mean: f64,
+    pub sd: f64,
+    pub median: u8,
+    // Population skewness (third standardized moment); 0 when SD is 0.
+    pub skewness: f64,
+}
+
+// `hist[q]` counts reads whose mean quality rounds to q.
+pub fn stats_from_hist(hist: &[u64]) -> PerSeqQualStats {
+    let total: u64 = hist.iter().sum();
+    if total == 0 {
+        return PerSeqQualStats::default();
+    }
+    let n = total as f64;
+    let mean = hist
+        .iter()
+        .enumerate()
+        .map(|(q, &c)| q as f64 * c as f64)
+        .sum::<f64>()
+        / n;
+    let (mut m2, mut m3) = (0.0f64, 0.0f64);
+    for (q, &c) in hist.iter().enumerate() {
+        let d = q as f64 - mean;
+        m2 += d * d * c as f64;
+        m3 += d * d * d * c as f64;
+    }
+    m2 /= n;
+    m3 /= n;
+    let sd = m2.sqrt();
+    PerSeqQualStats {
+        mean,
+        sd,
+        median: quantile_from_hist(hist, 0.5),
+        skewness: if sd > 0.0 { m3 / (sd * sd * sd) } else { 0.0 },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hist(counts: &[(usize, u64)]) -> Vec<u64> {
+        let mut hist = vec![0u64; 94];
+        for &(q, c) in counts {
+            hist[q] = c;
+        }
+        hist
+    }
+
+    #[test]
+    fn stats_of_known_distributions() {
+        let s = stats_from_hist(&hist(&[(10, 1), (20, 2), (30, 1)]));
+        assert_eq!((s.mean, s.median), (20.0, 20));
+        assert!((s.sd - 50f64.sqrt()).abs() < 1e-12);
+        assert_eq!(s.skewness, 0.0);
+
+        // A low-quality tail under a Q30 peak skews left.
+        let s = stats_from_hist(&hist(&[(10, 1), (30, 3)]));
+        assert_eq!((s.mean, s.median), (25.0, 30));
+        assert!((s.sd - 75f64.sqrt()).abs() < 1e-12);
+        assert!((s.skewness - -2.0 / 3f64.sqrt()).abs() < 1e-12);
+
+        let s = stats_from_hist(&hist(&[(35, 7)]));
+        assert_eq!((s.mean, s.sd, s.median, s.skewness), (35.0, 0.0, 35, 0.0));
+        let s = stats_from_hist(&hist(&[]));
+        assert_eq!((s.mean, s.sd, s.median, s.skewness), (0.0, 0.0, 0, 0.0));
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BinaryHeap;
+
+// Per-position quality sample for --exact-quantiles. Each observation gets a
+// hash priority from its read and position and the RESERVOIR_SIZE smallest
+// are kept (bottom-k sampling), so merging chunk samples yields the same
+// reservoir regardless of chunking or thread count.
+pub const RESERVOIR_SIZE: usize = 10_000;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct QualReservoir {
+    heap: BinaryHeap<(u64, u8)>,
+}
+
+impl QualReservoir {
+    pub fn add(&mut self, priority: u64, q: u8) {
+        if self.heap.len() < RESERVOIR_SIZE {
+            self.heap.push((priority, q));
+        } else if let Some(mut top) = self.heap.peek_mut()
+            && (priority, q) < *top
+        {
+            *top = (priority, q);
+        }
+    }
+
+    pub fn merge(&mut self, other: &QualReservoir) {
+        for &(priority, q) in other.heap.iter() {
+            self.add(priority, q);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn sorted(&self) -> Vec<u8> {
+        let mut values: Vec<u8> = self.heap.iter().map(|&(_, q)| q).collect();
+        values.sort_unstable();
+        values
+    }
+}
+
+pub fn priority(read_hash: u64, pos: usize) -> u64 {
+    // splitmix64 finalizer
+    let mut z = read_hash ^ (pos as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// Same nearest-rank rule as quantile_from_hist, so both paths agree whenever
+// the reservoir holds every observation.
+pub fn quantile_sorted(sorted: &[u8], q: f64) -> u8 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((q * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::model::{MAX_Q, quantile_from_hist};
+
+    #[test]
+    fn full_reservoir_quantiles_match_the_histogram() {
+        let mut hist = [0u64; MAX_Q + 1];
+        let mut res = QualReservoir::default();
+        for i in 0..5_000u64 {
+            let q = ((i * 7919) % 41) as u8;
+            hist[q as usize] += 1;
+            res.add(priority(i, 0), q);
+        }
+        let sorted = res.sorted();
+        assert_eq!(sorted.len(), 5_000);
+        for q in [0.1, 0.25, 0.5, 0.75, 0.9] {
+            assert_eq!(quantile_sorted(&sorted, q), quantile_from_hist(&hist, q));
+        }
+    }
+
+    #[test]
+    fn merged_samples_do_not_depend_on_chunking() {
+        let mut whole = QualReservoir::default();
+        let mut parts = [QualReservoir::default(), QualReservoir::default()];
+        for i in 0..3 * RESERVOIR_SIZE as u64 {
+            let q = (i % 40) as u8;
+            whole.add(priority(i, 3), q);
+            parts[(i % 2) as usize].add(priority(i, 3), q);
+        }
+        let [mut a, b] = parts;
+        a.merge(&b);
+        assert_eq!(a.sorted(), whole.sorted());
+    }
+}
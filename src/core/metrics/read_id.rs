@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// Distinct values kept per field. Headers that vary more than this are not
+// carrying run metadata in the expected slots, so collection stops there.
+const MAX_DISTINCT: usize = 64;
+
+// Instrument / flowcell / lane metadata from Casava 1.8+ Illumina headers:
+// `@<instrument>:<run>:<flowcell>:<lane>:<tile>:<x>:<y> [<comment>]`.
+// Headers in any other shape are ignored.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ReadIdMeta {
+    pub instruments: HashSet<String>,
+    pub flowcells: HashSet<String>,
+    // "<flowcell>:<lane>", since lane numbers repeat across flowcells.
+    pub lanes: HashSet<String>,
+    // Set when a field hit MAX_DISTINCT and later values were dropped.
+    pub overflow: bool,
+    // `<instrument>:<run>:<flowcell>:<lane>` of the previous read; reads
+    // from one lane share it, so the sets are only touched on a change.
+    #[serde(skip)]
+    last_prefix: Vec<u8>,
+}
+
+// The fields of a Casava 1.8+ read name.
+pub struct IlluminaName<'a> {
+    pub instrument: &'a [u8],
+    pub flowcell: &'a [u8],
+    pub lane: &'a [u8],
+    // `<instrument>:<run>:<flowcell>:<lane>`.
+    pub lane_id: &'a [u8],
+    // lane_id followed by `:<tile>`.
+    pub tile_id: &'a [u8],
+    pub x: u32,
+    pub y: u32,
+}
+
+impl<'a> IlluminaName<'a> {
+    pub fn parse(id: &'a [u8]) -> Option<Self> {
+        let id = id.strip_prefix(b"@").unwrap_or(id);
+        let name = id.split(|b| b.is_ascii_whitespace()).next()?;
+        let mut split = name.split(|&b| b == b':');
+        let mut fields: [&[u8]; 7] = [&[]; 7];
+        for field in &mut fields {
+            *field = split.next()?;
+        }
+        if split.next().is_some() {
+            return None;
+        }
+        let [instrument, run, flowcell, lane, tile, x, y] = fields;
+        if instrument.is_empty()
+            || flowcell.is_empty()
+            || ![run, lane, tile, x, y].iter().all(|f| is_number(f))
+        {
+            return None;
+        }
+        let lane_len = instrument.len() + run.len() + flowcell.len() + lane.len() + 3;
+        Some(Self {
+            instrument,
+            flowcell,
+            lane,
+            lane_id: &name[..lane_len],
+            tile_id: &name[..lane_len + 1 + tile.len()],
+            x: parse_u32(x)?,
+            y: parse_u32(y)?,
+        })
+    }
+}
+
+// Sample name carried by a read ID: the barcode of a Casava 1.8+ comment
+// (`<read>:<filtered>:<control>:<barcode>`), else the flowcell of an
+// Illumina read name.
+pub fn sample_name_from_id(id: &[u8]) -> Option<String> {
+    let id = id.strip_prefix(b"@").unwrap_or(id);
+    let mut parts = id
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|p| !p.is_empty());
+    let name = parts.next()?;
+    let barcode = parts.next().and_then(|comment| {
+        let fields: Vec<&[u8]> = comment.split(|&b| b == b':').collect();
+        match fields.as_slice() {
+            [read, filtered, control, barcode]
+                if is_number(read)
+                    && matches!(*filtered, b"Y" | b"N")
+                    && is_number(control)
+                    && !barcode.is_empty() =>
+            {
+                Some(*barcode)
+            }
+            _ => None,
+        }
+    });
+    let field = match barcode {
+        Some(barcode) => barcode,
+        None => IlluminaName::parse(name)?.flowcell,
+    };
+    Some(String::from_utf8_lossy(field).into_owned())
+}
+
+impl ReadIdMeta {
+    pub fn update(&mut self, id: &[u8]) {
+        let Some(name) = IlluminaName::parse(id) else {
+            return;
+        };
+        if name.lane_id == self.last_prefix.as_slice() {
+            return;
+        }
+        self.last_prefix.clear();
+        self.last_prefix.extend_from_slice(name.lane_id);
+
+        let flowcell = String::from_utf8_lossy(name.flowcell);
+        let lane = format!("{}:{}", flowcell, String::from_utf8_lossy(name.lane));
+        self.overflow |= !insert_capped(
+            &mut self.instruments,
+            String::from_utf8_lossy(name.instrument).into_owned(),
+        );
+        self.overflow |= !insert_capped(&mut self.flowcells, flowcell.into_owned());
+        self.overflow |= !insert_capped(&mut self.lanes, lane);
+    }
+
+    pub fn merge(&mut self, other: &ReadIdMeta) {
+        for (set, other_set) in [
+            (&mut self.instruments, &other.instruments),
+            (&mut self.flowcells, &other.flowcells),
+            (&mut self.lanes, &other.lanes),
+        ] {
+            for v in other_set {
+                self.overflow |= !insert_capped(set, v.clone());
+            }
+        }
+        self.overflow |= other.overflow;
+    }
+}
+
+// Values of one field, sorted, for the basic statistics table.
+pub fn sorted(set: &HashSet<String>) -> Vec<String> {
+    let mut v: Vec<String> = set.iter().cloned().collect();
+    v.sort();
+    v
+}
+
+// False when the value is new but the set is already full.
+fn insert_capped(set: &mut HashSet<String>, v: String) -> bool {
+    if set.len() >= MAX_DISTINCT && !set.contains(&v) {
+        return false;
+    }
+    set.insert(v);
+    true
+}
+
+fn is_number(field: &[u8]) -> bool {
+    !field.is_empty() && field.iter().all(u8::is_ascii_digit)
+}
+
+fn parse_u32(field: &[u8]) -> Option<u32> {
+    std::str::from_utf8(field).ok()?.parse().ok()
+}
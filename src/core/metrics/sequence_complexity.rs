@@ -0,0 +1,204 @@
+use crate::simd;
+use std::sync::OnceLock;
+
+// Entropy is taken over dinucleotides, so it ranges 0..=4 bits; the
+// histogram uses 0.1-bit bins.
+pub const WINDOW: usize = 64;
+pub const BINS: usize = 41;
+pub const LOW_ENTROPY_BITS: f64 = 2.0;
+
+pub struct ComplexityRow {
+    pub entropy: f64,
+    pub count: u64,
+}
+
+pub fn entropy_bin(bits: f64) -> usize {
+    ((bits * 10.0).round() as usize).min(BINS - 1)
+}
+
+pub fn bin_entropy(bin: usize) -> f64 {
+    bin as f64 / 10.0
+}
+
+pub fn low_entropy_bin() -> usize {
+    entropy_bin(LOW_ENTROPY_BITS)
+}
+
+// Mean dinucleotide Shannon entropy over every WINDOW bp window of the read,
+// sliding one base at a time; a read shorter than WINDOW is one window.
+// Windows without a single ACGT dinucleotide are left out, and reads
+// without any score 0.
+pub fn read_entropy(seq: &[u8]) -> f64 {
+    // Dinucleotide counts of the window and the sum of c * log2(c) over
+    // them, so each window's entropy is log2(total) - sum / total.
+    let mut counts = [0u32; 16];
+    let mut total = 0u32;
+    let mut c_log_c = 0.0f64;
+    // Dinucleotide ending at each base of the window, by position % WINDOW.
+    let mut ring = [NO_DINUC; WINDOW];
+    let mut prev: Option<u32> = None;
+    let mut sum = 0.0f64;
+    let mut windows = 0u32;
+    let mut window_done = |total: u32, c_log_c: f64| {
+        if total > 0 {
+            let total = total as f64;
+            sum += (total.log2() - c_log_c / total).max(0.0);
+            windows += 1;
+        }
+    };
+
+    for_each_code(seq, |pos, code| {
+        let dinuc = match (prev, code) {
+            (Some(p), Some(c)) => ((p << 2) | c) as u8,
+            _ => NO_DINUC,
+        };
+        prev = code;
+        // The dinucleotide ending WINDOW - 1 bases back drops out.
+        let old = ring[(pos + 1) % WINDOW];
+        if old != NO_DINUC {
+            let c = &mut counts[old as usize];
+            c_log_c -= c_log2_c(*c) - c_log2_c(*c - 1);
+            *c -= 1;
+            total -= 1;
+        }
+        ring[pos % WINDOW] = dinuc;
+        if dinuc != NO_DINUC {
+            let c = &mut counts[dinuc as usize];
+            c_log_c += c_log2_c(*c + 1) - c_log2_c(*c);
+            *c += 1;
+            total += 1;
+        }
+        if pos + 1 >= WINDOW {
+            window_done(total, c_log_c);
+        }
+    });
+    if seq.len() < WINDOW {
+        window_done(total, c_log_c);
+    }
+
+    if windows == 0 {
+        0.0
+    } else {
+        sum / windows as f64
+    }
+}
+
+const NO_DINUC: u8 = 16;
+
+// c * log2(c) for the counts a window can hold (at most WINDOW - 1).
+fn c_log2_c(c: u32) -> f64 {
+    static TABLE: OnceLock<[f64; WINDOW]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        std::array::from_fn(|c| {
+            if c == 0 {
+                0.0
+            } else {
+                c as f64 * (c as f64).log2()
+            }
+        })
+    })[c as usize]
+}
+
+// Calls `f` with each position of `seq` and the 2-bit code of its base,
+// None for anything but ACGT (either case).
+fn for_each_code(seq: &[u8], mut f: impl FnMut(usize, Option<u32>)) {
+    let mut i = 0usize;
+    while i + 16 <= seq.len() {
+        let (mask, packed) = simd::acgt_2bit_block_16(seq[i..].as_ptr());
+        for j in 0..16 {
+            let code = (mask & (1 << j) != 0).then(|| (packed >> (2 * j)) & 0x3);
+            f(i + j, code);
+        }
+        i += 16;
+    }
+    for (j, &b) in seq[i..].iter().enumerate() {
+        let code = match b & 0xDF {
+            b'A' => Some(0),
+            b'C' => Some(1),
+            b'G' => Some(2),
+            b'T' => Some(3),
+            _ => None,
+        };
+        f(i + j, code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Entropy of one window, counted from scratch.
+    fn naive_entropy(win: &[u8]) -> Option<f64> {
+        let code = |b: u8| b"ACGT".iter().position(|&x| x == b.to_ascii_uppercase());
+        let mut counts = [0u32; 16];
+        for pair in win.windows(2) {
+            if let (Some(a), Some(b)) = (code(pair[0]), code(pair[1])) {
+                counts[a * 4 + b] += 1;
+            }
+        }
+        let total: u32 = counts.iter().sum();
+        (total > 0).then(|| {
+            counts
+                .iter()
+                .filter(|&&c| c > 0)
+                .map(|&c| {
+                    let p = c as f64 / total as f64;
+                    -p * p.log2()
+                })
+                .sum()
+        })
+    }
+
+    fn naive_read_entropy(seq: &[u8]) -> f64 {
+        let windows: Vec<f64> = if seq.len() < WINDOW {
+            naive_entropy(seq).into_iter().collect()
+        } else {
+            seq.windows(WINDOW).filter_map(naive_entropy).collect()
+        };
+        if windows.is_empty() {
+            0.0
+        } else {
+            windows.iter().sum::<f64>() / windows.len() as f64
+        }
+    }
+
+    #[test]
+    fn homopolymer_scores_zero() {
+        assert_eq!(read_entropy(&[b'A'; 150]), 0.0);
+        assert_eq!(read_entropy(&[b'N'; 150]), 0.0);
+        assert_eq!(read_entropy(b""), 0.0);
+    }
+
+    #[test]
+    fn matches_every_window_counted_from_scratch() {
+        let mut state = 12345u32;
+        let mut random = |len: usize| -> Vec<u8> {
+            (0..len)
+                .map(|_| {
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    b"ACGTacgtN"[(state >> 16) as usize % 9]
+                })
+                .collect()
+        };
+        let mut seqs = vec![
+            random(20),
+            random(63),
+            random(64),
+            random(150),
+            random(1000),
+        ];
+        // Complex start, poly-A tail: the windows crossing the junction are
+        // scored too.
+        let mut tailed = random(100);
+        tailed.extend_from_slice(&[b'A'; 100]);
+        seqs.push(tailed);
+        for seq in &seqs {
+            let (got, want) = (read_entropy(seq), naive_read_entropy(seq));
+            assert!(
+                (got - want).abs() < 1e-9,
+                "len {}: {got} != {want}",
+                seq.len()
+            );
+        }
+    }
+}
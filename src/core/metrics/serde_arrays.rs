@@ -0,0 +1,46 @@
+// serde only implements fixed-size arrays up to 32 elements; the quality and
+// percentage histograms are larger, so they round-trip through slices.
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<S: Serializer, const N: usize>(v: &[u64; N], s: S) -> Result<S::Ok, S::Error> {
+    v.as_slice().serialize(s)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(d: D) -> Result<[u64; N], D::Error> {
+    let v = Vec::<u64>::deserialize(d)?;
+    let len = v.len();
+    v.try_into()
+        .map_err(|_| D::Error::invalid_length(len, &"a fixed-size histogram"))
+}
+
+pub mod vec {
+    use serde::de::Error;
+    use serde::ser::SerializeSeq;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        v: &[[u64; N]],
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = s.serialize_seq(Some(v.len()))?;
+        for row in v {
+            seq.serialize_element(row.as_slice())?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        d: D,
+    ) -> Result<Vec<[u64; N]>, D::Error> {
+        let rows = Vec::<Vec<u64>>::deserialize(d)?;
+        rows.into_iter()
+            .map(|row| {
+                let len = row.len();
+                row.try_into()
+                    .map_err(|_| D::Error::invalid_length(len, &"a fixed-size histogram"))
+            })
+            .collect()
+    }
+}
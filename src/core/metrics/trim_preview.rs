@@ -0,0 +1,18 @@
+// What 3' adapter trimming would remove: each read is cut at its earliest
+// adapter match, so everything from the match start to the read end counts
+// as removed.
+pub struct TrimPreview {
+    pub reads_trimmed: u64,
+    pub bases_removed: u64,
+    pub bases_retained: u64,
+    // Mean read length after trimming, over all reads.
+    pub mean_trimmed_length: f64,
+    // Non-empty bins of the removed-bases distribution.
+    pub rows: Vec<TrimRow>,
+}
+
+pub struct TrimRow {
+    // Bases cut from the read; the last bin also holds longer cuts.
+    pub removed: usize,
+    pub reads: u64,
+}
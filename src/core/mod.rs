@@ -1,4 +1,7 @@
 pub mod engine;
 pub mod fastq;
+pub mod input;
 pub mod metrics;
 pub mod model;
+pub mod progress;
+pub mod saved_agg;
@@ -1,4 +1,6 @@
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Mode {
     Short,
     Long,
@@ -9,6 +11,9 @@ pub enum Status {
     Pass,
     Warn,
     Fail,
+    // Module cannot be evaluated for this input (e.g. quality modules on
+    // FASTA, which has no quality scores).
+    NotApplicable,
 }
 
 impl Status {
@@ -17,6 +22,7 @@ impl Status {
             Status::Pass => "pass",
             Status::Warn => "warn",
             Status::Fail => "fail",
+            Status::NotApplicable => "n/a",
         }
     }
 
@@ -25,14 +31,75 @@ impl Status {
             Status::Pass => "PASS",
             Status::Warn => "WARN",
             Status::Fail => "FAIL",
+            Status::NotApplicable => "N/A",
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Encoding {
     Sanger,
     Illumina15,
+    Solexa,
+    // Never detected; only reported when forced with --encoding.
+    Illumina13,
+    Illumina18,
+}
+
+impl Encoding {
+    pub fn phred_offset(self) -> u8 {
+        match self {
+            Encoding::Sanger | Encoding::Illumina18 => 33,
+            Encoding::Illumina15 | Encoding::Illumina13 | Encoding::Solexa => 64,
+        }
+    }
+
+    // Basic Statistics "Encoding" value.
+    pub fn label(self) -> &'static str {
+        match self {
+            Encoding::Sanger => "Sanger / Illumina 1.9",
+            Encoding::Illumina15 => "Illumina 1.5",
+            Encoding::Solexa => "Illumina 1.0",
+            Encoding::Illumina13 => "Illumina 1.3",
+            Encoding::Illumina18 => "Illumina 1.8",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    Crc32,
+    Md5,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HashAlgorithm::Crc32 => "CRC32",
+            HashAlgorithm::Md5 => "MD5",
+            HashAlgorithm::Sha256 => "SHA-256",
+        }
+    }
+}
+
+// Digest of the input file as stored on disk (compressed bytes for gzip,
+// BGZF and bzip2), as lowercase hex.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FileHash {
+    pub algorithm: HashAlgorithm,
+    pub value: String,
+}
+
+// Solexa (Illumina 1.0) stores log-odds scores at offset 64, down to -5.
+// Rewrites a quality string in place as phred+64 so the rest of the
+// pipeline can treat it like Illumina 1.5 data.
+pub fn solexa_to_phred64(qual: &mut [u8]) {
+    for b in qual {
+        let q_solexa = *b as f64 - 64.0;
+        let q_phred = 10.0 * (10f64.powf(q_solexa / 10.0) + 1.0).log10();
+        *b = (q_phred.round() as u8).saturating_add(64);
+    }
 }
 
 pub struct FinalizeContext {
@@ -41,6 +108,33 @@ pub struct FinalizeContext {
     pub file_name: String,
     pub sample_name: String,
     pub mode: Mode,
+    pub subsampled: Option<u64>,
+    // Reads processed before --max-reads/--max-bases stopped the run.
+    pub truncated: Option<u64>,
+    // Custom report heading and description line (HTML report only).
+    pub title: Option<String>,
+    pub description: Option<String>,
+    // Target genome/panel size in bp for the estimated coverage line.
+    pub genome_size: Option<u64>,
+    // Base Composition warns when |A-T| or |G-C| exceeds this share of all
+    // bases.
+    pub strand_bias_pct: f64,
+    // Per base sequence content also rates the first this many bp and the
+    // rest separately.
+    pub content_bias_window: Option<usize>,
+    // Moving-average window, in plotted positions, of the trend line drawn
+    // over the per base quality means.
+    pub qual_trend_window: Option<usize>,
+    // Overrepresented sequences at or above this percentage of reads are
+    // listed (at most max_overrep_rows of them, when set); k-mer content
+    // lists at most max_kmer_rows.
+    pub overrep_threshold_pct: f64,
+    pub max_overrep_rows: Option<usize>,
+    pub max_kmer_rows: usize,
+    // Per sequence GC content is rated against this when set.
+    pub gc_reference: Option<crate::core::metrics::GcReference>,
+    // Set with --hash; None when the run stopped before the end of the file.
+    pub file_hash: Option<FileHash>,
 }
 
 pub const MAX_Q: usize = 93;
@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(2);
+
+// Bytes of framing per FASTQ record that are not part of id/seq/qual:
+// '@', '+', and four newlines.
+pub const RECORD_OVERHEAD: u64 = 6;
+
+pub struct Progress {
+    total_bytes: Option<u64>,
+    start: Instant,
+    last: Instant,
+}
+
+impl Progress {
+    pub fn new(total_bytes: Option<u64>) -> Self {
+        let now = Instant::now();
+        Self {
+            total_bytes,
+            start: now,
+            last: now,
+        }
+    }
+
+    pub fn update(&mut self, reads: u64, bytes: u64) {
+        if self.last.elapsed() < REPORT_INTERVAL {
+            return;
+        }
+        self.last = Instant::now();
+        self.print(reads, bytes, false);
+    }
+
+    pub fn finish(&self, reads: u64, bytes: u64) {
+        self.print(reads, bytes, true);
+    }
+
+    // Each report is a single complete line so it never splices into the
+    // KIRA_STATS lines written from other threads.
+    fn print(&self, reads: u64, bytes: u64, done: bool) {
+        let secs = self.start.elapsed().as_secs_f64().max(1e-9);
+        let reads_per_sec = reads as f64 / secs;
+        let bytes_per_sec = bytes as f64 / secs;
+        match self.total_bytes {
+            Some(total) if total > 0 && !done => {
+                let pct = (bytes as f64 * 100.0 / total as f64).min(100.0);
+                let remaining = total.saturating_sub(bytes) as f64;
+                let eta = if bytes_per_sec > 0.0 {
+                    fmt_eta(remaining / bytes_per_sec)
+                } else {
+                    "-".to_string()
+                };
+                eprintln!(
+                    "progress: {:.1}% ({} / {}) {:.0} reads/s ETA {}",
+                    pct,
+                    fmt_bytes(bytes),
+                    fmt_bytes(total),
+                    reads_per_sec,
+                    eta
+                );
+            }
+            _ => {
+                eprintln!(
+                    "progress: {}{} processed, {} reads, {}/s, {:.0} reads/s",
+                    if done { "done, " } else { "" },
+                    fmt_bytes(bytes),
+                    reads,
+                    fmt_bytes(bytes_per_sec as u64),
+                    reads_per_sec
+                );
+            }
+        }
+    }
+}
+
+fn fmt_bytes(v: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = v as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", v, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn fmt_eta(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
@@ -0,0 +1,355 @@
+use crate::core::engine::RunOutput;
+use crate::core::metrics::{Agg, AggConfig, GcReference};
+use crate::core::model::{Encoding, FileHash, FinalizeContext};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedAgg {
+    pub version: u32,
+    pub file_name: String,
+    pub phred_offset: u8,
+    pub encoding: Encoding,
+    pub file_hash: Option<FileHash>,
+    pub config: AggConfig,
+    pub settings: ReportSettings,
+    pub agg: Agg,
+}
+
+// Report settings of the saved run. `report` takes them from the first
+// aggregate unless they are given again on its command line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportSettings {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub genome_size: Option<u64>,
+    pub strand_bias_pct: f64,
+    pub content_bias_window: Option<usize>,
+    pub qual_trend_window: Option<usize>,
+    pub overrep_threshold_pct: f64,
+    pub max_overrep_rows: Option<usize>,
+    pub max_kmer_rows: usize,
+    pub gc_reference: Option<GcReference>,
+}
+
+pub fn save(path: &Path, output: &RunOutput, config: &AggConfig) -> Result<()> {
+    let ctx = &output.ctx;
+    let saved = SavedAgg {
+        version: FORMAT_VERSION,
+        file_name: ctx.file_name.clone(),
+        phred_offset: ctx.phred_offset,
+        encoding: ctx.encoding,
+        file_hash: ctx.file_hash.clone(),
+        config: *config,
+        settings: ReportSettings {
+            title: ctx.title.clone(),
+            description: ctx.description.clone(),
+            genome_size: ctx.genome_size,
+            strand_bias_pct: ctx.strand_bias_pct,
+            content_bias_window: ctx.content_bias_window,
+            qual_trend_window: ctx.qual_trend_window,
+            overrep_threshold_pct: ctx.overrep_threshold_pct,
+            max_overrep_rows: ctx.max_overrep_rows,
+            max_kmer_rows: ctx.max_kmer_rows,
+            gc_reference: ctx.gc_reference.clone(),
+        },
+        agg: output.agg.clone(),
+    };
+    let w = BufWriter::new(
+        File::create(path).with_context(|| format!("create {} failed", path.display()))?,
+    );
+    serde_json::to_writer(w, &saved)
+        .with_context(|| format!("failed to serialize aggregate to {}", path.display()))
+}
+
+pub fn load(path: &Path) -> Result<SavedAgg> {
+    let r = BufReader::new(
+        File::open(path).with_context(|| format!("open {} failed", path.display()))?,
+    );
+    let saved: SavedAgg = serde_json::from_reader(r)
+        .with_context(|| format!("failed to parse aggregate {}", path.display()))?;
+    if saved.version != FORMAT_VERSION {
+        bail!(
+            "{}: unsupported aggregate format version {} (expected {})",
+            path.display(),
+            saved.version,
+            FORMAT_VERSION
+        );
+    }
+    Ok(saved)
+}
+
+// Combines aggregates saved from separate files (e.g. lanes of one library)
+// into a single output, as if the reads had come from one input.
+pub fn merge_files<P: AsRef<Path>>(paths: &[P], sample_name: &str) -> Result<RunOutput> {
+    let Some((first_path, rest)) = paths.split_first() else {
+        bail!("no aggregates to merge");
+    };
+    let first_path = first_path.as_ref();
+    let first = load(first_path)?;
+    let mut file_names = vec![first.file_name];
+    let mut file_hashes = vec![first.file_hash];
+    let settings = first.settings;
+    let mut agg = first.agg;
+
+    for path in rest {
+        let path = path.as_ref();
+        let next = load(path)?;
+        if next.config.mode != first.config.mode {
+            bail!(
+                "cannot merge {} ({:?} mode) with {} ({:?} mode)",
+                path.display(),
+                next.config.mode,
+                first_path.display(),
+                first.config.mode
+            );
+        }
+        if next.phred_offset != first.phred_offset {
+            bail!(
+                "cannot merge {} (phred+{}) with {} (phred+{})",
+                path.display(),
+                next.phred_offset,
+                first_path.display(),
+                first.phred_offset
+            );
+        }
+        if let Some(flag) = config_mismatch(&first.config, &next.config) {
+            bail!(
+                "cannot merge {} with {}: they were run with different {}",
+                path.display(),
+                first_path.display(),
+                flag
+            );
+        }
+        agg.merge(&next.agg);
+        file_names.push(next.file_name);
+        file_hashes.push(next.file_hash);
+    }
+
+    let ctx = FinalizeContext {
+        phred_offset: first.phred_offset,
+        encoding: first.encoding,
+        file_name: file_names.join(", "),
+        sample_name: sample_name.to_string(),
+        mode: agg.mode,
+        subsampled: None,
+        truncated: None,
+        title: settings.title,
+        description: settings.description,
+        genome_size: settings.genome_size,
+        strand_bias_pct: settings.strand_bias_pct,
+        content_bias_window: settings.content_bias_window,
+        qual_trend_window: settings.qual_trend_window,
+        overrep_threshold_pct: settings.overrep_threshold_pct,
+        max_overrep_rows: settings.max_overrep_rows,
+        max_kmer_rows: settings.max_kmer_rows,
+        gc_reference: settings.gc_reference,
+        file_hash: merged_hash(&file_hashes),
+    };
+    Ok(RunOutput {
+        agg,
+        ctx,
+        workers: 0,
+        profile: None,
+    })
+}
+
+// The option two saved runs differ in, if any. --skip may differ (a module
+// skipped on either side is left out of the merge), and --seed only
+// matters when both kept --exact-quantiles samples.
+fn config_mismatch(a: &AggConfig, b: &AggConfig) -> Option<&'static str> {
+    let checks = [
+        (a.max_read_len != b.max_read_len, "--max-read-len"),
+        (a.overrep_max_len != b.overrep_max_len, "--overrep-max-len"),
+        (
+            a.overrep_prefix_len != b.overrep_prefix_len,
+            "--overrep-prefix-len",
+        ),
+        (
+            a.optical_dup_distance != b.optical_dup_distance,
+            "--optical-dup-distance",
+        ),
+        (
+            a.length_quality_map != b.length_quality_map,
+            "--length-quality-map",
+        ),
+        (
+            a.adapter_positions != b.adapter_positions,
+            "--adapter-positions",
+        ),
+        (a.gc_resolution != b.gc_resolution, "--gc-resolution"),
+        (a.exact_quantiles != b.exact_quantiles, "--exact-quantiles"),
+        (a.exact_quantiles && a.seed != b.seed, "--seed"),
+        (a.track_softmask != b.track_softmask, "--track-softmask"),
+        (
+            a.collapse_revcomp != b.collapse_revcomp,
+            "--collapse-revcomp",
+        ),
+        (a.memory != b.memory, "--memory"),
+    ];
+    checks
+        .into_iter()
+        .find(|&(differs, _)| differs)
+        .map(|(_, flag)| flag)
+}
+
+// Digests of the merged inputs in file order, when every input was hashed
+// with the same algorithm.
+fn merged_hash(hashes: &[Option<FileHash>]) -> Option<FileHash> {
+    let first = hashes.first()?.as_ref()?;
+    let mut values = Vec::with_capacity(hashes.len());
+    for hash in hashes {
+        let hash = hash.as_ref()?;
+        if hash.algorithm != first.algorithm {
+            return None;
+        }
+        values.push(hash.value.as_str());
+    }
+    Some(FileHash {
+        algorithm: first.algorithm,
+        value: values.join(", "),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fastq::ReadView;
+    use crate::core::metrics::{GcResolution, MemoryProfile, SkipModules};
+    use crate::core::model::Mode;
+    use std::path::PathBuf;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let name = format!("kira-qc-saved-agg-{}-{}", std::process::id(), name);
+            TempFile(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn config() -> AggConfig {
+        AggConfig {
+            mode: Mode::Short,
+            max_read_len: 1000,
+            overrep_max_len: 100,
+            overrep_prefix_len: None,
+            optical_dup_distance: None,
+            length_quality_map: false,
+            adapter_positions: false,
+            gc_resolution: GcResolution::Percent,
+            exact_quantiles: false,
+            seed: 0,
+            track_softmask: false,
+            collapse_revcomp: false,
+            skip: SkipModules::default(),
+            memory: MemoryProfile::Low,
+        }
+    }
+
+    fn output(file_name: &str, config: &AggConfig, seqs: &[&[u8]]) -> RunOutput {
+        let mut agg = Agg::new(config);
+        for seq in seqs {
+            let qual = vec![b'I'; seq.len()];
+            let read = ReadView {
+                id: b"r",
+                seq,
+                qual: &qual,
+            };
+            agg.update_read(&read, 33);
+        }
+        let ctx = FinalizeContext {
+            phred_offset: 33,
+            encoding: Encoding::Illumina18,
+            file_name: file_name.to_string(),
+            sample_name: file_name.to_string(),
+            mode: config.mode,
+            subsampled: None,
+            truncated: None,
+            title: Some("Lane QC".to_string()),
+            description: None,
+            genome_size: Some(5_000_000),
+            strand_bias_pct: 5.0,
+            content_bias_window: Some(12),
+            qual_trend_window: None,
+            overrep_threshold_pct: 0.5,
+            max_overrep_rows: None,
+            max_kmer_rows: 20,
+            gc_reference: None,
+            file_hash: None,
+        };
+        RunOutput {
+            agg,
+            ctx,
+            workers: 1,
+            profile: None,
+        }
+    }
+
+    #[test]
+    fn merges_saved_runs_and_restores_their_settings() {
+        let (a, b) = (TempFile::new("a.json"), TempFile::new("b.json"));
+        let cfg = config();
+        save(&a.0, &output("L001.fastq", &cfg, &[b"ACGT", b"GGCC"]), &cfg).unwrap();
+        save(&b.0, &output("L002.fastq", &cfg, &[b"ACGTACGT"]), &cfg).unwrap();
+
+        let merged = merge_files(&[&a.0, &b.0], "library1").unwrap();
+        assert_eq!(merged.agg.total_reads, 3);
+        assert_eq!(merged.agg.total_bases, 16);
+        assert_eq!(merged.agg.gc_bases, 10);
+        assert_eq!(merged.ctx.file_name, "L001.fastq, L002.fastq");
+        assert_eq!(merged.ctx.sample_name, "library1");
+        assert_eq!(merged.ctx.title.as_deref(), Some("Lane QC"));
+        assert_eq!(merged.ctx.genome_size, Some(5_000_000));
+        assert_eq!(merged.ctx.content_bias_window, Some(12));
+        assert_eq!(merged.ctx.strand_bias_pct, 5.0);
+        assert_eq!(merged.ctx.overrep_threshold_pct, 0.5);
+        assert_eq!(merged.ctx.max_kmer_rows, 20);
+    }
+
+    #[test]
+    fn rejects_runs_with_different_metric_options() {
+        let (a, b) = (TempFile::new("c.json"), TempFile::new("d.json"));
+        let cfg = config();
+        let other = AggConfig {
+            max_read_len: 500,
+            ..cfg
+        };
+        save(&a.0, &output("L001.fastq", &cfg, &[b"ACGT"]), &cfg).unwrap();
+        save(&b.0, &output("L002.fastq", &other, &[b"ACGT"]), &other).unwrap();
+
+        let Err(err) = merge_files(&[&a.0, &b.0], "library1") else {
+            panic!("merged runs with different --max-read-len");
+        };
+        assert!(err.to_string().contains("--max-read-len"), "{err}");
+    }
+
+    #[test]
+    fn skipped_modules_may_differ() {
+        let (a, b) = (TempFile::new("e.json"), TempFile::new("f.json"));
+        let cfg = config();
+        let other = AggConfig {
+            skip: SkipModules {
+                kmer_content: true,
+                ..SkipModules::default()
+            },
+            ..cfg
+        };
+        save(&a.0, &output("L001.fastq", &cfg, &[b"ACGT"]), &cfg).unwrap();
+        save(&b.0, &output("L002.fastq", &other, &[b"ACGT"]), &other).unwrap();
+
+        let merged = merge_files(&[&a.0, &b.0], "library1").unwrap();
+        assert!(merged.agg.skip.kmer_content);
+    }
+}
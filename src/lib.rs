@@ -0,0 +1,29 @@
+//! kira-qc as a library.
+//!
+//! [`analyze`] runs the same streaming engine as the `kira-qc run` command but
+//! writes nothing to disk. Call `output.agg.finalize(&output.ctx)` on the
+//! returned [`RunOutput`] to obtain the [`FinalMetrics`] (including
+//! [`Statuses`]) that the report writers in [`report`] render.
+
+pub mod core;
+pub mod report;
+mod simd;
+
+pub use crate::core::engine::{PhredOffsetConfig, RunConfig, RunOutput, Subsample, WorkerPool};
+pub use crate::core::metrics::{
+    Agg, FinalMetrics, GcResolution, MemoryProfile, SkipModules, Statuses,
+};
+pub use crate::core::model::{Encoding, FinalizeContext, Mode, Status};
+
+/// Runs QC over `config.reads1` and returns the merged aggregate together
+/// with its finalize context. No output files are written.
+pub fn analyze(config: RunConfig) -> anyhow::Result<RunOutput> {
+    core::engine::run(config)
+}
+
+/// Like [`analyze`], but runs on an existing [`WorkerPool`] so several inputs
+/// can share worker threads. `config.threads` and `config.pin_threads` are
+/// ignored in favour of the pool's settings.
+pub fn analyze_with_pool(config: RunConfig, pool: &WorkerPool) -> anyhow::Result<RunOutput> {
+    core::engine::run_with_pool(config, pool)
+}
@@ -1,7 +1,4 @@
 mod cli;
-mod core;
-mod report;
-mod simd;
 
 fn main() -> anyhow::Result<()> {
     cli::run::entry()
@@ -6,31 +6,57 @@ use std::io::{BufWriter, Write};
 use std::path::Path;
 
 pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
-    let metrics = output.agg.finalize(&output.ctx);
     let mut w =
         BufWriter::new(File::create(path).with_context(|| "create fastqc_data.txt failed")?);
+    write_to(&mut w, output)?;
+    w.flush()?;
+    Ok(())
+}
 
-    write_basic(&mut w, &metrics, &output.ctx.file_name)?;
+pub fn write_to(w: &mut dyn Write, output: &RunOutput) -> Result<()> {
+    let metrics = output.agg.finalize(&output.ctx);
+
+    write_basic(w, &metrics, &output.ctx.file_name)?;
     match output.ctx.mode {
         Mode::Short => {
-            write_per_base_quality(&mut w, &metrics)?;
-            write_per_seq_quality(&mut w, &metrics)?;
-            write_per_base_content(&mut w, &metrics)?;
-            write_per_seq_gc(&mut w, &metrics)?;
-            write_per_base_n(&mut w, &metrics)?;
-            write_length_dist_short(&mut w, &metrics)?;
-            write_duplication(&mut w, &metrics)?;
-            write_overrep(&mut w, &metrics)?;
-            write_adapter_content_short(&mut w, &metrics)?;
+            write_per_base_quality(w, &metrics)?;
+            write_error_rate(w, &metrics)?;
+            write_per_seq_quality(w, &metrics)?;
+            write_per_base_content(w, &metrics)?;
+            write_per_seq_gc(w, &metrics)?;
+            write_per_base_n(w, &metrics)?;
+            write_gc_skew(w, &metrics)?;
+            write_length_dist_short(w, &metrics)?;
+            if !metrics.skipped.duplication {
+                write_duplication(w, &metrics)?;
+            }
+            if !metrics.skipped.overrepresented {
+                write_overrep(w, &metrics)?;
+            }
+            if !metrics.skipped.adapter_content {
+                write_adapter_content_short(w, &metrics)?;
+                write_trim_preview(w, &metrics)?;
+            }
+            write_sequence_complexity(w, &metrics)?;
+            write_base_composition(w, &metrics)?;
             #[cfg(not(feature = "no-kmer"))]
-            write_kmer_content(&mut w, &metrics)?;
+            if !metrics.skipped.kmer_content {
+                write_kmer_content(w, &metrics)?;
+            }
         }
         Mode::Long => {
-            write_length_dist_long(&mut w, &metrics)?;
-            write_per_seq_quality(&mut w, &metrics)?;
-            write_per_seq_gc(&mut w, &metrics)?;
-            write_per_seq_n(&mut w, &metrics)?;
-            write_adapter_content_long(&mut w, &metrics)?;
+            write_length_dist_long(w, &metrics)?;
+            write_per_seq_quality(w, &metrics)?;
+            if !metrics.length_quality.is_empty() {
+                write_length_quality(w, &metrics)?;
+            }
+            write_per_seq_gc(w, &metrics)?;
+            write_per_seq_n(w, &metrics)?;
+            if !metrics.skipped.adapter_content {
+                write_adapter_content_long(w, &metrics)?;
+            }
+            write_sequence_complexity(w, &metrics)?;
+            write_base_composition(w, &metrics)?;
         }
     }
 
@@ -49,6 +75,9 @@ fn write_basic(
     )?;
     writeln!(w, "#Measure\tValue")?;
     writeln!(w, "Filename\t{}", file_name)?;
+    if let Some(hash) = &metrics.basic.file_hash {
+        writeln!(w, "File hash\t{}", hash)?;
+    }
     writeln!(w, "File type\t{}", metrics.basic.file_type)?;
     writeln!(w, "Encoding\t{}", metrics.basic.encoding)?;
     writeln!(w, "Total Sequences\t{}", metrics.basic.total_sequences)?;
@@ -67,6 +96,44 @@ fn write_basic(
         )?;
     }
     writeln!(w, "%GC\t{}", metrics.basic.gc_percent)?;
+    writeln!(w, "%Ambiguous\t{:.2}", metrics.basic.ambiguous_percent)?;
+    if let Some(pct) = metrics.basic.softmasked_percent {
+        writeln!(w, "%Soft-masked\t{:.2}", pct)?;
+    }
+    if let Some(coverage) = metrics.basic.estimated_coverage {
+        writeln!(w, "Estimated coverage\t{:.2}", coverage)?;
+    }
+    if let Some(record) = metrics.basic.first_invalid_qual_record {
+        writeln!(
+            w,
+            "Invalid quality bytes\t{}",
+            metrics.basic.invalid_qual_bytes
+        )?;
+        writeln!(w, "First invalid quality record\t{}", record)?;
+    }
+    if metrics.basic.malformed_records > 0 {
+        writeln!(
+            w,
+            "Malformed records skipped\t{}",
+            metrics.basic.malformed_records
+        )?;
+    }
+    if metrics.basic.ws_trimmed_records > 0 {
+        writeln!(
+            w,
+            "Records with trailing whitespace trimmed\t{}",
+            metrics.basic.ws_trimmed_records
+        )?;
+    }
+    for (label, values) in [
+        ("Instruments", &metrics.basic.instruments),
+        ("Flowcells", &metrics.basic.flowcells),
+        ("Lanes", &metrics.basic.lanes),
+    ] {
+        if !values.is_empty() {
+            writeln!(w, "{}\t{}", label, values.join(", "))?;
+        }
+    }
     writeln!(w, ">>END_MODULE")?;
     Ok(())
 }
@@ -118,6 +185,31 @@ fn write_per_seq_quality(
     Ok(())
 }
 
+fn write_length_quality(
+    w: &mut dyn Write,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    writeln!(
+        w,
+        ">>Length vs Quality\t{}",
+        metrics.statuses.length_quality.as_str_lower()
+    )?;
+    write!(w, "#Length")?;
+    for i in 0..crate::core::metrics::LengthQualityRow::COLUMNS {
+        write!(w, "\tQ{}", i * crate::core::metrics::QUAL_BIN_WIDTH)?;
+    }
+    writeln!(w)?;
+    for row in &metrics.length_quality {
+        write!(w, "{}", row.length_bin)?;
+        for c in row.counts {
+            write!(w, "\t{}", c)?;
+        }
+        writeln!(w)?;
+    }
+    writeln!(w, ">>END_MODULE")?;
+    Ok(())
+}
+
 fn write_per_base_content(
     w: &mut dyn Write,
     metrics: &crate::core::metrics::FinalMetrics,
@@ -167,6 +259,34 @@ fn write_per_base_n(w: &mut dyn Write, metrics: &crate::core::metrics::FinalMetr
     Ok(())
 }
 
+fn write_error_rate(w: &mut dyn Write, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    writeln!(
+        w,
+        ">>Per base error rate\t{}",
+        metrics.statuses.error_rate.as_str_lower()
+    )?;
+    writeln!(w, "#Base\tError rate")?;
+    for row in &metrics.error_rate {
+        writeln!(w, "{}\t{:.4e}", row.base, row.error_rate)?;
+    }
+    writeln!(w, ">>END_MODULE")?;
+    Ok(())
+}
+
+fn write_gc_skew(w: &mut dyn Write, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    writeln!(
+        w,
+        ">>Per base GC skew\t{}",
+        metrics.statuses.gc_skew.as_str_lower()
+    )?;
+    writeln!(w, "#Base\tGC-Skew")?;
+    for row in &metrics.gc_skew {
+        writeln!(w, "{}\t{:.4}", row.base, row.skew)?;
+    }
+    writeln!(w, ">>END_MODULE")?;
+    Ok(())
+}
+
 fn write_per_seq_n(w: &mut dyn Write, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
     writeln!(
         w,
@@ -181,6 +301,44 @@ fn write_per_seq_n(w: &mut dyn Write, metrics: &crate::core::metrics::FinalMetri
     Ok(())
 }
 
+fn write_sequence_complexity(
+    w: &mut dyn Write,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    writeln!(
+        w,
+        ">>Sequence Complexity\t{}",
+        metrics.statuses.sequence_complexity.as_str_lower()
+    )?;
+    writeln!(w, "#Entropy\tCount")?;
+    for row in &metrics.sequence_complexity {
+        writeln!(w, "{:.1}\t{}", row.entropy, row.count)?;
+    }
+    writeln!(w, ">>END_MODULE")?;
+    Ok(())
+}
+
+fn write_base_composition(
+    w: &mut dyn Write,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    let bc = &metrics.base_composition;
+    writeln!(
+        w,
+        ">>Base Composition\t{}",
+        metrics.statuses.base_composition.as_str_lower()
+    )?;
+    writeln!(w, "#AT/GC Ratio\t{:.4}", bc.at_gc_ratio)?;
+    writeln!(w, "#A-T Imbalance %\t{:.2}", bc.at_imbalance_pct)?;
+    writeln!(w, "#G-C Imbalance %\t{:.2}", bc.gc_imbalance_pct)?;
+    writeln!(w, "#Base\tCount")?;
+    for (base, count) in [("A", bc.a), ("C", bc.c), ("G", bc.g), ("T", bc.t)] {
+        writeln!(w, "{}\t{}", base, count)?;
+    }
+    writeln!(w, ">>END_MODULE")?;
+    Ok(())
+}
+
 fn write_length_dist_short(
     w: &mut dyn Write,
     metrics: &crate::core::metrics::FinalMetrics,
@@ -214,9 +372,19 @@ fn write_length_dist_long(
         writeln!(w, "Mean\t{:.1}", ll.mean)?;
         writeln!(w, "N50\t{}", ll.n50)?;
         writeln!(w, "N90\t{}", ll.n90)?;
-        writeln!(w, "#Length\tCount")?;
-        for i in 0..ll.bins.len() {
-            writeln!(w, "{}\t{}", ll.labels[i], ll.bins[i])?;
+        match &ll.trimmed_bins {
+            Some(trimmed) => {
+                writeln!(w, "#Length\tCount\tTrimmed Count")?;
+                for ((label, count), trimmed) in ll.labels.iter().zip(&ll.bins).zip(trimmed) {
+                    writeln!(w, "{label}\t{count}\t{trimmed}")?;
+                }
+            }
+            None => {
+                writeln!(w, "#Length\tCount")?;
+                for i in 0..ll.bins.len() {
+                    writeln!(w, "{}\t{}", ll.labels[i], ll.bins[i])?;
+                }
+            }
         }
     }
     writeln!(w, ">>END_MODULE")?;
@@ -232,6 +400,17 @@ fn write_duplication(
         ">>Sequence Duplication Levels\t{}",
         metrics.statuses.duplication.as_str_lower()
     )?;
+    writeln!(
+        w,
+        "#Total Deduplicated Percentage\t{}",
+        metrics.deduplicated_pct
+    )?;
+    if let Some(top) = &metrics.top_sequence {
+        writeln!(w, "#Top Sequence Percentage\t{:.2}", top.percent)?;
+        if let Some(seq) = &top.sequence {
+            writeln!(w, "#Top Sequence\t{}", seq)?;
+        }
+    }
     writeln!(w, "#Duplication Level\tRelative Count")?;
     for row in &metrics.duplication {
         writeln!(w, "{}\t{:.2}", row.level.as_str(), row.relative)?;
@@ -246,12 +425,12 @@ fn write_overrep(w: &mut dyn Write, metrics: &crate::core::metrics::FinalMetrics
         ">>Overrepresented sequences\t{}",
         metrics.statuses.overrepresented.as_str_lower()
     )?;
-    writeln!(w, "#Sequence\tCount\tPercentage\tPossible Source")?;
+    writeln!(w, "#Sequence\tCount\tPercentage\tPossible Source\tError")?;
     for row in &metrics.overrepresented {
         writeln!(
             w,
-            "{}\t{}\t{:.2}\t{}",
-            row.sequence, row.count, row.percent, row.source
+            "{}\t{}\t{:.2}\t{}\t{}",
+            row.sequence, row.count, row.percent, row.source, row.error
         )?;
     }
     writeln!(w, ">>END_MODULE")?;
@@ -283,6 +462,28 @@ fn write_adapter_content_short(
     Ok(())
 }
 
+fn write_trim_preview(
+    w: &mut dyn Write,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    let trim = &metrics.trim_preview;
+    writeln!(
+        w,
+        ">>Adapter Trimming Preview\t{}",
+        metrics.statuses.trim_preview.as_str_lower()
+    )?;
+    writeln!(w, "#Reads Trimmed\t{}", trim.reads_trimmed)?;
+    writeln!(w, "#Total Bases Removed\t{}", trim.bases_removed)?;
+    writeln!(w, "#Total Bases Retained\t{}", trim.bases_retained)?;
+    writeln!(w, "#Mean Trimmed Length\t{:.2}", trim.mean_trimmed_length)?;
+    writeln!(w, "#Bases Removed\tRead Count")?;
+    for row in &trim.rows {
+        writeln!(w, "{}\t{}", row.removed, row.reads)?;
+    }
+    writeln!(w, ">>END_MODULE")?;
+    Ok(())
+}
+
 fn write_adapter_content_long(
     w: &mut dyn Write,
     metrics: &crate::core::metrics::FinalMetrics,
@@ -304,6 +505,13 @@ fn write_adapter_content_long(
         }
         writeln!(w)?;
     }
+    for row in &metrics.adapter_positions {
+        write!(w, "{}", row.bin)?;
+        for v in row.values.iter() {
+            write!(w, "\t{:.1}", v)?;
+        }
+        writeln!(w)?;
+    }
     writeln!(w, ">>END_MODULE")?;
     Ok(())
 }
@@ -0,0 +1,60 @@
+use crate::core::engine::RunOutput;
+use crate::report::latex;
+use anyhow::{Context, Result, anyhow};
+use resvg::{tiny_skia, usvg};
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug)]
+pub enum FigureFormat {
+    Svg,
+    Png,
+    Pdf,
+}
+
+impl FigureFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            FigureFormat::Svg => "svg",
+            FigureFormat::Png => "png",
+            FigureFormat::Pdf => "pdf",
+        }
+    }
+}
+
+// Writes each module plot as a standalone file under `figures/`, using the
+// same SVGs (and 800x260 size) as the HTML report.
+pub fn write(out_dir: &Path, output: &RunOutput, format: FigureFormat) -> Result<()> {
+    let metrics = output.agg.finalize(&output.ctx);
+    let figures_dir = out_dir.join("figures");
+    fs::create_dir_all(&figures_dir)
+        .with_context(|| format!("failed to create {}", figures_dir.display()))?;
+
+    for f in latex::module_figures(&metrics, output.ctx.mode)? {
+        let bytes = match format {
+            FigureFormat::Svg => f.svg.into_bytes(),
+            FigureFormat::Png => {
+                svg_to_png(&f.svg).with_context(|| format!("failed to render {}", f.name))?
+            }
+            FigureFormat::Pdf => latex::svg_to_pdf(&f.svg)
+                .with_context(|| format!("failed to convert {} to PDF", f.name))?,
+        };
+        let path = figures_dir.join(format!("{}.{}", f.name, format.extension()));
+        fs::write(&path, bytes).with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn svg_to_png(svg: &str) -> Result<Vec<u8>> {
+    let mut opt = usvg::Options::default();
+    opt.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(svg, &opt).map_err(|e| anyhow!("usvg parse failed: {e}"))?;
+    let size = tree.size().to_int_size();
+    let mut pixmap =
+        tiny_skia::Pixmap::new(size.width(), size.height()).context("figure has zero size")?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap
+        .encode_png()
+        .map_err(|e| anyhow!("PNG encoding failed: {e}"))
+}
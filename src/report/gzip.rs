@@ -0,0 +1,36 @@
+use anyhow::{Context, Result};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Replaces `path` with `<path>.gz`. The gzip header carries no mtime or
+// name, so identical inputs give identical bytes.
+pub fn compress_file(path: &Path) -> Result<PathBuf> {
+    let mut gz_name = path.as_os_str().to_owned();
+    gz_name.push(".gz");
+    let gz_path = PathBuf::from(gz_name);
+    let mut tmp_name = gz_path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let result = (|| -> Result<()> {
+        let mut src =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let dst = File::create(&tmp_path)
+            .with_context(|| format!("failed to create {}", tmp_path.display()))?;
+        let mut enc = GzEncoder::new(BufWriter::new(dst), Compression::default());
+        io::copy(&mut src, &mut enc)?;
+        enc.finish()?.flush()?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    fs::rename(&tmp_path, &gz_path)
+        .with_context(|| format!("failed to move output to {}", gz_path.display()))?;
+    fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))?;
+    Ok(gz_path)
+}
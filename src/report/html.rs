@@ -1,6 +1,9 @@
 use crate::core::engine::RunOutput;
-use crate::core::model::{Mode, Status};
+use crate::core::model::{FinalizeContext, MAX_Q, Mode, QualHist, Status};
 use anyhow::{Context, Result};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use serde::Serialize;
 use std::fmt::Write as FmtWrite;
 use std::fs::File;
 use std::io::{BufWriter, Write};
@@ -10,10 +13,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 fn write_modern(path: &Path, output: &RunOutput) -> Result<()> {
     let metrics = output.agg.finalize(&output.ctx);
     let mut html = String::with_capacity(256 * 1024);
-    let ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
+    let ts = report_time();
 
     writeln!(html, "<!DOCTYPE html>")?;
     writeln!(html, "<html lang=\"en\">")?;
@@ -25,7 +25,8 @@ fn write_modern(path: &Path, output: &RunOutput) -> Result<()> {
     )?;
     writeln!(
         html,
-        "<title>kira-qc report: {}</title>",
+        "<title>{}: {}</title>",
+        escape_html(output.ctx.title.as_deref().unwrap_or("kira-qc report")),
         output.ctx.sample_name
     )?;
     writeln!(html, "<style>")?;
@@ -50,6 +51,11 @@ fn write_modern(path: &Path, output: &RunOutput) -> Result<()> {
     writeln!(html, ".pass{{color:#0a7a0a;font-weight:bold;}}")?;
     writeln!(html, ".warn{{color:#d98200;font-weight:bold;}}")?;
     writeln!(html, ".fail{{color:#c00000;font-weight:bold;}}")?;
+    writeln!(
+        html,
+        ".description{{color:#333;font-size:14px;margin:0 0 8px 0;}}"
+    )?;
+    writeln!(html, ".na{{color:#999;font-weight:bold;}}")?;
     writeln!(
         html,
         ".module{{border-top:1px solid #eee;padding-top:8px;}}"
@@ -82,15 +88,15 @@ fn write_modern(path: &Path, output: &RunOutput) -> Result<()> {
         Mode::Long => "Long-read (ONT / PacBio)",
     };
 
-    writeln!(html, "<h1>kira-qc report</h1>")?;
+    report_heading(&mut html, &output.ctx, "kira-qc report")?;
     writeln!(
         html,
-        "<div class=\"meta\">Sample: <b>{}</b><br/>File: {}<br/>Mode: {}<br/>Timestamp: {} (unix: {})</div>",
+        "<div class=\"meta\">Sample: <b>{}</b><br/>File: {}<br/>Mode: {}<br/>Timestamp: {}{}</div>",
         output.ctx.sample_name,
         output.ctx.file_name,
         mode_label,
-        fmt_timestamp(ts),
-        ts
+        fmt_report_time(ts),
+        meta_notes(&output.ctx, &metrics)
     )?;
 
     writeln!(html, "<h2>Summary</h2>")?;
@@ -178,7 +184,7 @@ fn write_modern(path: &Path, output: &RunOutput) -> Result<()> {
         Mode::Short => {
             module_per_base_quality(&mut html, &metrics)?;
             module_per_seq_quality(&mut html, &metrics)?;
-            module_per_base_content(&mut html, &metrics)?;
+            module_per_base_content(&mut html, &metrics, false)?;
             module_per_seq_gc(&mut html, &metrics)?;
             module_per_base_n(&mut html, &metrics)?;
             module_length_dist_short(&mut html, &metrics)?;
@@ -208,13 +214,174 @@ fn write_modern(path: &Path, output: &RunOutput) -> Result<()> {
     Ok(())
 }
 
-pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
+const INTERACTIVE_JS: &str = include_str!("interactive.js");
+const ADAPTER_COLORS: [&str; 5] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd"];
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HtmlOptions {
+    // Adds zoomable, hoverable charts for per-base quality and adapter
+    // content, keeping the static SVGs as a <noscript> fallback.
+    pub interactive: bool,
+    // Replaces each inline plot SVG with an embedded PNG, for mail clients
+    // that strip SVG.
+    pub png_plots: bool,
+    pub qual_plot: QualPlot,
+    pub theme: Theme,
+    // Marks the per base content lines with their values at a few sampled
+    // positions (labels plus hover tooltips).
+    pub content_annotate: bool,
+    // Appends summary.txt and fastqc_data.txt as collapsible plain text, so
+    // the page carries everything the report directory would.
+    pub embed_data: bool,
+}
+
+// Static rendering of the per-base quality module.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum QualPlot {
+    #[default]
+    Boxplot,
+    // One column per position, shaded by that position's quality histogram.
+    Heatmap,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Theme {
+    #[default]
+    Default,
+    Dark,
+    // Okabe-Ito / Tol hues that stay distinct under red-green colour blindness.
+    Colorblind,
+}
+
+impl Theme {
+    fn palette(self) -> &'static Palette {
+        match self {
+            Theme::Default => &DEFAULT_PALETTE,
+            Theme::Dark => &DARK_PALETTE,
+            Theme::Colorblind => &COLORBLIND_PALETTE,
+        }
+    }
+}
+
+// Colours shared by the stylesheet, the status icons and the plot bands, so
+// a theme changes all of them together.
+struct Palette {
+    page: &'static str,
+    panel: &'static str,
+    sidebar: &'static str,
+    text: &'static str,
+    description: &'static str,
+    desc: &'static str,
+    muted: &'static str,
+    link: &'static str,
+    border: &'static str,
+    rule: &'static str,
+    header: &'static str,
+    header_border: &'static str,
+    focus: &'static str,
+    plot_bg: &'static str,
+    plot_border: &'static str,
+    pass: &'static str,
+    warn: &'static str,
+    fail: &'static str,
+    na: &'static str,
+    band_good: &'static str,
+    band_warn: &'static str,
+    band_bad: &'static str,
+}
+
+const DEFAULT_PALETTE: Palette = Palette {
+    page: "#eee",
+    panel: "#fff",
+    sidebar: "#f6f6f6",
+    text: "#222",
+    description: "#333",
+    desc: "#444",
+    muted: "#555",
+    link: "#003366",
+    border: "#ddd",
+    rule: "#eee",
+    header: "#3b6ea5",
+    header_border: "#2f5a86",
+    focus: "#99c",
+    plot_bg: "#fafafa",
+    plot_border: "#e5e5e5",
+    pass: "#2e8b57",
+    warn: "#e6a400",
+    fail: "#c00000",
+    na: "#999999",
+    band_good: "#cdeccf",
+    band_warn: "#ffe5b4",
+    band_bad: "#f4c7c3",
+};
+
+// Plot canvases stay light so axis labels and series colours keep their
+// contrast; only the page chrome goes dark.
+const DARK_PALETTE: Palette = Palette {
+    page: "#121212",
+    panel: "#1e1e1e",
+    sidebar: "#181818",
+    text: "#e0e0e0",
+    description: "#d0d0d0",
+    desc: "#c0c0c0",
+    muted: "#9e9e9e",
+    link: "#8ab4f8",
+    border: "#3a3a3a",
+    rule: "#2c2c2c",
+    header: "#2b4f75",
+    header_border: "#23405f",
+    focus: "#5c6bc0",
+    plot_bg: "#e8e8e8",
+    plot_border: "#3a3a3a",
+    pass: "#4caf50",
+    warn: "#ffb300",
+    fail: "#ef5350",
+    na: "#757575",
+    band_good: "#b9dcbc",
+    band_warn: "#f0d29c",
+    band_bad: "#e8b0aa",
+};
+
+const COLORBLIND_PALETTE: Palette = Palette {
+    pass: "#0072b2",
+    warn: "#e69f00",
+    fail: "#882255",
+    band_good: "#d6e6f4",
+    band_warn: "#fbe3b0",
+    band_bad: "#e6c6d6",
+    ..DEFAULT_PALETTE
+};
+
+fn gc_skew_bands(pal: &Palette) -> [(f64, f64, &str); 3] {
+    [
+        (-1.0, -0.1, pal.band_warn),
+        (-0.1, 0.1, pal.band_good),
+        (0.1, 1.0, pal.band_warn),
+    ]
+}
+
+#[derive(Serialize)]
+struct PlotSpec<'a> {
+    x: Vec<f64>,
+    series: Vec<PlotSeries<'a>>,
+    y_min: f64,
+    y_max: f64,
+    x_label: &'a str,
+    y_label: &'a str,
+}
+
+#[derive(Serialize)]
+struct PlotSeries<'a> {
+    name: &'a str,
+    color: &'a str,
+    values: Vec<f64>,
+}
+
+pub fn write(path: &Path, output: &RunOutput, opts: &HtmlOptions) -> Result<()> {
     let metrics = output.agg.finalize(&output.ctx);
     let mut html = String::with_capacity(256 * 1024);
-    let ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
+    let ts = report_time();
+    let pal = opts.theme.palette();
 
     writeln!(html, "<!DOCTYPE html>")?;
     writeln!(html, "<html lang=\"en\">")?;
@@ -226,13 +393,21 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
     )?;
     writeln!(
         html,
-        "<title>FastQC-compatible report: {}</title>",
+        "<title>{}: {}</title>",
+        escape_html(
+            output
+                .ctx
+                .title
+                .as_deref()
+                .unwrap_or("FastQC-compatible report")
+        ),
         output.ctx.sample_name
     )?;
     writeln!(html, "<style>")?;
     writeln!(
         html,
-        "body{{font-family:Arial,Helvetica,sans-serif;margin:0;background:#eee;color:#222;}}"
+        "body{{font-family:Arial,Helvetica,sans-serif;margin:0;background:{};color:{};}}",
+        pal.page, pal.text
     )?;
     writeln!(
         html,
@@ -240,7 +415,8 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
     )?;
     writeln!(
         html,
-        ".sidebar{{width:260px;position:sticky;top:16px;align-self:flex-start;background:#f6f6f6;border:1px solid #ddd;border-radius:4px;padding:10px;}}"
+        ".sidebar{{width:260px;position:sticky;top:16px;align-self:flex-start;background:{};border:1px solid {};border-radius:4px;padding:10px;}}",
+        pal.sidebar, pal.border
     )?;
     writeln!(html, ".sidebar h2{{margin:4px 0 8px 0;font-size:16px;}}")?;
     writeln!(html, ".sidebar ul{{list-style:none;margin:0;padding:0;}}")?;
@@ -248,21 +424,33 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
         html,
         ".sidebar li{{display:flex;align-items:center;gap:8px;padding:4px 0;font-size:13px;}}"
     )?;
-    writeln!(html, ".sidebar a{{color:#003366;text-decoration:none;}}")?;
+    writeln!(
+        html,
+        ".sidebar a{{color:{};text-decoration:none;}}",
+        pal.link
+    )?;
     writeln!(html, ".sidebar a:hover{{text-decoration:underline;}}")?;
     writeln!(
         html,
-        ".main{{flex:1;background:#fff;border:1px solid #ddd;border-radius:4px;box-shadow:0 1px 3px rgba(0,0,0,0.08);padding:16px 20px;}}"
+        ".main{{flex:1;background:{};border:1px solid {};border-radius:4px;box-shadow:0 1px 3px rgba(0,0,0,0.08);padding:16px 20px;}}",
+        pal.panel, pal.border
     )?;
     writeln!(html, "h1{{margin:0 0 6px 0;font-size:22px;}}")?;
     writeln!(html, "h2{{margin:20px 0 6px 0;font-size:18px;}}")?;
     writeln!(
         html,
-        ".meta{{color:#555;font-size:12px;margin-bottom:12px;}}"
+        ".meta{{color:{};font-size:12px;margin-bottom:12px;}}",
+        pal.muted
     )?;
     writeln!(
         html,
-        ".module{{padding:8px 0 14px 0;border-bottom:1px solid #eee;}}"
+        ".description{{color:{};font-size:14px;margin:0 0 8px 0;}}",
+        pal.description
+    )?;
+    writeln!(
+        html,
+        ".module{{padding:8px 0 14px 0;border-bottom:1px solid {};}}",
+        pal.rule
     )?;
     writeln!(html, ".module:last-child{{border-bottom:none;}}")?;
     writeln!(
@@ -270,9 +458,16 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
         ".module h2{{display:flex;align-items:center;gap:8px;}}"
     )?;
     writeln!(html, ".plot{{margin:8px 0 6px 0;}}")?;
+    writeln!(html, ".iplot{{position:relative;}}")?;
     writeln!(
         html,
-        ".desc{{color:#444;font-size:13px;max-width:1000px;margin:4px 0 10px 0;}}"
+        ".itip{{display:none;position:absolute;top:8px;background:{};border:1px solid {};padding:4px 6px;font-size:12px;white-space:pre;pointer-events:none;}}",
+        pal.panel, pal.border
+    )?;
+    writeln!(
+        html,
+        ".desc{{color:{};font-size:13px;max-width:1000px;margin:4px 0 10px 0;}}",
+        pal.desc
     )?;
     writeln!(
         html,
@@ -280,7 +475,8 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
     )?;
     writeln!(
         html,
-        ".table th,.table td{{border:1px solid #ddd;padding:4px 6px;text-align:right;}}"
+        ".table th,.table td{{border:1px solid {};padding:4px 6px;text-align:right;}}",
+        pal.border
     )?;
     writeln!(
         html,
@@ -292,11 +488,13 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
     )?;
     writeln!(
         html,
-        ".bs-table th{{background:#3b6ea5;color:#fff;text-align:left;padding:4px 6px;border:1px solid #2f5a86;}}"
+        ".bs-table th{{background:{};color:#fff;text-align:left;padding:4px 6px;border:1px solid {};}}",
+        pal.header, pal.header_border
     )?;
     writeln!(
         html,
-        ".bs-table td{{border:1px solid #ddd;padding:4px 6px;text-align:left;}}"
+        ".bs-table td{{border:1px solid {};padding:4px 6px;text-align:left;}}",
+        pal.border
     )?;
     writeln!(html, "details{{margin:6px 0 0 0;}}")?;
     writeln!(
@@ -305,9 +503,23 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
     )?;
     writeln!(
         html,
-        "section:target{{outline:2px solid #99c;outline-offset:4px;border-radius:4px;}}"
+        "section:target{{outline:2px solid {};outline-offset:4px;border-radius:4px;}}",
+        pal.focus
     )?;
-    writeln!(html, "svg{{background:#fafafa;border:1px solid #e5e5e5;}}")?;
+    writeln!(
+        html,
+        "svg{{background:{};border:1px solid {};}}",
+        pal.plot_bg, pal.plot_border
+    )?;
+    // Status icons fill with currentColor, so these also colour the icons.
+    for (class, color) in [
+        ("pass", pal.pass),
+        ("warn", pal.warn),
+        ("fail", pal.fail),
+        ("na", pal.na),
+    ] {
+        writeln!(html, ".{}{{color:{};}}", class, color)?;
+    }
     writeln!(html, "</style>")?;
     writeln!(html, "</head>")?;
     writeln!(html, "<body>")?;
@@ -335,6 +547,12 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
                 "Per base sequence quality",
                 module_id_per_base_qual(),
             )?;
+            sidebar_item(
+                &mut html,
+                metrics.statuses.error_rate,
+                "Per base error rate",
+                module_id_error_rate(),
+            )?;
             sidebar_item(
                 &mut html,
                 metrics.statuses.per_seq_qual,
@@ -361,35 +579,67 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
             )?;
             sidebar_item(
                 &mut html,
-                metrics.statuses.length_dist,
-                "Sequence Length Distribution",
-                module_id_length_dist(),
+                metrics.statuses.gc_skew,
+                "Per base GC skew",
+                module_id_gc_skew(),
             )?;
             sidebar_item(
                 &mut html,
-                metrics.statuses.duplication,
-                "Sequence Duplication Levels",
-                module_id_duplication(),
+                metrics.statuses.length_dist,
+                "Sequence Length Distribution",
+                module_id_length_dist(),
             )?;
+            if !metrics.skipped.duplication {
+                sidebar_item(
+                    &mut html,
+                    metrics.statuses.duplication,
+                    "Sequence Duplication Levels",
+                    module_id_duplication(),
+                )?;
+            }
+            if !metrics.skipped.overrepresented {
+                sidebar_item(
+                    &mut html,
+                    metrics.statuses.overrepresented,
+                    "Overrepresented sequences",
+                    module_id_overrep(),
+                )?;
+            }
+            if !metrics.skipped.adapter_content {
+                sidebar_item(
+                    &mut html,
+                    metrics.statuses.adapter_content,
+                    "Adapter Content",
+                    module_id_adapter_content(),
+                )?;
+                sidebar_item(
+                    &mut html,
+                    metrics.statuses.trim_preview,
+                    "Adapter Trimming Preview",
+                    module_id_trim_preview(),
+                )?;
+            }
             sidebar_item(
                 &mut html,
-                metrics.statuses.overrepresented,
-                "Overrepresented sequences",
-                module_id_overrep(),
+                metrics.statuses.sequence_complexity,
+                "Sequence Complexity",
+                module_id_sequence_complexity(),
             )?;
             sidebar_item(
                 &mut html,
-                metrics.statuses.adapter_content,
-                "Adapter Content",
-                module_id_adapter_content(),
+                metrics.statuses.base_composition,
+                "Base Composition",
+                module_id_base_composition(),
             )?;
             #[cfg(not(feature = "no-kmer"))]
-            sidebar_item(
-                &mut html,
-                metrics.statuses.kmer_content,
-                "Kmer Content",
-                module_id_kmer(),
-            )?;
+            if !metrics.skipped.kmer_content {
+                sidebar_item(
+                    &mut html,
+                    metrics.statuses.kmer_content,
+                    "Kmer Content",
+                    module_id_kmer(),
+                )?;
+            }
         }
         Mode::Long => {
             sidebar_item(
@@ -404,6 +654,14 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
                 "Per sequence quality scores",
                 module_id_per_seq_qual(),
             )?;
+            if !metrics.length_quality.is_empty() {
+                sidebar_item(
+                    &mut html,
+                    metrics.statuses.length_quality,
+                    "Length vs Quality",
+                    module_id_length_quality(),
+                )?;
+            }
             sidebar_item(
                 &mut html,
                 metrics.statuses.per_seq_gc,
@@ -416,11 +674,25 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
                 "Per sequence N content",
                 module_id_per_seq_n(),
             )?;
+            if !metrics.skipped.adapter_content {
+                sidebar_item(
+                    &mut html,
+                    metrics.statuses.adapter_content,
+                    "Adapter Content",
+                    module_id_adapter_content(),
+                )?;
+            }
             sidebar_item(
                 &mut html,
-                metrics.statuses.adapter_content,
-                "Adapter Content",
-                module_id_adapter_content(),
+                metrics.statuses.sequence_complexity,
+                "Sequence Complexity",
+                module_id_sequence_complexity(),
+            )?;
+            sidebar_item(
+                &mut html,
+                metrics.statuses.base_composition,
+                "Base Composition",
+                module_id_base_composition(),
             )?;
         }
     }
@@ -428,51 +700,130 @@ pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
     writeln!(html, "</aside>")?;
 
     writeln!(html, "<main class=\"main\">")?;
-    writeln!(html, "<h1>kira-qc FastQC-compatible Report</h1>")?;
+    report_heading(&mut html, &output.ctx, "kira-qc FastQC-compatible Report")?;
     writeln!(
         html,
-        "<div class=\"meta\">File: {}<br/>Mode: {}<br/>Timestamp: {} (unix: {})</div>",
+        "<div class=\"meta\">File: {}<br/>Mode: {}<br/>Timestamp: {}{}</div>",
         output.ctx.file_name,
         mode_label,
-        fmt_timestamp(ts),
-        ts
+        fmt_report_time(ts),
+        meta_notes(&output.ctx, &metrics)
     )?;
 
     compat_basic_stats(&mut html, &metrics, &output.ctx.file_name)?;
     match output.ctx.mode {
         Mode::Short => {
-            compat_per_base_quality(&mut html, &metrics)?;
+            compat_per_base_quality(&mut html, &metrics, &output.agg.per_pos_qual, opts)?;
+            compat_error_rate(&mut html, &metrics)?;
             compat_per_seq_quality(&mut html, &metrics)?;
-            compat_per_base_content(&mut html, &metrics)?;
-            compat_per_seq_gc(&mut html, &metrics)?;
-            compat_per_base_n(&mut html, &metrics)?;
+            compat_per_base_content(&mut html, &metrics, opts.content_annotate)?;
+            compat_per_seq_gc(&mut html, &metrics, pal)?;
+            compat_per_base_n(&mut html, &metrics, pal)?;
+            compat_gc_skew(&mut html, &metrics, pal)?;
             compat_length_dist_short(&mut html, &metrics)?;
-            compat_duplication(&mut html, &metrics)?;
-            compat_overrep(&mut html, &metrics)?;
-            compat_adapter_content_short(&mut html, &metrics)?;
+            if !metrics.skipped.duplication {
+                compat_duplication(&mut html, &metrics)?;
+            }
+            if !metrics.skipped.overrepresented {
+                compat_overrep(&mut html, &metrics)?;
+            }
+            if !metrics.skipped.adapter_content {
+                compat_adapter_content_short(&mut html, &metrics, opts)?;
+                compat_trim_preview(&mut html, &metrics)?;
+            }
+            compat_sequence_complexity(&mut html, &metrics, pal)?;
+            compat_base_composition(&mut html, &metrics, output.ctx.strand_bias_pct)?;
             #[cfg(not(feature = "no-kmer"))]
-            compat_kmer_content(&mut html, &metrics)?;
+            if !metrics.skipped.kmer_content {
+                compat_kmer_content(&mut html, &metrics)?;
+            }
         }
         Mode::Long => {
             compat_length_dist_long(&mut html, &metrics)?;
             compat_per_seq_quality(&mut html, &metrics)?;
-            compat_per_seq_gc(&mut html, &metrics)?;
-            compat_per_seq_n(&mut html, &metrics)?;
-            compat_adapter_content_long(&mut html, &metrics)?;
+            if !metrics.length_quality.is_empty() {
+                compat_length_quality(&mut html, &metrics)?;
+            }
+            compat_per_seq_gc(&mut html, &metrics, pal)?;
+            compat_per_seq_n(&mut html, &metrics, pal)?;
+            if !metrics.skipped.adapter_content {
+                compat_adapter_content_long(&mut html, &metrics)?;
+            }
+            compat_sequence_complexity(&mut html, &metrics, pal)?;
+            compat_base_composition(&mut html, &metrics, output.ctx.strand_bias_pct)?;
         }
     }
 
+    if opts.embed_data {
+        raw_data_section(&mut html, output)?;
+    }
+
     writeln!(html, "<div class=\"meta\">Produced by kira-qc</div>")?;
     writeln!(html, "</main>")?;
     writeln!(html, "</div>")?;
+    if opts.interactive {
+        writeln!(html, "<script>{}</script>", INTERACTIVE_JS)?;
+    }
     writeln!(html, "</body></html>")?;
+    if opts.png_plots {
+        html = embed_plots_as_png(&html)?;
+    }
 
-    let mut w =
-        BufWriter::new(File::create(path).with_context(|| "create fastqc_compat.html failed")?);
+    let mut w = BufWriter::new(
+        File::create(path).with_context(|| format!("create {} failed", path.display()))?,
+    );
     w.write_all(html.as_bytes())?;
     Ok(())
 }
 
+fn raw_data_section(out: &mut String, output: &RunOutput) -> Result<()> {
+    let mut summary = Vec::new();
+    crate::report::summary_txt::write_to(&mut summary, output)?;
+    let mut data = Vec::new();
+    crate::report::fastqc_txt::write_to(&mut data, output)?;
+    writeln!(out, "<section id=\"raw_data\" class=\"module\">")?;
+    writeln!(out, "<h2>Raw data</h2>")?;
+    for (name, text) in [("summary.txt", summary), ("fastqc_data.txt", data)] {
+        writeln!(
+            out,
+            "<details><summary>{}</summary><pre>{}</pre></details>",
+            name,
+            escape_html(&String::from_utf8_lossy(&text))
+        )?;
+    }
+    compat_section_footer(out)
+}
+
+// Rasterizes the SVG inside every plot container and swaps it for a
+// base64 PNG <img>, so the page keeps no inline plot SVG. Status icons are
+// left as SVG.
+fn embed_plots_as_png(html: &str) -> Result<String> {
+    const PLOT_OPEN: &str = "<div class=\"plot\">";
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(open) = rest.find(PLOT_OPEN) {
+        let body = open + PLOT_OPEN.len();
+        let (Some(svg_start), Some(svg_len)) = (
+            rest[body..].find("<svg").map(|i| body + i),
+            rest[body..].find("</svg>"),
+        ) else {
+            break;
+        };
+        let svg_end = body + svg_len + "</svg>".len();
+        let png = crate::report::figures::svg_to_png(&rest[svg_start..svg_end])
+            .context("failed to rasterize HTML plot")?;
+        out.push_str(&rest[..svg_start]);
+        write!(
+            out,
+            "<img src=\"data:image/png;base64,{}\" alt=\"\"/>",
+            BASE64_STANDARD.encode(png)
+        )?;
+        rest = &rest[svg_end..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
 fn summary_row(out: &mut String, status: Status, name: &str) -> Result<()> {
     let class = status_class(status);
     writeln!(
@@ -497,6 +848,191 @@ fn module_desc(out: &mut String, text: &str) -> Result<()> {
     Ok(())
 }
 
+// Where runs of consecutive Ns sit in their reads: end-anchored runs point
+// to read-through or trailing no-calls, internal ones to dropouts mid-read.
+fn n_run_note(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    use crate::core::metrics::{N_RUN_END, N_RUN_INTERNAL, N_RUN_START};
+    let n = &metrics.n_runs;
+    if n.runs.iter().all(|&r| r == 0) {
+        return Ok(());
+    }
+    module_desc(
+        out,
+        &format!(
+            "N runs: {} at read start ({} bases), {} internal ({} bases), {} at read end ({} bases).",
+            fmt_int(n.runs[N_RUN_START]),
+            fmt_int(n.bases[N_RUN_START]),
+            fmt_int(n.runs[N_RUN_INTERNAL]),
+            fmt_int(n.bases[N_RUN_INTERNAL]),
+            fmt_int(n.runs[N_RUN_END]),
+            fmt_int(n.bases[N_RUN_END])
+        ),
+    )
+}
+
+// --content-bias-window: rates the leading window (priming bias) apart
+// from the rest of the read (tail bias).
+fn content_bias_note(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    use crate::core::metrics::deviation_status;
+    let Some(bias) = &metrics.content_bias else {
+        return Ok(());
+    };
+    module_desc(
+        out,
+        &format!(
+            "Priming bias (first {n} bp): max deviation {:.1}% ({}). Tail bias (after {n} bp): max deviation {:.1}% ({}).",
+            bias.head_deviation,
+            deviation_status(bias.head_deviation).as_str_upper(),
+            bias.tail_deviation,
+            deviation_status(bias.tail_deviation).as_str_upper(),
+            n = bias.window
+        ),
+    )
+}
+
+// Binned qualities make quartiles snap between a few levels, so the
+// per-base module is drawn as bars and the levels are listed.
+// Names the positions that set a WARN / FAIL per base quality status.
+fn low_quality_positions_note(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    use crate::core::metrics::{MEDIAN_FAIL, MEDIAN_WARN};
+    let low = &metrics.per_base_qual_low;
+    let mut parts = Vec::new();
+    if !low.below_fail.is_empty() {
+        parts.push(format!(
+            "positions {} below Q{}",
+            fmt_position_runs(&low.below_fail),
+            MEDIAN_FAIL
+        ));
+    }
+    if !low.below_warn.is_empty() {
+        parts.push(format!(
+            "positions {} below Q{}",
+            fmt_position_runs(&low.below_warn),
+            MEDIAN_WARN
+        ));
+    }
+    if parts.is_empty() {
+        return Ok(());
+    }
+    module_desc(out, &format!("Median quality: {}.", parts.join("; ")))
+}
+
+fn fmt_position_runs(runs: &[(usize, usize)]) -> String {
+    runs.iter()
+        .map(|&(first, last)| {
+            if first == last {
+                first.to_string()
+            } else {
+                format!("{}\u{2013}{}", first, last)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn binned_quality_note(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    if metrics.binned_quality_levels.is_empty() {
+        return Ok(());
+    }
+    let levels = metrics
+        .binned_quality_levels
+        .iter()
+        .map(|q| format!("Q{}", q))
+        .collect::<Vec<_>>()
+        .join(", ");
+    module_desc(
+        out,
+        &format!(
+            "Binned quality detected ({}); bars show the mean quality and the line the median at each position.",
+            levels
+        ),
+    )
+}
+
+fn gc_deviation_note(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    let Some(deviation) = &metrics.gc_deviation else {
+        return Ok(());
+    };
+    module_desc(
+        out,
+        &format!(
+            "{:.1}% of reads deviate from {} (warns above 15%, fails above 30%).",
+            deviation.deviation_pct,
+            escape_html(&deviation.reference)
+        ),
+    )
+}
+
+fn top_sequence_note(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    let Some(top) = &metrics.top_sequence else {
+        return Ok(());
+    };
+    let mut note = format!(
+        "Most common sequence: {:.2}% of reads ({} copies)",
+        top.percent,
+        fmt_int(top.count)
+    );
+    if let Some(seq) = &top.sequence {
+        let _ = write!(note, ", <code>{}</code>", escape_html(seq));
+    }
+    note.push('.');
+    module_desc(out, &note)
+}
+
+fn dup_saturation_note(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    let Some(saturation) = metrics.dup_saturation else {
+        return Ok(());
+    };
+    let mut note = format!(
+        "The duplication sketch was saturated ({} evictions for {} reads), so these levels are a lower bound.",
+        fmt_int(saturation.evictions),
+        fmt_int(metrics.basic.total_sequences)
+    );
+    if saturation.larger_sketch {
+        note.push_str(" Rerun with <code>--memory high</code> to track more sequences.");
+    }
+    module_desc(out, &note)
+}
+
+fn optical_dup_note(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    let Some(optical) = &metrics.optical_dups else {
+        return Ok(());
+    };
+    let pct = |reads: f64| reads * 100.0 / optical.sampled_reads as f64;
+    module_desc(
+        out,
+        &format!(
+            "Optical duplicates (same tile, within {} px): {:.2}% of reads; library (PCR) duplicates: {:.2}%. Estimated from {} reads with Illumina coordinates in a hash sample of sequences.",
+            optical.distance,
+            pct(optical.optical_reads),
+            pct(optical.duplicate_reads as f64 - optical.optical_reads),
+            fmt_int(optical.sampled_reads)
+        ),
+    )
+}
+
+fn truncation_note(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    if metrics.reads_truncated > 0 {
+        module_desc(
+            out,
+            &format!(
+                "Positions beyond {} bp are not tracked ({} reads are longer; see --max-read-len).",
+                metrics.max_read_len, metrics.reads_truncated
+            ),
+        )?;
+    }
+    Ok(())
+}
+
 fn module_footer(out: &mut String) -> Result<()> {
     writeln!(out, "</div>")?;
     Ok(())
@@ -507,19 +1043,22 @@ fn status_class(status: Status) -> &'static str {
         Status::Pass => "pass",
         Status::Warn => "warn",
         Status::Fail => "fail",
+        Status::NotApplicable => "na",
     }
 }
 
 fn status_icon_svg(status: Status, size: u32) -> String {
-    let (fill, mark) = match status {
-        Status::Pass => ("#2e8b57", "M6 10 L10 14 L18 6"),
-        Status::Warn => ("#e6a400", "M11 5 L11 13 M11 16 L11 18"),
-        Status::Fail => ("#c00000", "M6 6 L18 18 M18 6 L6 18"),
+    let mark = match status {
+        Status::Pass => "M6 10 L10 14 L18 6",
+        Status::Warn => "M11 5 L11 13 M11 16 L11 18",
+        Status::Fail => "M6 6 L18 18 M18 6 L6 18",
+        Status::NotApplicable => "M7 12 L17 12",
     };
+    // The circle takes the theme's status colour from the class.
     format!(
-        "<svg width=\"{s}\" height=\"{s}\" viewBox=\"0 0 24 24\" aria-hidden=\"true\"><circle cx=\"12\" cy=\"12\" r=\"11\" fill=\"{f}\"/><path d=\"{p}\" stroke=\"#fff\" stroke-width=\"2\" fill=\"none\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/></svg>",
+        "<svg class=\"{c}\" width=\"{s}\" height=\"{s}\" viewBox=\"0 0 24 24\" aria-hidden=\"true\"><circle cx=\"12\" cy=\"12\" r=\"11\" fill=\"currentColor\"/><path d=\"{p}\" stroke=\"#fff\" stroke-width=\"2\" fill=\"none\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/></svg>",
+        c = status_class(status),
         s = size,
-        f = fill,
         p = mark
     )
 }
@@ -568,6 +1107,16 @@ fn module_id_per_seq_gc() -> &'static str {
 fn module_id_per_base_n() -> &'static str {
     "per_base_n_content"
 }
+fn module_id_error_rate() -> &'static str {
+    "per_base_error_rate"
+}
+
+fn module_id_gc_skew() -> &'static str {
+    "per_base_gc_skew"
+}
+fn module_id_length_quality() -> &'static str {
+    "length_vs_quality"
+}
 fn module_id_per_seq_n() -> &'static str {
     "per_sequence_n_content"
 }
@@ -583,9 +1132,18 @@ fn module_id_overrep() -> &'static str {
 fn module_id_adapter_content() -> &'static str {
     "adapter_content"
 }
+fn module_id_trim_preview() -> &'static str {
+    "adapter_trimming_preview"
+}
 fn module_id_kmer() -> &'static str {
     "kmer_content"
 }
+fn module_id_sequence_complexity() -> &'static str {
+    "sequence_complexity"
+}
+fn module_id_base_composition() -> &'static str {
+    "base_composition"
+}
 
 fn table_with_summary<F>(out: &mut String, summary: &str, f: F) -> Result<()>
 where
@@ -620,6 +1178,9 @@ fn compat_basic_stats(
     writeln!(out, "<table class=\"bs-table\">")?;
     writeln!(out, "<tr><th>Measure</th><th>Value</th></tr>")?;
     writeln!(out, "<tr><td>Filename</td><td>{}</td></tr>", file)?;
+    if let Some(hash) = &metrics.basic.file_hash {
+        writeln!(out, "<tr><td>File hash</td><td>{}</td></tr>", hash)?;
+    }
     writeln!(
         out,
         "<tr><td>File type</td><td>{}</td></tr>",
@@ -658,6 +1219,57 @@ fn compat_basic_stats(
         "<tr><td>%GC</td><td>{}</td></tr>",
         metrics.basic.gc_percent
     )?;
+    writeln!(
+        out,
+        "<tr><td>%Ambiguous</td><td>{:.2}</td></tr>",
+        metrics.basic.ambiguous_percent
+    )?;
+    if let Some(pct) = metrics.basic.softmasked_percent {
+        writeln!(out, "<tr><td>%Soft-masked</td><td>{:.2}</td></tr>", pct)?;
+    }
+    if let Some(coverage) = metrics.basic.estimated_coverage {
+        writeln!(
+            out,
+            "<tr><td>Estimated coverage</td><td>{:.1}&times;</td></tr>",
+            coverage
+        )?;
+    }
+    if let Some(record) = metrics.basic.first_invalid_qual_record {
+        writeln!(
+            out,
+            "<tr><td>Invalid quality bytes</td><td>{} (first in record {})</td></tr>",
+            fmt_int(metrics.basic.invalid_qual_bytes),
+            fmt_int(record)
+        )?;
+    }
+    if metrics.basic.malformed_records > 0 {
+        writeln!(
+            out,
+            "<tr><td>Malformed records skipped</td><td>{}</td></tr>",
+            fmt_int(metrics.basic.malformed_records)
+        )?;
+    }
+    if metrics.basic.ws_trimmed_records > 0 {
+        writeln!(
+            out,
+            "<tr><td>Records with trailing whitespace trimmed</td><td>{}</td></tr>",
+            fmt_int(metrics.basic.ws_trimmed_records)
+        )?;
+    }
+    for (label, values) in [
+        ("Instruments", &metrics.basic.instruments),
+        ("Flowcells", &metrics.basic.flowcells),
+        ("Lanes", &metrics.basic.lanes),
+    ] {
+        if !values.is_empty() {
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                label,
+                escape_html(&values.join(", "))
+            )?;
+        }
+    }
     writeln!(out, "</table>")?;
     compat_section_footer(out)
 }
@@ -665,6 +1277,8 @@ fn compat_basic_stats(
 fn compat_per_base_quality(
     out: &mut String,
     metrics: &crate::core::metrics::FinalMetrics,
+    per_pos_qual: &[QualHist],
+    opts: &HtmlOptions,
 ) -> Result<()> {
     compat_section_header(
         out,
@@ -676,21 +1290,92 @@ fn compat_per_base_quality(
         out,
         "Shows quality score distributions at each base position. Systematic drops toward read ends often reflect sequencing degradation or adapter read-through.",
     )?;
+    binned_quality_note(out, metrics)?;
+    low_quality_positions_note(out, metrics)?;
+    truncation_note(out, metrics)?;
     let (w, h) = (800.0, 260.0);
     let max_q = metrics
         .per_base_qual
         .iter()
         .map(|r| r.p90 as f64)
         .fold(40.0, f64::max);
-    svg_boxplot(
-        out,
-        &metrics.per_base_qual,
+    if opts.interactive {
+        let rows = &metrics.per_base_qual;
+        let column = |f: fn(&crate::core::metrics::PerBaseQualRow) -> f64| {
+            rows.iter().map(f).collect::<Vec<_>>()
+        };
+        interactive_plot(
+            out,
+            &PlotSpec {
+                x: column(|r| r.base as f64),
+                series: vec![
+                    PlotSeries {
+                        name: "Mean",
+                        color: "#d62728",
+                        values: column(|r| r.mean),
+                    },
+                    PlotSeries {
+                        name: "Median",
+                        color: "#333333",
+                        values: column(|r| r.median as f64),
+                    },
+                    PlotSeries {
+                        name: "Upper quartile",
+                        color: "#1f77b4",
+                        values: column(|r| r.upper_quartile as f64),
+                    },
+                    PlotSeries {
+                        name: "Lower quartile",
+                        color: "#1f77b4",
+                        values: column(|r| r.lower_quartile as f64),
+                    },
+                    PlotSeries {
+                        name: "90th percentile",
+                        color: "#9ecae1",
+                        values: column(|r| r.p90 as f64),
+                    },
+                    PlotSeries {
+                        name: "10th percentile",
+                        color: "#9ecae1",
+                        values: column(|r| r.p10 as f64),
+                    },
+                ]
+                .into_iter()
+                .chain(
+                    (!metrics.per_base_qual_trend.is_empty()).then(|| PlotSeries {
+                        name: "Mean (smoothed)",
+                        color: QUAL_TREND_COLOR,
+                        values: metrics.per_base_qual_trend.clone(),
+                    }),
+                )
+                .collect(),
+                y_min: 0.0,
+                y_max: max_q,
+                x_label: "Position",
+                y_label: "Quality",
+            },
+        )?;
+        writeln!(out, "<noscript>")?;
+    }
+    let chart = QualChart {
         w,
         h,
         max_q,
-        "Position",
-        "Quality",
-    )?;
+        x_label: "Position",
+        y_label: "Quality",
+        pal: opts.theme.palette(),
+        trend: &metrics.per_base_qual_trend,
+    };
+    match opts.qual_plot {
+        QualPlot::Boxplot if !metrics.binned_quality_levels.is_empty() => {
+            svg_binned_quality(out, &metrics.per_base_qual, &chart)?
+        }
+        QualPlot::Boxplot => svg_boxplot(out, &metrics.per_base_qual, &chart)?,
+        QualPlot::Heatmap => svg_quality_heatmap(out, per_pos_qual, w, h, "Position", "Quality")?,
+    }
+    if opts.interactive {
+        writeln!(out, "</noscript>")?;
+    }
     table_with_summary(out, "Data", |o| {
         table_per_base_quality(o, &metrics.per_base_qual)
     })?;
@@ -711,6 +1396,14 @@ fn compat_per_seq_quality(
         out,
         "Shows the distribution of mean quality per read. A left-shifted distribution indicates overall low-quality reads or mixed data.",
     )?;
+    module_desc(
+        out,
+        &format!(
+            "Most frequent mean quality: Q{}. Reads with mean quality below Q20: {:.2}%.",
+            metrics.per_seq_qual_mode, metrics.per_seq_qual_low_pct
+        ),
+    )?;
+    per_seq_quality_stats(out, metrics)?;
     let (w, h) = (800.0, 260.0);
     let data = metrics
         .per_seq_qual
@@ -727,6 +1420,7 @@ fn compat_per_seq_quality(
 fn compat_per_base_content(
     out: &mut String,
     metrics: &crate::core::metrics::FinalMetrics,
+    annotate: bool,
 ) -> Result<()> {
     compat_section_header(
         out,
@@ -738,16 +1432,30 @@ fn compat_per_base_content(
         out,
         "Shows the percentage of each base at each position. Strong positional biases can indicate priming artifacts or residual adapters.",
     )?;
+    content_bias_note(out, metrics)?;
+    truncation_note(out, metrics)?;
     let (w, h) = (800.0, 260.0);
     legend_base_content(out)?;
-    svg_multi_line(out, &metrics.per_base_content, w, h, "Position", "%")?;
+    svg_multi_line(
+        out,
+        &metrics.per_base_content,
+        w,
+        h,
+        "Position",
+        "%",
+        annotate,
+    )?;
     table_with_summary(out, "Data", |o| {
         table_per_base_content(o, &metrics.per_base_content)
     })?;
     compat_section_footer(out)
 }
 
-fn compat_per_seq_gc(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+fn compat_per_seq_gc(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+    pal: &Palette,
+) -> Result<()> {
     compat_section_header(
         out,
         metrics.statuses.per_seq_gc,
@@ -758,11 +1466,12 @@ fn compat_per_seq_gc(out: &mut String, metrics: &crate::core::metrics::FinalMetr
         out,
         "Shows the distribution of GC% across reads. Broad or multi-modal shapes can indicate contamination or mixed libraries.",
     )?;
+    gc_deviation_note(out, metrics)?;
     let (w, h) = (800.0, 260.0);
     let data = metrics
         .per_seq_gc
         .iter()
-        .map(|r| (r.gc as f64, r.count as f64))
+        .map(|r| (r.gc, r.count as f64))
         .collect::<Vec<_>>();
     svg_histogram_xbands(
         out,
@@ -771,7 +1480,7 @@ fn compat_per_seq_gc(out: &mut String, metrics: &crate::core::metrics::FinalMetr
         h,
         0.0,
         100.0,
-        &[(40.0, 60.0, "#cdeccf")],
+        &[(40.0, 60.0, pal.band_good)],
         "GC%",
         "Count",
     )?;
@@ -779,7 +1488,11 @@ fn compat_per_seq_gc(out: &mut String, metrics: &crate::core::metrics::FinalMetr
     compat_section_footer(out)
 }
 
-fn compat_per_base_n(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+fn compat_per_base_n(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+    pal: &Palette,
+) -> Result<()> {
     compat_section_header(
         out,
         metrics.statuses.per_base_n,
@@ -790,6 +1503,8 @@ fn compat_per_base_n(out: &mut String, metrics: &crate::core::metrics::FinalMetr
         out,
         "Shows the proportion of Ns at each position. Spikes or elevated Ns suggest base-calling issues or low-complexity regions.",
     )?;
+    n_run_note(out, metrics)?;
+    truncation_note(out, metrics)?;
     let (w, h) = (800.0, 260.0);
     let data = metrics
         .per_base_n
@@ -806,9 +1521,9 @@ fn compat_per_base_n(out: &mut String, metrics: &crate::core::metrics::FinalMetr
         y_max,
         "#555",
         &[
-            (0.0, 5.0, "#cdeccf"),
-            (5.0, 20.0, "#ffe5b4"),
-            (20.0, 100.0, "#f4c7c3"),
+            (0.0, 5.0, pal.band_good),
+            (5.0, 20.0, pal.band_warn),
+            (20.0, 100.0, pal.band_bad),
         ],
         "Position",
         "% N",
@@ -817,66 +1532,233 @@ fn compat_per_base_n(out: &mut String, metrics: &crate::core::metrics::FinalMetr
     compat_section_footer(out)
 }
 
-fn compat_per_seq_n(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+fn compat_error_rate(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
     compat_section_header(
         out,
-        metrics.statuses.per_seq_n,
-        "Per sequence N content",
-        module_id_per_seq_n(),
+        metrics.statuses.error_rate,
+        "Per base error rate",
+        module_id_error_rate(),
     )?;
     module_desc(
         out,
-        "Shows the distribution of N% per read. Excess high-N reads indicate poor base-calling or low-quality segments.",
+        "Shows the expected base-call error probability at each position, 10^(-Q/10) averaged over the quality distribution, on a log10 scale (-2 = 1 error in 100 bases, -3 = 1 in 1000).",
     )?;
-    let data = metrics
-        .per_seq_n
-        .iter()
-        .map(|r| (r.n_percent as f64, r.count as f64))
-        .collect::<Vec<_>>();
-    svg_histogram_xbands(
+    truncation_note(out, metrics)?;
+    let (data, min_y) = error_rate_data(metrics);
+    svg_single_line(
         out,
         data.as_slice(),
         800.0,
         260.0,
+        min_y,
         0.0,
-        100.0,
-        &[
-            (0.0, 10.0, "#cdeccf"),
-            (10.0, 20.0, "#ffe5b4"),
-            (20.0, 100.0, "#f4c7c3"),
-        ],
-        "N%",
-        "Count",
+        "#c00000",
+        "Position",
+        "log10 error probability",
     )?;
-    table_with_summary(out, "Data", |o| table_per_seq_n(o, &metrics.per_seq_n))?;
+    table_with_summary(out, "Data", |o| table_error_rate(o, &metrics.error_rate))?;
     compat_section_footer(out)
 }
 
-fn compat_length_dist_short(
+fn compat_gc_skew(
     out: &mut String,
     metrics: &crate::core::metrics::FinalMetrics,
+    pal: &Palette,
 ) -> Result<()> {
     compat_section_header(
         out,
-        metrics.statuses.length_dist,
-        "Sequence Length Distribution",
-        module_id_length_dist(),
+        metrics.statuses.gc_skew,
+        "Per base GC skew",
+        module_id_gc_skew(),
     )?;
     module_desc(
         out,
-        "Shows read length frequencies. Multiple peaks or long tails may indicate trimming or mixed read sources.",
+        "Shows (G-C)/(G+C) at each position. Sustained skew away from zero is expected around replication origins in microbial genomes; in other libraries it points to strand or composition bias.",
     )?;
-    let (w, h) = (800.0, 260.0);
-    let data = metrics
-        .length_dist
-        .iter()
-        .map(|r| (r.length as f64, r.count as f64))
-        .collect::<Vec<_>>();
-    svg_histogram_compat_bars(out, data.as_slice(), w, h, 0.0, 0.0, "Length", "Count")?;
-    table_with_summary(out, "Data", |o| table_length_dist(o, &metrics.length_dist))?;
-    compat_section_footer(out)
-}
-
+    truncation_note(out, metrics)?;
+    let data = gc_skew_data(metrics);
+    svg_single_line_ybands(
+        out,
+        data.as_slice(),
+        800.0,
+        260.0,
+        -1.0,
+        1.0,
+        "#555",
+        &gc_skew_bands(pal),
+        "Position",
+        "GC skew",
+    )?;
+    table_with_summary(out, "Data", |o| table_gc_skew(o, &metrics.gc_skew))?;
+    compat_section_footer(out)
+}
+
+fn compat_per_seq_n(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+    pal: &Palette,
+) -> Result<()> {
+    compat_section_header(
+        out,
+        metrics.statuses.per_seq_n,
+        "Per sequence N content",
+        module_id_per_seq_n(),
+    )?;
+    module_desc(
+        out,
+        "Shows the distribution of N% per read. Excess high-N reads indicate poor base-calling or low-quality segments.",
+    )?;
+    n_run_note(out, metrics)?;
+    let data = metrics
+        .per_seq_n
+        .iter()
+        .map(|r| (r.n_percent as f64, r.count as f64))
+        .collect::<Vec<_>>();
+    svg_histogram_xbands(
+        out,
+        data.as_slice(),
+        800.0,
+        260.0,
+        0.0,
+        100.0,
+        &[
+            (0.0, 10.0, pal.band_good),
+            (10.0, 20.0, pal.band_warn),
+            (20.0, 100.0, pal.band_bad),
+        ],
+        "N%",
+        "Count",
+    )?;
+    table_with_summary(out, "Data", |o| table_per_seq_n(o, &metrics.per_seq_n))?;
+    compat_section_footer(out)
+}
+
+fn compat_sequence_complexity(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+    pal: &Palette,
+) -> Result<()> {
+    compat_section_header(
+        out,
+        metrics.statuses.sequence_complexity,
+        "Sequence Complexity",
+        module_id_sequence_complexity(),
+    )?;
+    module_desc(
+        out,
+        "Shows the distribution of per-read dinucleotide entropy (mean over sliding 64 bp windows, 0-4 bits). Reads below 2 bits are low-complexity: homopolymers, simple repeats or poly-A tails.",
+    )?;
+    module_desc(
+        out,
+        &format!(
+            "Reads below {:.1} bits: {:.2}%.",
+            crate::core::metrics::LOW_ENTROPY_BITS,
+            metrics.low_complexity_pct
+        ),
+    )?;
+    let data = metrics
+        .sequence_complexity
+        .iter()
+        .map(|r| (r.entropy, r.count as f64))
+        .collect::<Vec<_>>();
+    svg_histogram_xbands(
+        out,
+        data.as_slice(),
+        800.0,
+        260.0,
+        0.0,
+        4.0,
+        &[(0.0, 2.0, pal.band_bad), (2.0, 4.0, pal.band_good)],
+        "Entropy (bits)",
+        "Count",
+    )?;
+    table_with_summary(out, "Data", |o| {
+        table_sequence_complexity(o, &metrics.sequence_complexity)
+    })?;
+    compat_section_footer(out)
+}
+
+fn compat_base_composition(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+    strand_bias_pct: f64,
+) -> Result<()> {
+    let bc = &metrics.base_composition;
+    compat_section_header(
+        out,
+        metrics.statuses.base_composition,
+        "Base Composition",
+        module_id_base_composition(),
+    )?;
+    module_desc(
+        out,
+        &format!(
+            "Totals each base over all reads. A and T (and G and C) should occur at similar rates; a gap above {:.1}% of all bases suggests strand-biased library preparation.",
+            strand_bias_pct
+        ),
+    )?;
+    let bases = (bc.a + bc.c + bc.g + bc.t).max(1) as f64;
+    writeln!(out, "<table class=\"bs-table\">")?;
+    writeln!(
+        out,
+        "<tr><th>Base</th><th>Count</th><th>% of ACGT</th></tr>"
+    )?;
+    for (base, count) in [("A", bc.a), ("C", bc.c), ("G", bc.g), ("T", bc.t)] {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{:.2}</td></tr>",
+            base,
+            fmt_int(count),
+            count as f64 * 100.0 / bases
+        )?;
+    }
+    writeln!(out, "</table>")?;
+    writeln!(out, "<table class=\"bs-table\">")?;
+    writeln!(out, "<tr><th>Measure</th><th>Value</th></tr>")?;
+    writeln!(
+        out,
+        "<tr><td>AT/GC ratio</td><td>{:.4}</td></tr>",
+        bc.at_gc_ratio
+    )?;
+    writeln!(
+        out,
+        "<tr><td>A-T imbalance (% of bases)</td><td>{:.2}</td></tr>",
+        bc.at_imbalance_pct
+    )?;
+    writeln!(
+        out,
+        "<tr><td>G-C imbalance (% of bases)</td><td>{:.2}</td></tr>",
+        bc.gc_imbalance_pct
+    )?;
+    writeln!(out, "</table>")?;
+    compat_section_footer(out)
+}
+
+fn compat_length_dist_short(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    compat_section_header(
+        out,
+        metrics.statuses.length_dist,
+        "Sequence Length Distribution",
+        module_id_length_dist(),
+    )?;
+    module_desc(
+        out,
+        "Shows read length frequencies. Multiple peaks or long tails may indicate trimming or mixed read sources.",
+    )?;
+    let (w, h) = (800.0, 260.0);
+    let data = metrics
+        .length_dist
+        .iter()
+        .map(|r| (r.length as f64, r.count as f64))
+        .collect::<Vec<_>>();
+    svg_histogram_compat_bars(out, data.as_slice(), w, h, 0.0, 0.0, "Length", "Count")?;
+    table_with_summary(out, "Data", |o| table_length_dist(o, &metrics.length_dist))?;
+    compat_section_footer(out)
+}
+
 fn compat_length_dist_long(
     out: &mut String,
     metrics: &crate::core::metrics::FinalMetrics,
@@ -892,27 +1774,52 @@ fn compat_length_dist_long(
         "Shows read length frequencies using log-scaled bins. Very long tails or multiple modes may indicate mixed input or variable trimming.",
     )?;
     if let Some(ref ll) = metrics.long_length {
-        let data = ll
-            .bins
-            .iter()
-            .enumerate()
-            .map(|(i, &c)| (i as f64 + 1.0, c as f64))
-            .collect::<Vec<_>>();
-        svg_histogram_compat_bars(
-            out,
-            data.as_slice(),
-            800.0,
-            260.0,
-            0.0,
-            0.0,
-            "Length bin",
-            "Count",
-        )?;
+        if let Some(trimmed) = &ll.trimmed_bins {
+            svg_long_length_trimmed(out, ll, trimmed)?;
+        } else {
+            let data = ll
+                .bins
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| (i as f64 + 1.0, c as f64))
+                .collect::<Vec<_>>();
+            svg_histogram_compat_bars(
+                out,
+                data.as_slice(),
+                800.0,
+                260.0,
+                0.0,
+                0.0,
+                "Length bin",
+                "Count",
+            )?;
+        }
         table_with_summary(out, "Data", |o| table_long_length(o, ll))?;
     }
     compat_section_footer(out)
 }
 
+fn compat_length_quality(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    compat_section_header(
+        out,
+        metrics.statuses.length_quality,
+        "Length vs Quality",
+        module_id_length_quality(),
+    )?;
+    module_desc(
+        out,
+        "Shows how mean read quality varies with read length (log-scaled length bins, 5-unit quality bins; color is log count). Long reads concentrated at low quality often point to degraded or chimeric molecules.",
+    )?;
+    svg_length_quality_heatmap(out, &metrics.length_quality, 800.0, 260.0)?;
+    table_with_summary(out, "Data", |o| {
+        table_length_quality(o, &metrics.length_quality)
+    })?;
+    compat_section_footer(out)
+}
+
 fn compat_duplication(
     out: &mut String,
     metrics: &crate::core::metrics::FinalMetrics,
@@ -927,6 +1834,16 @@ fn compat_duplication(
         out,
         "Estimates duplication using a streaming heavy-hitter model. High duplication often indicates PCR over-amplification or low library complexity.",
     )?;
+    module_desc(
+        out,
+        &format!(
+            "Percent of seqs remaining if deduplicated: {:.2}%",
+            metrics.deduplicated_pct
+        ),
+    )?;
+    top_sequence_note(out, metrics)?;
+    dup_saturation_note(out, metrics)?;
+    optical_dup_note(out, metrics)?;
     let data = metrics
         .duplication
         .iter()
@@ -965,6 +1882,7 @@ fn compat_overrep(out: &mut String, metrics: &crate::core::metrics::FinalMetrics
 fn compat_adapter_content_short(
     out: &mut String,
     metrics: &crate::core::metrics::FinalMetrics,
+    opts: &HtmlOptions,
 ) -> Result<()> {
     compat_section_header(
         out,
@@ -974,16 +1892,140 @@ fn compat_adapter_content_short(
     )?;
     module_desc(
         out,
-        "Shows adapter match percentages by position. Increasing signal toward read ends suggests adapter read-through.",
+        "Shows the cumulative percentage of reads with an adapter match at or before each position. A curve rising toward read ends suggests adapter read-through.",
     )?;
+    if metrics.uncatalogued_adapter {
+        module_desc(
+            out,
+            "Note: possible uncatalogued adapter. An overrepresented sequence with no known source makes up more than 1% of reads; see Overrepresented sequences.",
+        )?;
+    }
     let (w, h) = (800.0, 260.0);
+    if opts.interactive {
+        let rows = &metrics.adapter_content;
+        let (y_min, y_max) = auto_range(
+            rows.iter().flat_map(|r| r.values.iter().copied()),
+            0.0,
+            100.0,
+        );
+        let series = crate::core::metrics::ADAPTERS
+            .iter()
+            .enumerate()
+            .map(|(i, name)| PlotSeries {
+                name,
+                color: ADAPTER_COLORS[i % ADAPTER_COLORS.len()],
+                values: rows.iter().map(|r| r.values[i]).collect(),
+            })
+            .collect();
+        interactive_plot(
+            out,
+            &PlotSpec {
+                x: rows.iter().map(|r| r.position as f64).collect(),
+                series,
+                y_min,
+                y_max,
+                x_label: "Position",
+                y_label: "%",
+            },
+        )?;
+        writeln!(out, "<noscript>")?;
+    }
     svg_adapter_lines(out, &metrics.adapter_content, w, h, "Position", "%")?;
+    if opts.interactive {
+        writeln!(out, "</noscript>")?;
+    }
     table_with_summary(out, "Data", |o| {
         table_adapter_content(o, &metrics.adapter_content)
     })?;
     compat_section_footer(out)
 }
 
+fn compat_trim_preview(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    let trim = &metrics.trim_preview;
+    compat_section_header(
+        out,
+        metrics.statuses.trim_preview,
+        "Adapter Trimming Preview",
+        module_id_trim_preview(),
+    )?;
+    module_desc(
+        out,
+        "Estimates what 3' adapter trimming would remove if each read were cut at its earliest adapter match. The plot shows the percentage of reads by number of bases removed.",
+    )?;
+    writeln!(out, "<table class=\"bs-table\">")?;
+    writeln!(out, "<tr><th>Measure</th><th>Value</th></tr>")?;
+    writeln!(
+        out,
+        "<tr><td>Reads trimmed</td><td>{}</td></tr>",
+        fmt_int(trim.reads_trimmed)
+    )?;
+    writeln!(
+        out,
+        "<tr><td>Bases removed</td><td>{}</td></tr>",
+        fmt_int(trim.bases_removed)
+    )?;
+    writeln!(
+        out,
+        "<tr><td>Bases retained</td><td>{}</td></tr>",
+        fmt_int(trim.bases_retained)
+    )?;
+    writeln!(
+        out,
+        "<tr><td>Mean length after trimming</td><td>{:.2}</td></tr>",
+        trim.mean_trimmed_length
+    )?;
+    writeln!(out, "</table>")?;
+    if !trim.rows.is_empty() {
+        let total = metrics.basic.total_sequences.max(1) as f64;
+        let data: Vec<(f64, f64)> = trim
+            .rows
+            .iter()
+            .map(|r| (r.removed as f64, r.reads as f64 * 100.0 / total))
+            .collect();
+        let max_y = data.iter().map(|d| d.1).fold(1.0f64, f64::max).ceil();
+        svg_single_line(
+            out,
+            data.as_slice(),
+            800.0,
+            260.0,
+            0.0,
+            max_y,
+            "#c00000",
+            "Bases removed",
+            "% of reads",
+        )?;
+        table_with_summary(out, "Data", |o| table_trim_preview(o, &trim.rows))?;
+    }
+    compat_section_footer(out)
+}
+
+fn table_trim_preview(out: &mut String, rows: &[crate::core::metrics::TrimRow]) -> Result<()> {
+    writeln!(
+        out,
+        "<details><summary>Table</summary><table class=\"table\">"
+    )?;
+    writeln!(out, "<tr><th>Bases removed</th><th>Reads</th></tr>")?;
+    for r in rows {
+        writeln!(out, "<tr><td>{}</td><td>{}</td></tr>", r.removed, r.reads)?;
+    }
+    writeln!(out, "</table></details>")?;
+    Ok(())
+}
+
+fn interactive_plot(out: &mut String, spec: &PlotSpec<'_>) -> Result<()> {
+    // "</" would end the inline <script> early.
+    let json = serde_json::to_string(spec)?.replace("</", "<\\/");
+    writeln!(
+        out,
+        "<div class=\"plot iplot\"><script type=\"application/json\">{}</script></div>",
+        json
+    )?;
+    Ok(())
+}
+
 fn compat_adapter_content_long(
     out: &mut String,
     metrics: &crate::core::metrics::FinalMetrics,
@@ -998,9 +2040,13 @@ fn compat_adapter_content_long(
         out,
         "Reports the fraction of reads containing common adapter motifs. Elevated percentages suggest residual adapters or chimeric reads.",
     )?;
-    table_with_summary(out, "Data", |o| {
-        table_adapter_summary(o, &metrics.adapter_content)
-    })?;
+    if metrics.internal_adapter {
+        module_desc(
+            out,
+            "Note: adapters found mid-read. More than 1% of reads carry an adapter away from both ends, which usually marks chimeric reads; see the Start / Middle / End rows.",
+        )?;
+    }
+    table_with_summary(out, "Data", |o| table_adapter_summary(o, metrics))?;
     compat_section_footer(out)
 }
 
@@ -1019,6 +2065,12 @@ fn compat_kmer_content(
         out,
         "Reports k-mers enriched at specific positions. Strong enrichment can indicate adapters or sequence bias.",
     )?;
+    if metrics.kmer_saturated {
+        module_desc(
+            out,
+            "Warning: some k-mer counts exceeded the sketch counter limit (4,294,967,295) and were capped, so enrichment figures are unreliable for this input.",
+        )?;
+    }
     table_with_summary(out, "Data", |o| table_kmer(o, &metrics.kmer_rows))?;
     compat_section_footer(out)
 }
@@ -1036,6 +2088,9 @@ fn module_basic_stats(
     writeln!(out, "<table class=\"table\">")?;
     writeln!(out, "<tr><th>Measure</th><th>Value</th></tr>")?;
     writeln!(out, "<tr><td>Filename</td><td>{}</td></tr>", file)?;
+    if let Some(hash) = &metrics.basic.file_hash {
+        writeln!(out, "<tr><td>File hash</td><td>{}</td></tr>", hash)?;
+    }
     writeln!(
         out,
         "<tr><td>File type</td><td>{}</td></tr>",
@@ -1091,29 +2146,55 @@ fn module_per_base_quality(
         out,
         "Shows quality score distributions at each base position. Systematic drops toward read ends often reflect sequencing degradation or adapter read-through.",
     )?;
+    binned_quality_note(out, metrics)?;
+    low_quality_positions_note(out, metrics)?;
     let (w, h) = (800.0, 260.0);
     let max_q = metrics
         .per_base_qual
         .iter()
         .map(|r| r.p90 as f64)
         .fold(40.0, f64::max);
-    svg_boxplot(
-        out,
-        &metrics.per_base_qual,
+    let chart = QualChart {
         w,
         h,
         max_q,
-        "Position",
-        "Quality",
-    )?;
+        x_label: "Position",
+        y_label: "Quality",
+        pal: &DEFAULT_PALETTE,
+        trend: &metrics.per_base_qual_trend,
+    };
+    if metrics.binned_quality_levels.is_empty() {
+        svg_boxplot(out, &metrics.per_base_qual, &chart)?;
+    } else {
+        svg_binned_quality(out, &metrics.per_base_qual, &chart)?;
+    }
     table_per_base_quality(out, &metrics.per_base_qual)?;
     module_footer(out)
 }
 
+// Mean/SD/median/skewness line shared by both per-sequence quality layouts.
+fn per_seq_quality_stats(
+    out: &mut String,
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<()> {
+    if metrics.per_seq_qual.is_empty() {
+        return Ok(());
+    }
+    let s = &metrics.per_seq_qual_stats;
+    module_desc(
+        out,
+        &format!(
+            "Mean {:.2}, SD {:.2}, median Q{}, skewness {:.2}.",
+            s.mean, s.sd, s.median, s.skewness
+        ),
+    )
+}
+
 fn module_per_seq_quality(
     out: &mut String,
     metrics: &crate::core::metrics::FinalMetrics,
 ) -> Result<()> {
+    let pal = &DEFAULT_PALETTE;
     module_header(
         out,
         metrics.statuses.per_seq_qual,
@@ -1123,6 +2204,7 @@ fn module_per_seq_quality(
         out,
         "Shows the distribution of mean quality per read. A left-shifted distribution indicates overall low-quality reads or mixed data.",
     )?;
+    per_seq_quality_stats(out, metrics)?;
     let (w, h) = (800.0, 260.0);
     let data = metrics
         .per_seq_qual
@@ -1137,9 +2219,9 @@ fn module_per_seq_quality(
         0.0,
         0.0,
         &[
-            (0.0, 20.0, "#f4c7c3"),
-            (20.0, 28.0, "#ffe5b4"),
-            (28.0, 60.0, "#cdeccf"),
+            (0.0, 20.0, pal.band_bad),
+            (20.0, 28.0, pal.band_warn),
+            (28.0, 60.0, pal.band_good),
         ],
         "Mean Q",
         "Count",
@@ -1151,6 +2233,7 @@ fn module_per_seq_quality(
 fn module_per_base_content(
     out: &mut String,
     metrics: &crate::core::metrics::FinalMetrics,
+    annotate: bool,
 ) -> Result<()> {
     module_header(
         out,
@@ -1161,24 +2244,35 @@ fn module_per_base_content(
         out,
         "Shows the percentage of each base at each position. Strong positional biases can indicate priming artifacts or residual adapters.",
     )?;
+    content_bias_note(out, metrics)?;
     let (w, h) = (800.0, 260.0);
     legend_base_content(out)?;
-    svg_multi_line(out, &metrics.per_base_content, w, h, "Position", "%")?;
+    svg_multi_line(
+        out,
+        &metrics.per_base_content,
+        w,
+        h,
+        "Position",
+        "%",
+        annotate,
+    )?;
     table_per_base_content(out, &metrics.per_base_content)?;
     module_footer(out)
 }
 
 fn module_per_seq_gc(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    let pal = &DEFAULT_PALETTE;
     module_header(out, metrics.statuses.per_seq_gc, "Per sequence GC content")?;
     module_desc(
         out,
         "Shows the distribution of GC% across reads. Broad or multi-modal shapes can indicate contamination or mixed libraries.",
     )?;
+    gc_deviation_note(out, metrics)?;
     let (w, h) = (800.0, 260.0);
     let data = metrics
         .per_seq_gc
         .iter()
-        .map(|r| (r.gc as f64, r.count as f64))
+        .map(|r| (r.gc, r.count as f64))
         .collect::<Vec<_>>();
     svg_histogram_xbands(
         out,
@@ -1187,7 +2281,7 @@ fn module_per_seq_gc(out: &mut String, metrics: &crate::core::metrics::FinalMetr
         h,
         0.0,
         100.0,
-        &[(40.0, 60.0, "#cdeccf")],
+        &[(40.0, 60.0, pal.band_good)],
         "GC%",
         "Count",
     )?;
@@ -1196,11 +2290,13 @@ fn module_per_seq_gc(out: &mut String, metrics: &crate::core::metrics::FinalMetr
 }
 
 fn module_per_base_n(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    let pal = &DEFAULT_PALETTE;
     module_header(out, metrics.statuses.per_base_n, "Per base N content")?;
     module_desc(
         out,
         "Shows the proportion of Ns at each position. Spikes or elevated Ns suggest base-calling issues or low-complexity regions.",
     )?;
+    n_run_note(out, metrics)?;
     let (w, h) = (800.0, 260.0);
     let data = metrics
         .per_base_n
@@ -1217,9 +2313,9 @@ fn module_per_base_n(out: &mut String, metrics: &crate::core::metrics::FinalMetr
         y_max,
         "#555",
         &[
-            (0.0, 5.0, "#cdeccf"),
-            (5.0, 20.0, "#ffe5b4"),
-            (20.0, 100.0, "#f4c7c3"),
+            (0.0, 5.0, pal.band_good),
+            (5.0, 20.0, pal.band_warn),
+            (20.0, 100.0, pal.band_bad),
         ],
         "Position",
         "% N",
@@ -1229,11 +2325,13 @@ fn module_per_base_n(out: &mut String, metrics: &crate::core::metrics::FinalMetr
 }
 
 fn module_per_seq_n(out: &mut String, metrics: &crate::core::metrics::FinalMetrics) -> Result<()> {
+    let pal = &DEFAULT_PALETTE;
     module_header(out, metrics.statuses.per_seq_n, "Per sequence N content")?;
     module_desc(
         out,
         "Shows the distribution of N% per read. Excess high-N reads indicate poor base-calling or low-quality segments.",
     )?;
+    n_run_note(out, metrics)?;
     let data = metrics
         .per_seq_n
         .iter()
@@ -1247,9 +2345,9 @@ fn module_per_seq_n(out: &mut String, metrics: &crate::core::metrics::FinalMetri
         0.0,
         100.0,
         &[
-            (0.0, 10.0, "#cdeccf"),
-            (10.0, 20.0, "#ffe5b4"),
-            (20.0, 100.0, "#f4c7c3"),
+            (0.0, 10.0, pal.band_good),
+            (10.0, 20.0, pal.band_warn),
+            (20.0, 100.0, pal.band_bad),
         ],
         "N%",
         "Count",
@@ -1296,22 +2394,26 @@ fn module_length_dist_long(
         "Shows read length frequencies using log-scaled bins. Very long tails or multiple modes may indicate mixed input or variable trimming.",
     )?;
     if let Some(ref ll) = metrics.long_length {
-        let data = ll
-            .bins
-            .iter()
-            .enumerate()
-            .map(|(i, &c)| (i as f64 + 1.0, c as f64))
-            .collect::<Vec<_>>();
-        svg_histogram(
-            out,
-            data.as_slice(),
-            800.0,
-            260.0,
-            0.0,
-            0.0,
-            "Length bin",
-            "Count",
-        )?;
+        if let Some(trimmed) = &ll.trimmed_bins {
+            svg_long_length_trimmed(out, ll, trimmed)?;
+        } else {
+            let data = ll
+                .bins
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| (i as f64 + 1.0, c as f64))
+                .collect::<Vec<_>>();
+            svg_histogram(
+                out,
+                data.as_slice(),
+                800.0,
+                260.0,
+                0.0,
+                0.0,
+                "Length bin",
+                "Count",
+            )?;
+        }
         table_long_length(out, ll)?;
     }
     module_footer(out)
@@ -1330,6 +2432,9 @@ fn module_duplication(
         out,
         "Estimates duplication using a streaming heavy-hitter model. High duplication often indicates PCR over-amplification or low library complexity.",
     )?;
+    top_sequence_note(out, metrics)?;
+    dup_saturation_note(out, metrics)?;
+    optical_dup_note(out, metrics)?;
     let data = metrics
         .duplication
         .iter()
@@ -1388,7 +2493,13 @@ fn module_adapter_content_long(
         out,
         "Reports the fraction of reads containing common adapter motifs. Elevated percentages suggest residual adapters or chimeric reads.",
     )?;
-    table_adapter_summary(out, &metrics.adapter_content)?;
+    if metrics.internal_adapter {
+        module_desc(
+            out,
+            "Note: adapters found mid-read. More than 1% of reads carry an adapter away from both ends, which usually marks chimeric reads; see the Start / Middle / End rows.",
+        )?;
+    }
+    table_adapter_summary(out, metrics)?;
     module_footer(out)
 }
 
@@ -1406,15 +2517,25 @@ fn module_kmer_content(
     module_footer(out)
 }
 
+// Line color of the smoothed per base quality trend.
+const QUAL_TREND_COLOR: &str = "#ff7f0e";
+
+// `trend`, when not empty, holds one smoothed mean per row and is drawn as a
+// line over the boxes.
 fn svg_boxplot(
     out: &mut String,
     rows: &[crate::core::metrics::PerBaseQualRow],
-    w: f64,
-    h: f64,
-    max_q: f64,
-    x_label: &str,
-    y_label: &str,
+    chart: &QualChart<'_>,
 ) -> Result<()> {
+    let QualChart {
+        w,
+        h,
+        max_q,
+        x_label,
+        y_label,
+        pal,
+        trend,
+    } = *chart;
     writeln!(out, "<div class=\"plot\">")?;
     writeln!(
         out,
@@ -1422,7 +2543,8 @@ fn svg_boxplot(
         w, h, w, h
     )?;
     let left = 50.0;
-    let right = 20.0;
+    // Room for the error-probability axis.
+    let right = 56.0;
     let top = 12.0;
     let bottom = 34.0;
     let plot_w = w - left - right;
@@ -1442,9 +2564,9 @@ fn svg_boxplot(
         0.0,
         max_q,
         &[
-            (0.0, 20.0, "#f4c7c3"),
-            (20.0, 28.0, "#ffe5b4"),
-            (28.0, max_q.max(28.0), "#cdeccf"),
+            (0.0, 20.0, pal.band_bad),
+            (20.0, 28.0, pal.band_warn),
+            (28.0, max_q.max(28.0), pal.band_good),
         ],
     )?;
 
@@ -1452,34 +2574,45 @@ fn svg_boxplot(
     let x_step = plot_w / n;
     let y_scale = if max_q <= 0.0 { 1.0 } else { plot_h / max_q };
     draw_y_axis_ticks(out, left, top, plot_w, plot_h, 0.0, max_q, 5)?;
-    draw_y_axis_ticks_right(out, left, top, plot_w, plot_h, 0.0, max_q, 5)?;
+    let area = PlotArea {
+        left,
+        top,
+        w: plot_w,
+        h: plot_h,
+        min_y: 0.0,
+        max_y: max_q,
+    };
+    draw_y_axis_ticks_right_with(out, &area, 5, fmt_error_prob)?;
     draw_x_axis_ticks(out, left, top, plot_w, plot_h, 1.0, n, 5)?;
     draw_axis_labels(out, left, top, plot_w, plot_h, x_label, y_label)?;
+    draw_right_axis_label(out, left, top, plot_w, plot_h, "Error probability")?;
 
     for (i, r) in rows.iter().enumerate() {
         let x = left + (i as f64 + 0.5) * x_step;
-        let y_m = top + plot_h - (r.median as f64 * y_scale);
-        let y_lq = top + plot_h - (r.lower_quartile as f64 * y_scale);
-        let y_uq = top + plot_h - (r.upper_quartile as f64 * y_scale);
-        let y_p10 = top + plot_h - (r.p10 as f64 * y_scale);
-        let y_p90 = top + plot_h - (r.p90 as f64 * y_scale);
+        // Quantiles from degenerate histograms can come back equal or
+        // crossed; order them so the box and whiskers never invert.
+        let mut q = [r.p10, r.lower_quartile, r.median, r.upper_quartile, r.p90];
+        q.sort_unstable();
+        let [y_p10, y_lq, y_m, y_uq, y_p90] = q.map(|v| top + plot_h - (v as f64 * y_scale));
+        // Keep a collapsed box visible as a 1px band around the median.
+        let (box_y, box_h) = if y_lq - y_uq < 1.0 {
+            (y_m - 0.5, 1.0)
+        } else {
+            (y_uq, y_lq - y_uq)
+        };
         let box_w = (x_step * 0.8).max(1.0);
         let box_x = x - box_w / 2.0;
         let color = if r.median >= 28 {
-            "#cdeccf"
+            pal.band_good
         } else if r.median >= 20 {
-            "#ffe5b4"
+            pal.band_warn
         } else {
-            "#f4c7c3"
+            pal.band_bad
         };
         writeln!(
             out,
             "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"#666\"/>",
-            box_x,
-            y_uq,
-            box_w,
-            (y_lq - y_uq).max(0.0),
-            color
+            box_x, box_y, box_w, box_h, color
         )?;
         // Whiskers (10th-90th) and median line.
         writeln!(
@@ -1513,6 +2646,202 @@ fn svg_boxplot(
             y_m
         )?;
     }
+    if !trend.is_empty() {
+        let points = trend
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                format!(
+                    "{:.2},{:.2}",
+                    left + (i as f64 + 0.5) * x_step,
+                    top + plot_h - v * y_scale
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            out,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>",
+            points, QUAL_TREND_COLOR
+        )?;
+    }
+    writeln!(out, "</svg></div>")?;
+    Ok(())
+}
+
+// Size, quality range, axis labels and band colours of a per-base quality
+// plot.
+struct QualChart<'a> {
+    w: f64,
+    h: f64,
+    max_q: f64,
+    x_label: &'a str,
+    y_label: &'a str,
+    pal: &'a Palette,
+    // --qual-trend-window means, drawn over the box plot only; empty for
+    // none.
+    trend: &'a [f64],
+}
+
+// Per-base quality for binned data: a bar to the mean at each position and a
+// step line through the medians.
+fn svg_binned_quality(
+    out: &mut String,
+    rows: &[crate::core::metrics::PerBaseQualRow],
+    chart: &QualChart<'_>,
+) -> Result<()> {
+    let QualChart {
+        w,
+        h,
+        max_q,
+        x_label,
+        y_label,
+        pal,
+        ..
+    } = *chart;
+    writeln!(out, "<div class=\"plot\">")?;
+    writeln!(
+        out,
+        "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        w, h, w, h
+    )?;
+    let left = 50.0;
+    let right = 20.0;
+    let top = 12.0;
+    let bottom = 34.0;
+    let plot_w = w - left - right;
+    let plot_h = h - top - bottom;
+    writeln!(
+        out,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#fff\" stroke=\"#ddd\"/>",
+        left, top, plot_w, plot_h
+    )?;
+    draw_y_bands(
+        out,
+        left,
+        top,
+        plot_w,
+        plot_h,
+        0.0,
+        max_q,
+        &[
+            (0.0, 20.0, pal.band_bad),
+            (20.0, 28.0, pal.band_warn),
+            (28.0, max_q.max(28.0), pal.band_good),
+        ],
+    )?;
+
+    let n = rows.len().max(1) as f64;
+    let x_step = plot_w / n;
+    let y_scale = if max_q <= 0.0 { 1.0 } else { plot_h / max_q };
+    draw_y_axis_ticks(out, left, top, plot_w, plot_h, 0.0, max_q, 5)?;
+    draw_y_axis_ticks_right(out, left, top, plot_w, plot_h, 0.0, max_q, 5)?;
+    draw_x_axis_ticks(out, left, top, plot_w, plot_h, 1.0, n, 5)?;
+    draw_axis_labels(out, left, top, plot_w, plot_h, x_label, y_label)?;
+
+    let base_y = top + plot_h;
+    let bar_w = (x_step * 0.8).max(1.0);
+    let mut step = String::new();
+    for (i, r) in rows.iter().enumerate() {
+        let x0 = left + i as f64 * x_step;
+        let y_mean = base_y - r.mean * y_scale;
+        writeln!(
+            out,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#1f77b4\" fill-opacity=\"0.6\"><title>Position {}: mean {:.2}, median {}</title></rect>",
+            x0 + (x_step - bar_w) / 2.0,
+            y_mean,
+            bar_w,
+            base_y - y_mean,
+            r.base,
+            r.mean,
+            r.median
+        )?;
+        let y_median = base_y - r.median as f64 * y_scale;
+        write!(
+            step,
+            "{:.2},{:.2} {:.2},{:.2} ",
+            x0,
+            y_median,
+            x0 + x_step,
+            y_median
+        )?;
+    }
+    writeln!(
+        out,
+        "<polyline fill=\"none\" stroke=\"#333\" stroke-width=\"1.2\" points=\"{}\"/>",
+        step.trim_end()
+    )?;
+    writeln!(out, "</svg></div>")?;
+    Ok(())
+}
+
+// Columns are positions, rows are single quality values; each cell is shaded
+// by its share of the busiest quality at that position, so columns stay
+// comparable even where fewer reads reach the position.
+fn svg_quality_heatmap(
+    out: &mut String,
+    hists: &[QualHist],
+    w: f64,
+    h: f64,
+    x_label: &str,
+    y_label: &str,
+) -> Result<()> {
+    writeln!(out, "<div class=\"plot\">")?;
+    writeln!(
+        out,
+        "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        w, h, w, h
+    )?;
+    let left = 50.0;
+    let right = 20.0;
+    let top = 12.0;
+    let bottom = 34.0;
+    let plot_w = w - left - right;
+    let plot_h = h - top - bottom;
+    writeln!(
+        out,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#fff\" stroke=\"#ddd\"/>",
+        left, top, plot_w, plot_h
+    )?;
+    let top_q = hists
+        .iter()
+        .filter_map(|hist| hist.iter().rposition(|&c| c > 0))
+        .fold(40, usize::max)
+        .min(MAX_Q);
+    let max_q = (top_q + 1) as f64;
+    let n = hists.len().max(1) as f64;
+    let cell_w = plot_w / n;
+    let cell_h = plot_h / max_q;
+    draw_y_axis_ticks(out, left, top, plot_w, plot_h, 0.0, max_q, 5)?;
+    draw_y_axis_ticks_right(out, left, top, plot_w, plot_h, 0.0, max_q, 5)?;
+    draw_x_axis_ticks(out, left, top, plot_w, plot_h, 1.0, n, 5)?;
+    draw_axis_labels(out, left, top, plot_w, plot_h, x_label, y_label)?;
+
+    for (i, hist) in hists.iter().enumerate() {
+        let col_max = hist.iter().copied().max().unwrap_or(0);
+        if col_max == 0 {
+            continue;
+        }
+        let total: u64 = hist.iter().sum();
+        for (q, &c) in hist.iter().enumerate().take(top_q + 1) {
+            if c == 0 {
+                continue;
+            }
+            let t = c as f64 / col_max as f64;
+            writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#1f4e79\" fill-opacity=\"{:.3}\"><title>Position {}, Q{}: {:.2}%</title></rect>",
+                left + i as f64 * cell_w,
+                top + plot_h - (q as f64 + 1.0) * cell_h,
+                cell_w,
+                cell_h,
+                0.05 + 0.95 * t,
+                i + 1,
+                q,
+                c as f64 * 100.0 / total as f64
+            )?;
+        }
+    }
     writeln!(out, "</svg></div>")?;
     Ok(())
 }
@@ -1643,6 +2972,82 @@ fn svg_histogram_compat_bars(
     Ok(())
 }
 
+const RAW_LENGTH_COLOR: &str = "#8ecae6";
+const TRIMMED_LENGTH_COLOR: &str = "#fb8500";
+
+// Long-mode length bins as grouped bars: raw reads next to the same reads
+// cut at their first adapter match.
+fn svg_long_length_trimmed(
+    out: &mut String,
+    ll: &crate::core::metrics::LongLengthSummary,
+    trimmed: &[u64; 8],
+) -> Result<()> {
+    writeln!(
+        out,
+        "<div class=\"desc\"><b>Legend:</b> <span style=\"display:inline-block;width:12px;height:12px;background:{};margin:0 6px 0 6px;vertical-align:middle;\"></span>Raw <span style=\"display:inline-block;width:12px;height:12px;background:{};margin:0 6px 0 10px;vertical-align:middle;\"></span>Adapter-trimmed</div>",
+        RAW_LENGTH_COLOR, TRIMMED_LENGTH_COLOR
+    )?;
+    let (w, h) = (800.0, 260.0);
+    writeln!(out, "<div class=\"plot\">")?;
+    writeln!(
+        out,
+        "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        w, h, w, h
+    )?;
+    let left = 50.0;
+    let right = 20.0;
+    let top = 12.0;
+    let bottom = 34.0;
+    let plot_w = w - left - right;
+    let plot_h = h - top - bottom;
+    writeln!(
+        out,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#fff\" stroke=\"#ddd\"/>",
+        left, top, plot_w, plot_h
+    )?;
+    let max_y = ll.bins.iter().chain(trimmed).copied().max().unwrap_or(0) as f64;
+    draw_y_axis_labels_only(out, left, top, plot_w, plot_h, 0.0, max_y, 4)?;
+    draw_axis_labels(out, left, top, plot_w, plot_h, "Length bin", "Count")?;
+    let group_w = plot_w / ll.bins.len() as f64;
+    let bar_w = group_w * 0.4;
+    for (i, label) in ll.labels.iter().enumerate() {
+        let x0 = left + i as f64 * group_w;
+        for (j, (count, color)) in [
+            (ll.bins[i], RAW_LENGTH_COLOR),
+            (trimmed[i], TRIMMED_LENGTH_COLOR),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let bar_h = if max_y == 0.0 {
+                0.0
+            } else {
+                count as f64 / max_y * plot_h
+            };
+            writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{}: {}</title></rect>",
+                x0 + group_w * 0.1 + j as f64 * bar_w,
+                top + plot_h - bar_h,
+                bar_w,
+                bar_h,
+                color,
+                label,
+                count
+            )?;
+        }
+        writeln!(
+            out,
+            "<text x=\"{}\" y=\"{}\" font-size=\"10\" fill=\"#666\" text-anchor=\"middle\" dominant-baseline=\"hanging\">{}</text>",
+            x0 + group_w / 2.0,
+            top + plot_h + 4.0,
+            label
+        )?;
+    }
+    writeln!(out, "</svg></div>")?;
+    Ok(())
+}
+
 fn svg_histogram_xbands(
     out: &mut String,
     data: &[(f64, f64)],
@@ -1687,38 +3092,118 @@ fn svg_histogram_xbands(
         if w_band > 0.0 {
             writeln!(
                 out,
-                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" opacity=\"0.18\"/>",
-                x, top, w_band, plot_h, color
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" opacity=\"0.18\"/>",
+                x, top, w_band, plot_h, color
+            )?;
+        }
+    }
+    let max_y = data.iter().map(|(_, y)| *y).fold(0.0, f64::max);
+    let bar_w = if data.is_empty() {
+        1.0
+    } else {
+        plot_w / data.len() as f64
+    };
+    draw_y_axis_ticks(out, left, top, plot_w, plot_h, 0.0, max_y, 4)?;
+    draw_y_axis_ticks_right(out, left, top, plot_w, plot_h, 0.0, max_y, 4)?;
+    draw_x_axis_ticks(out, left, top, plot_w, plot_h, x_min, x_max, 5)?;
+    draw_axis_labels(out, left, top, plot_w, plot_h, x_label, y_label)?;
+    for (i, (_xv, yv)) in data.iter().enumerate() {
+        let x = left + (i as f64) * bar_w;
+        let y = if max_y == 0.0 {
+            0.0
+        } else {
+            yv / max_y * plot_h
+        };
+        let y0 = top + plot_h - y;
+        writeln!(
+            out,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#7db8da\"/>",
+            x,
+            y0,
+            bar_w.max(1.0),
+            y
+        )?;
+    }
+    writeln!(out, "</svg></div>")?;
+    Ok(())
+}
+
+// Rows are length bins (shortest at the bottom), columns are mean quality
+// bins; cell shade is log-scaled so sparse bins stay visible.
+fn svg_length_quality_heatmap(
+    out: &mut String,
+    rows: &[crate::core::metrics::LengthQualityRow],
+    w: f64,
+    h: f64,
+) -> Result<()> {
+    writeln!(out, "<div class=\"plot\">")?;
+    writeln!(
+        out,
+        "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        w, h, w, h
+    )?;
+    let left = 70.0;
+    let right = 20.0;
+    let top = 12.0;
+    let bottom = 34.0;
+    let plot_w = w - left - right;
+    let plot_h = h - top - bottom;
+    writeln!(
+        out,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#fff\" stroke=\"#ddd\"/>",
+        left, top, plot_w, plot_h
+    )?;
+    let cols = crate::core::metrics::LengthQualityRow::COLUMNS;
+    let cell_w = plot_w / cols as f64;
+    let cell_h = plot_h / rows.len().max(1) as f64;
+    let max_log = rows
+        .iter()
+        .flat_map(|r| r.counts.iter())
+        .map(|&c| (c as f64 + 1.0).ln())
+        .fold(0.0, f64::max);
+    for (ri, row) in rows.iter().enumerate() {
+        let y = top + plot_h - (ri as f64 + 1.0) * cell_h;
+        for (ci, &c) in row.counts.iter().enumerate() {
+            if c == 0 {
+                continue;
+            }
+            let t = if max_log > 0.0 {
+                (c as f64 + 1.0).ln() / max_log
+            } else {
+                0.0
+            };
+            writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#1f4e79\" fill-opacity=\"{:.3}\"><title>{} bp, Q{}-{}: {}</title></rect>",
+                left + ci as f64 * cell_w,
+                y,
+                cell_w,
+                cell_h,
+                0.1 + 0.9 * t,
+                row.length_bin,
+                ci * crate::core::metrics::QUAL_BIN_WIDTH,
+                ci * crate::core::metrics::QUAL_BIN_WIDTH + crate::core::metrics::QUAL_BIN_WIDTH
+                    - 1,
+                c
             )?;
         }
-    }
-    let max_y = data.iter().map(|(_, y)| *y).fold(0.0, f64::max);
-    let bar_w = if data.is_empty() {
-        1.0
-    } else {
-        plot_w / data.len() as f64
-    };
-    draw_y_axis_ticks(out, left, top, plot_w, plot_h, 0.0, max_y, 4)?;
-    draw_y_axis_ticks_right(out, left, top, plot_w, plot_h, 0.0, max_y, 4)?;
-    draw_x_axis_ticks(out, left, top, plot_w, plot_h, x_min, x_max, 5)?;
-    draw_axis_labels(out, left, top, plot_w, plot_h, x_label, y_label)?;
-    for (i, (_xv, yv)) in data.iter().enumerate() {
-        let x = left + (i as f64) * bar_w;
-        let y = if max_y == 0.0 {
-            0.0
-        } else {
-            yv / max_y * plot_h
-        };
-        let y0 = top + plot_h - y;
         writeln!(
             out,
-            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#7db8da\"/>",
-            x,
-            y0,
-            bar_w.max(1.0),
-            y
+            "<text x=\"{}\" y=\"{}\" font-size=\"10\" fill=\"#666\" text-anchor=\"end\" dominant-baseline=\"middle\">{}</text>",
+            left - 4.0,
+            y + cell_h / 2.0,
+            row.length_bin
         )?;
     }
+    let q_max = (cols * crate::core::metrics::QUAL_BIN_WIDTH) as f64;
+    draw_x_axis_labels_only(out, left, top, plot_w, plot_h, 0.0, q_max, 5)?;
+    let x = left + plot_w / 2.0;
+    writeln!(
+        out,
+        "<text x=\"{}\" y=\"{}\" font-size=\"11\" fill=\"#444\" text-anchor=\"middle\">Mean quality</text>",
+        x,
+        top + plot_h + 22.0
+    )?;
     writeln!(out, "</svg></div>")?;
     Ok(())
 }
@@ -1730,22 +3215,23 @@ fn svg_multi_line(
     h: f64,
     x_label: &str,
     y_label: &str,
+    annotate: bool,
 ) -> Result<()> {
     let data_g = rows
         .iter()
-        .map(|r| (r.base as f64, r.g))
+        .map(|r| (r.start as f64, r.g))
         .collect::<Vec<_>>();
     let data_a = rows
         .iter()
-        .map(|r| (r.base as f64, r.a))
+        .map(|r| (r.start as f64, r.a))
         .collect::<Vec<_>>();
     let data_t = rows
         .iter()
-        .map(|r| (r.base as f64, r.t))
+        .map(|r| (r.start as f64, r.t))
         .collect::<Vec<_>>();
     let data_c = rows
         .iter()
-        .map(|r| (r.base as f64, r.c))
+        .map(|r| (r.start as f64, r.c))
         .collect::<Vec<_>>();
     writeln!(out, "<div class=\"plot\">")?;
     writeln!(
@@ -1775,7 +3261,8 @@ fn svg_multi_line(
     let y_max = (max_y + pad).min(100.0);
     draw_y_axis_ticks(out, left, top, plot_w, plot_h, y_min, y_max, 5)?;
     draw_y_axis_ticks_right(out, left, top, plot_w, plot_h, y_min, y_max, 5)?;
-    draw_x_axis_ticks(out, left, top, plot_w, plot_h, 1.0, rows.len() as f64, 5)?;
+    let max_x = rows.last().map(|r| r.start).unwrap_or(1) as f64;
+    draw_x_axis_ticks(out, left, top, plot_w, plot_h, 1.0, max_x, 5)?;
     draw_axis_labels(out, left, top, plot_w, plot_h, x_label, y_label)?;
     // FastQC line colours (Tol scheme): #882255, #332288, #117733, #DDCC77
     svg_line(
@@ -1790,10 +3277,101 @@ fn svg_multi_line(
     svg_line(
         out, &data_c, left, top, plot_w, plot_h, y_min, y_max, "#DDCC77",
     )?;
+    if annotate {
+        let area = PlotArea {
+            left,
+            top,
+            w: plot_w,
+            h: plot_h,
+            min_y: y_min,
+            max_y: y_max,
+        };
+        annotate_base_content(out, rows, &area)?;
+    }
     writeln!(out, "</svg></div>")?;
     Ok(())
 }
 
+// Positions labelled by --content-annotate.
+const CONTENT_ANNOTATE_POINTS: usize = 8;
+
+// Inner plot rectangle in SVG coordinates and the y range it spans.
+struct PlotArea {
+    left: f64,
+    top: f64,
+    w: f64,
+    h: f64,
+    min_y: f64,
+    max_y: f64,
+}
+
+// Dots with value labels on each base's line at evenly spaced rows, using
+// svg_line's coordinate mapping; hovering a dot shows all four values.
+fn annotate_base_content(
+    out: &mut String,
+    rows: &[crate::core::metrics::PerBaseContentRow],
+    area: &PlotArea,
+) -> Result<()> {
+    let (Some(first), Some(last)) = (rows.first(), rows.last()) else {
+        return Ok(());
+    };
+    let PlotArea {
+        left,
+        top,
+        w: plot_w,
+        h: plot_h,
+        min_y: y_min,
+        max_y: y_max,
+    } = *area;
+    let x_min = first.start as f64;
+    let x_range = (last.start as f64 - x_min).max(1.0);
+    let y_range = (y_max - y_min).max(1.0);
+    let points = CONTENT_ANNOTATE_POINTS.min(rows.len());
+    for k in 0..points {
+        let idx = if points == 1 {
+            0
+        } else {
+            k * (rows.len() - 1) / (points - 1)
+        };
+        let r = &rows[idx];
+        let x = left + (r.start as f64 - x_min) / x_range * plot_w;
+        let tooltip = format!(
+            "Position {}: G {:.1}%, A {:.1}%, T {:.1}%, C {:.1}%",
+            r.start, r.g, r.a, r.t, r.c
+        );
+        // Labels sit left of the dot on the last point so they stay inside
+        // the plot.
+        let (dx, anchor) = if k + 1 == points && points > 1 {
+            (-5.0, "end")
+        } else {
+            (5.0, "start")
+        };
+        for (value, color) in [
+            (r.g, "#882255"),
+            (r.a, "#332288"),
+            (r.t, "#117733"),
+            (r.c, "#DDCC77"),
+        ] {
+            let y = top + plot_h - ((value - y_min) / y_range * plot_h);
+            writeln!(
+                out,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\"><title>{}</title></circle>",
+                x, y, color, tooltip
+            )?;
+            writeln!(
+                out,
+                "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"9\" fill=\"{}\" text-anchor=\"{}\">{:.1}</text>",
+                x + dx,
+                y - 3.0,
+                color,
+                anchor,
+                value
+            )?;
+        }
+    }
+    Ok(())
+}
+
 fn svg_single_line(
     out: &mut String,
     data: &[(f64, f64)],
@@ -1948,6 +3526,33 @@ fn draw_y_axis_ticks_right(
     max_y: f64,
     ticks: usize,
 ) -> Result<()> {
+    let area = PlotArea {
+        left,
+        top,
+        w: plot_w,
+        h: plot_h,
+        min_y,
+        max_y,
+    };
+    draw_y_axis_ticks_right_with(out, &area, ticks, fmt_tick)
+}
+
+// Right-hand tick labels at the same positions as the left axis, with each
+// value passed through `fmt` (e.g. to relabel phred as error probability).
+fn draw_y_axis_ticks_right_with(
+    out: &mut String,
+    area: &PlotArea,
+    ticks: usize,
+    fmt: fn(f64) -> String,
+) -> Result<()> {
+    let PlotArea {
+        left,
+        top,
+        w: plot_w,
+        h: plot_h,
+        min_y,
+        max_y,
+    } = *area;
     if ticks < 2 || (max_y - min_y).abs() < 1e-9 {
         return Ok(());
     }
@@ -1960,12 +3565,30 @@ fn draw_y_axis_ticks_right(
             "<text x=\"{}\" y=\"{}\" font-size=\"10\" fill=\"#666\" text-anchor=\"start\" dominant-baseline=\"middle\">{}</text>",
             left + plot_w + 4.0,
             y,
-            fmt_tick(v)
+            fmt(v)
         )?;
     }
     Ok(())
 }
 
+fn draw_right_axis_label(
+    out: &mut String,
+    left: f64,
+    top: f64,
+    plot_w: f64,
+    plot_h: f64,
+    label: &str,
+) -> Result<()> {
+    let x = left + plot_w + 46.0;
+    let y = top + plot_h / 2.0;
+    writeln!(
+        out,
+        "<text x=\"{}\" y=\"{}\" font-size=\"11\" fill=\"#444\" text-anchor=\"middle\" transform=\"rotate(90 {} {})\">{}</text>",
+        x, y, x, y, label
+    )?;
+    Ok(())
+}
+
 fn draw_x_axis_ticks(
     out: &mut String,
     left: f64,
@@ -2066,6 +3689,20 @@ fn fmt_tick(v: f64) -> String {
     }
 }
 
+// Phred score as error probability, P = 10^(-Q/10): "1", "1e-2", or two
+// significant digits between decades.
+fn fmt_error_prob(q: f64) -> String {
+    let decade = q / 10.0;
+    if (decade - decade.round()).abs() < 1e-6 {
+        match decade.round() as i64 {
+            0 => "1".to_string(),
+            d => format!("1e-{}", d),
+        }
+    } else {
+        format!("{:.1e}", 10f64.powf(-decade))
+    }
+}
+
 fn fmt_int(v: u64) -> String {
     let s = v.to_string();
     let mut out = String::with_capacity(s.len() + s.len() / 3);
@@ -2079,6 +3716,63 @@ fn fmt_int(v: u64) -> String {
     out
 }
 
+fn report_heading(out: &mut String, ctx: &FinalizeContext, default_title: &str) -> Result<()> {
+    let title = ctx.title.as_deref().unwrap_or(default_title);
+    writeln!(out, "<h1>{}</h1>", escape_html(title))?;
+    if let Some(description) = &ctx.description {
+        writeln!(
+            out,
+            "<p class=\"description\">{}</p>",
+            escape_html(description)
+        )?;
+    }
+    Ok(())
+}
+
+fn meta_notes(ctx: &FinalizeContext, metrics: &crate::core::metrics::FinalMetrics) -> String {
+    let mut notes = String::new();
+    if let Some(mode) = metrics.suggested_mode {
+        let (flag, kind) = match mode {
+            Mode::Short => ("--mode short", "short"),
+            Mode::Long => ("--mode long", "long"),
+        };
+        let _ = write!(
+            notes,
+            "<br/><span class=\"warn\">Warning: the median read length suggests {} reads; rerun with {}.</span>",
+            kind, flag
+        );
+    }
+    if let Some(n) = ctx.subsampled {
+        let _ = write!(notes, "<br/>Note: subsampled to {} reads", fmt_int(n));
+    }
+    if let Some(n) = ctx.truncated {
+        let _ = write!(
+            notes,
+            "<br/>Note: input truncated after {} reads (--max-reads/--max-bases limit reached)",
+            fmt_int(n)
+        );
+    }
+    notes
+}
+
+// None when the system clock reads before the Unix epoch; the report then says
+// so instead of claiming 1970-01-01.
+fn report_time() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+fn fmt_report_time(ts: Option<u64>) -> String {
+    match ts {
+        Some(ts) => format!("{} (unix: {})", fmt_timestamp(ts), ts),
+        None => "unknown (system clock before 1970-01-01)".to_string(),
+    }
+}
+
+// Civil date from days since 1970-01-01 (proleptic Gregorian, UTC), e.g.
+// 1600000000 -> 2020-09-13 12:26:40 UTC.
 fn fmt_timestamp(ts: u64) -> String {
     let days = (ts / 86_400) as i64;
     let secs = (ts % 86_400) as u32;
@@ -2273,6 +3967,26 @@ fn table_per_seq_n(out: &mut String, rows: &[crate::core::metrics::PerSeqNRow])
     Ok(())
 }
 
+fn table_sequence_complexity(
+    out: &mut String,
+    rows: &[crate::core::metrics::ComplexityRow],
+) -> Result<()> {
+    writeln!(
+        out,
+        "<details><summary>Table</summary><table class=\"table\">"
+    )?;
+    writeln!(out, "<tr><th>Entropy</th><th>Count</th></tr>")?;
+    for r in rows {
+        writeln!(
+            out,
+            "<tr><td>{:.1}</td><td>{}</td></tr>",
+            r.entropy, r.count
+        )?;
+    }
+    writeln!(out, "</table></details>")?;
+    Ok(())
+}
+
 fn table_per_base_content(
     out: &mut String,
     rows: &[crate::core::metrics::PerBaseContentRow],
@@ -2283,13 +3997,13 @@ fn table_per_base_content(
     )?;
     writeln!(
         out,
-        "<tr><th>Base</th><th>G</th><th>A</th><th>T</th><th>C</th></tr>"
+        "<tr><th>Base</th><th>G</th><th>A</th><th>T</th><th>C</th><th>Other</th></tr>"
     )?;
     for r in rows {
         writeln!(
             out,
-            "<tr><td>{}</td><td>{:.1}</td><td>{:.1}</td><td>{:.1}</td><td>{:.1}</td></tr>",
-            r.base, r.g, r.a, r.t, r.c
+            "<tr><td>{}</td><td>{:.1}</td><td>{:.1}</td><td>{:.1}</td><td>{:.1}</td><td>{:.1}</td></tr>",
+            r.base, r.g, r.a, r.t, r.c, r.other
         )?;
     }
     writeln!(out, "</table></details>")?;
@@ -2326,6 +4040,36 @@ fn table_per_base_n(out: &mut String, rows: &[crate::core::metrics::PerBaseNRow]
     Ok(())
 }
 
+fn table_error_rate(out: &mut String, rows: &[crate::core::metrics::ErrorRateRow]) -> Result<()> {
+    writeln!(
+        out,
+        "<details><summary>Table</summary><table class=\"table\">"
+    )?;
+    writeln!(out, "<tr><th>Base</th><th>Error rate</th></tr>")?;
+    for r in rows {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{:.4e}</td></tr>",
+            r.base, r.error_rate
+        )?;
+    }
+    writeln!(out, "</table></details>")?;
+    Ok(())
+}
+
+fn table_gc_skew(out: &mut String, rows: &[crate::core::metrics::GcSkewRow]) -> Result<()> {
+    writeln!(
+        out,
+        "<details><summary>Table</summary><table class=\"table\">"
+    )?;
+    writeln!(out, "<tr><th>Base</th><th>GC skew</th></tr>")?;
+    for r in rows {
+        writeln!(out, "<tr><td>{}</td><td>{:.4}</td></tr>", r.base, r.skew)?;
+    }
+    writeln!(out, "</table></details>")?;
+    Ok(())
+}
+
 fn table_length_dist(out: &mut String, rows: &[crate::core::metrics::LengthDistRow]) -> Result<()> {
     writeln!(
         out,
@@ -2352,14 +4096,58 @@ fn table_long_length(out: &mut String, ll: &crate::core::metrics::LongLengthSumm
     writeln!(out, "<tr><td>N90</td><td>{}</td></tr>", ll.n90)?;
     writeln!(out, "</table>")?;
     writeln!(out, "<table class=\"table\">")?;
-    writeln!(out, "<tr><th>Length Bin</th><th>Count</th></tr>")?;
-    for i in 0..ll.bins.len() {
-        writeln!(
+    match &ll.trimmed_bins {
+        Some(trimmed) => {
+            writeln!(
+                out,
+                "<tr><th>Length Bin</th><th>Count</th><th>Trimmed Count</th></tr>"
+            )?;
+            for ((label, count), trimmed) in ll.labels.iter().zip(&ll.bins).zip(trimmed) {
+                writeln!(
+                    out,
+                    "<tr><td>{label}</td><td>{count}</td><td>{trimmed}</td></tr>"
+                )?;
+            }
+        }
+        None => {
+            writeln!(out, "<tr><th>Length Bin</th><th>Count</th></tr>")?;
+            for i in 0..ll.bins.len() {
+                writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td></tr>",
+                    ll.labels[i], ll.bins[i]
+                )?;
+            }
+        }
+    }
+    writeln!(out, "</table></details>")?;
+    Ok(())
+}
+
+fn table_length_quality(
+    out: &mut String,
+    rows: &[crate::core::metrics::LengthQualityRow],
+) -> Result<()> {
+    writeln!(
+        out,
+        "<details><summary>Table</summary><table class=\"table\">"
+    )?;
+    write!(out, "<tr><th>Length Bin</th>")?;
+    for i in 0..crate::core::metrics::LengthQualityRow::COLUMNS {
+        write!(
             out,
-            "<tr><td>{}</td><td>{}</td></tr>",
-            ll.labels[i], ll.bins[i]
+            "<th>Q{}</th>",
+            i * crate::core::metrics::QUAL_BIN_WIDTH
         )?;
     }
+    writeln!(out, "</tr>")?;
+    for r in rows {
+        write!(out, "<tr><td>{}</td>", r.length_bin)?;
+        for c in r.counts {
+            write!(out, "<td>{}</td>", c)?;
+        }
+        writeln!(out, "</tr>")?;
+    }
     writeln!(out, "</table></details>")?;
     Ok(())
 }
@@ -2398,10 +4186,15 @@ fn table_overrep(out: &mut String, rows: &[crate::core::metrics::OverrepRow]) ->
         "<tr><th>Sequence</th><th>Count</th><th>Percentage</th><th>Possible Source</th></tr>"
     )?;
     for r in rows {
+        let count = if r.error > 0 {
+            format!("{} &plusmn; {}", r.count, r.error)
+        } else {
+            r.count.to_string()
+        };
         writeln!(
             out,
             "<tr><td>{}</td><td>{}</td><td>{:.2}</td><td>{}</td></tr>",
-            r.sequence, r.count, r.percent, r.source
+            r.sequence, count, r.percent, r.source
         )?;
     }
     writeln!(out, "</table></details>")?;
@@ -2432,9 +4225,10 @@ fn table_adapter_content(
     Ok(())
 }
 
+// The "Any" row, followed by the --adapter-positions rows when present.
 fn table_adapter_summary(
     out: &mut String,
-    rows: &[crate::core::metrics::AdapterRow],
+    metrics: &crate::core::metrics::FinalMetrics,
 ) -> Result<()> {
     writeln!(
         out,
@@ -2445,13 +4239,20 @@ fn table_adapter_summary(
         write!(out, "<th>{}</th>", name)?;
     }
     writeln!(out, "</tr>")?;
-    if let Some(r) = rows.first() {
+    if let Some(r) = metrics.adapter_content.first() {
         write!(out, "<tr><td>Any</td>")?;
         for v in r.values.iter() {
             write!(out, "<td>{:.1}</td>", v)?;
         }
         writeln!(out, "</tr>")?;
     }
+    for r in &metrics.adapter_positions {
+        write!(out, "<tr><td>{}</td>", r.bin)?;
+        for v in r.values.iter() {
+            write!(out, "<td>{:.1}</td>", v)?;
+        }
+        writeln!(out, "</tr>")?;
+    }
     writeln!(out, "</table></details>")?;
     Ok(())
 }
@@ -2490,11 +4291,15 @@ pub(crate) fn latex_svg_per_base_quality(
     svg_boxplot(
         &mut s,
         &metrics.per_base_qual,
-        w,
-        h,
-        max_q,
-        "Position",
-        "Quality",
+        &QualChart {
+            w,
+            h,
+            max_q,
+            x_label: "Position",
+            y_label: "Quality",
+            pal: &DEFAULT_PALETTE,
+            trend: &metrics.per_base_qual_trend,
+        },
     )?;
     Ok(extract_svg(&s))
 }
@@ -2562,6 +4367,7 @@ pub(crate) fn latex_svg_per_base_content(
         260.0,
         "Position",
         "%",
+        false,
     )?;
     Ok(extract_svg(&s))
 }
@@ -2570,7 +4376,7 @@ pub(crate) fn latex_svg_per_seq_gc(metrics: &crate::core::metrics::FinalMetrics)
     let data = metrics
         .per_seq_gc
         .iter()
-        .map(|r| (r.gc as f64, r.count as f64))
+        .map(|r| (r.gc, r.count as f64))
         .collect::<Vec<_>>();
     let mut s = String::new();
     svg_histogram_compat_bars(
@@ -2587,6 +4393,7 @@ pub(crate) fn latex_svg_per_seq_gc(metrics: &crate::core::metrics::FinalMetrics)
 }
 
 pub(crate) fn latex_svg_per_base_n(metrics: &crate::core::metrics::FinalMetrics) -> Result<String> {
+    let pal = &DEFAULT_PALETTE;
     let data = metrics
         .per_base_n
         .iter()
@@ -2603,9 +4410,9 @@ pub(crate) fn latex_svg_per_base_n(metrics: &crate::core::metrics::FinalMetrics)
         y_max,
         "#555",
         &[
-            (0.0, 5.0, "#cdeccf"),
-            (5.0, 20.0, "#ffe5b4"),
-            (20.0, 100.0, "#f4c7c3"),
+            (0.0, 5.0, pal.band_good),
+            (5.0, 20.0, pal.band_warn),
+            (20.0, 100.0, pal.band_bad),
         ],
         "Position",
         "% N",
@@ -2613,6 +4420,70 @@ pub(crate) fn latex_svg_per_base_n(metrics: &crate::core::metrics::FinalMetrics)
     Ok(extract_svg(&s))
 }
 
+pub(crate) fn latex_svg_error_rate(metrics: &crate::core::metrics::FinalMetrics) -> Result<String> {
+    let (data, min_y) = error_rate_data(metrics);
+    let mut s = String::new();
+    svg_single_line(
+        &mut s,
+        data.as_slice(),
+        800.0,
+        260.0,
+        min_y,
+        0.0,
+        "#c00000",
+        "Position",
+        "log10 error probability",
+    )?;
+    Ok(extract_svg(&s))
+}
+
+// Points as (position, log10 error rate) plus a y-axis floor rounded down
+// to a whole decade (at least -1).
+fn error_rate_data(metrics: &crate::core::metrics::FinalMetrics) -> (Vec<(f64, f64)>, f64) {
+    let data: Vec<(f64, f64)> = metrics
+        .error_rate
+        .iter()
+        .map(|r| (r.base as f64, r.error_rate.log10()))
+        .collect();
+    let min_y = data.iter().map(|d| d.1).fold(-1.0f64, f64::min).floor();
+    (data, min_y)
+}
+
+pub(crate) fn latex_svg_gc_skew(metrics: &crate::core::metrics::FinalMetrics) -> Result<String> {
+    let pal = &DEFAULT_PALETTE;
+    let data = gc_skew_data(metrics);
+    let mut s = String::new();
+    svg_single_line_ybands(
+        &mut s,
+        data.as_slice(),
+        800.0,
+        260.0,
+        -1.0,
+        1.0,
+        "#555",
+        &gc_skew_bands(pal),
+        "Position",
+        "GC skew",
+    )?;
+    Ok(extract_svg(&s))
+}
+
+fn gc_skew_data(metrics: &crate::core::metrics::FinalMetrics) -> Vec<(f64, f64)> {
+    metrics
+        .gc_skew
+        .iter()
+        .map(|r| (r.base as f64, r.skew))
+        .collect()
+}
+
+pub(crate) fn latex_svg_length_quality(
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<String> {
+    let mut s = String::new();
+    svg_length_quality_heatmap(&mut s, &metrics.length_quality, 800.0, 260.0)?;
+    Ok(extract_svg(&s))
+}
+
 pub(crate) fn latex_svg_per_seq_n(metrics: &crate::core::metrics::FinalMetrics) -> Result<String> {
     let data = metrics
         .per_seq_n
@@ -2633,6 +4504,28 @@ pub(crate) fn latex_svg_per_seq_n(metrics: &crate::core::metrics::FinalMetrics)
     Ok(extract_svg(&s))
 }
 
+pub(crate) fn latex_svg_sequence_complexity(
+    metrics: &crate::core::metrics::FinalMetrics,
+) -> Result<String> {
+    let data = metrics
+        .sequence_complexity
+        .iter()
+        .map(|r| (r.entropy, r.count as f64))
+        .collect::<Vec<_>>();
+    let mut s = String::new();
+    svg_histogram_compat_bars(
+        &mut s,
+        data.as_slice(),
+        800.0,
+        260.0,
+        0.0,
+        4.0,
+        "Entropy (bits)",
+        "Count",
+    )?;
+    Ok(extract_svg(&s))
+}
+
 pub(crate) fn latex_svg_length_dist(
     metrics: &crate::core::metrics::FinalMetrics,
 ) -> Result<String> {
@@ -2735,6 +4628,14 @@ fn simple_text_svg(title: &str, lines: &[String]) -> String {
     out
 }
 
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
 fn escape_svg(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
@@ -2791,9 +4692,8 @@ fn svg_adapter_lines(
         .unwrap_or(1.0);
     draw_x_axis_ticks(out, left, top, plot_w, plot_h, x_min, x_max, 5)?;
     draw_axis_labels(out, left, top, plot_w, plot_h, x_label, y_label)?;
-    let colors = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd"];
     for i in 0..series.len() {
-        let color = colors[i % colors.len()];
+        let color = ADAPTER_COLORS[i % ADAPTER_COLORS.len()];
         svg_line(
             out, &series[i], left, top, plot_w, plot_h, y_min, y_max, color,
         )?;
@@ -2801,3 +4701,78 @@ fn svg_adapter_lines(
     writeln!(out, "</svg></div>")?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fmt_timestamp_gives_utc_civil_time() {
+        assert_eq!(fmt_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(fmt_timestamp(1_600_000_000), "2020-09-13 12:26:40 UTC");
+        assert_eq!(fmt_timestamp(1_582_934_400), "2020-02-29 00:00:00 UTC");
+        assert_eq!(fmt_timestamp(951_782_400), "2000-02-29 00:00:00 UTC");
+        // 2100 is not a leap year.
+        assert_eq!(fmt_timestamp(4_107_542_400), "2100-03-01 00:00:00 UTC");
+    }
+
+    // Every `name="<number>"` attribute value in `svg`.
+    fn attr_values(svg: &str, name: &str) -> Vec<f64> {
+        let key = format!(" {name}=\"");
+        svg.match_indices(&key)
+            .map(|(i, _)| {
+                let rest = &svg[i + key.len()..];
+                rest[..rest.find('"').unwrap()].parse().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn boxplot_of_a_single_quality_has_no_negative_sizes() {
+        use crate::core::metrics::PerBaseQualRow;
+
+        let mut rows: Vec<PerBaseQualRow> = (1..=150)
+            .map(|base| PerBaseQualRow {
+                base,
+                mean: 40.0,
+                median: 40,
+                lower_quartile: 40,
+                upper_quartile: 40,
+                p10: 40,
+                p90: 40,
+            })
+            .collect();
+        // Crossed quantiles, as a degenerate histogram can produce.
+        rows.push(PerBaseQualRow {
+            base: 151,
+            mean: 37.0,
+            median: 37,
+            lower_quartile: 38,
+            upper_quartile: 36,
+            p10: 39,
+            p90: 35,
+        });
+        let mut svg = String::new();
+        svg_boxplot(
+            &mut svg,
+            &rows,
+            &QualChart {
+                w: 800.0,
+                h: 300.0,
+                max_q: 40.0,
+                x_label: "Position in read (bp)",
+                y_label: "Quality",
+                pal: &DEFAULT_PALETTE,
+                trend: &[],
+            },
+        )
+        .unwrap();
+
+        let heights = attr_values(&svg, "height");
+        let widths = attr_values(&svg, "width");
+        assert!(heights.len() > rows.len());
+        assert!(heights.iter().chain(&widths).all(|&v| v >= 0.0), "{svg}");
+        // Each collapsed box is still drawn as a 1px band.
+        assert!(svg.contains(" height=\"1\" "));
+    }
+}
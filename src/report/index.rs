@@ -0,0 +1,67 @@
+use crate::report::html::escape_html;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// One row of the batch index: either a finished report or a failed input.
+pub struct IndexEntry {
+    pub sample_name: String,
+    pub file_name: String,
+    // Path to the sample's HTML report relative to the index, if one was written.
+    pub report_href: Option<String>,
+    pub reads: u64,
+    pub warn: usize,
+    pub fail: usize,
+    pub error: Option<String>,
+}
+
+pub fn write(path: &Path, entries: &[IndexEntry]) -> Result<()> {
+    let mut w = BufWriter::new(File::create(path).with_context(|| "create index.html failed")?);
+
+    writeln!(w, "<!DOCTYPE html>")?;
+    writeln!(w, "<html><head><meta charset=\"utf-8\">")?;
+    writeln!(w, "<title>kira-qc reports</title>")?;
+    writeln!(
+        w,
+        "<style>body{{font-family:Arial,Helvetica,sans-serif;margin:20px;color:#222;background:#fff;}}\
+table{{border-collapse:collapse;}}th,td{{border:1px solid #ccc;padding:4px 8px;text-align:left;}}\
+th{{background:#f2f2f2;}}td.num{{text-align:right;}}.warn{{color:#c80;}}.fail{{color:#c00;}}</style>"
+    )?;
+    writeln!(w, "</head><body>")?;
+    writeln!(w, "<h1>kira-qc reports</h1>")?;
+    writeln!(
+        w,
+        "<table><tr><th>Sample</th><th>File</th><th>Reads</th><th>WARN</th><th>FAIL</th><th>Report</th></tr>"
+    )?;
+    for entry in entries {
+        let file = escape_html(&entry.file_name);
+        if let Some(error) = &entry.error {
+            writeln!(
+                w,
+                "<tr><td></td><td>{}</td><td colspan=\"4\" class=\"fail\">{}</td></tr>",
+                file,
+                escape_html(error)
+            )?;
+            continue;
+        }
+        let report = match &entry.report_href {
+            Some(href) => format!("<a href=\"{}\">report</a>", escape_html(href)),
+            None => String::new(),
+        };
+        writeln!(
+            w,
+            "<tr><td>{}</td><td>{}</td><td class=\"num\">{}</td><td class=\"num warn\">{}</td><td class=\"num fail\">{}</td><td>{}</td></tr>",
+            escape_html(&entry.sample_name),
+            file,
+            entry.reads,
+            entry.warn,
+            entry.fail,
+            report
+        )?;
+    }
+    writeln!(w, "</table>")?;
+    writeln!(w, "</body></html>")?;
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+// Minimal zoomable line charts for kira-qc reports (--html-interactive).
+// Each .iplot element carries its data as an inline JSON spec:
+// {x, series: [{name, color, values}], y_min, y_max, x_label, y_label}.
+// Drag horizontally to zoom, double-click to reset, hover for values.
+(function () {
+  "use strict";
+  var NS = "http://www.w3.org/2000/svg";
+  var W = 800, H = 260, L = 50, R = 20, T = 12, B = 34;
+  var PW = W - L - R, PH = H - T - B;
+
+  function el(tag, attrs, parent) {
+    var e = document.createElementNS(NS, tag);
+    for (var k in attrs) e.setAttribute(k, attrs[k]);
+    if (parent) parent.appendChild(e);
+    return e;
+  }
+
+  function fmt(v) {
+    return Math.abs(v) >= 100 || v === Math.round(v) ? String(Math.round(v)) : v.toFixed(1);
+  }
+
+  function render(root, spec) {
+    var n = spec.x.length;
+    if (n === 0) return;
+    var lo = 0, hi = n - 1;
+    var svg = el("svg", { width: W, height: H, viewBox: "0 0 " + W + " " + H });
+    root.appendChild(svg);
+    var tip = document.createElement("div");
+    tip.className = "itip";
+    root.appendChild(tip);
+    var dragFrom = null;
+
+    function sx(i) {
+      return hi === lo ? L + PW / 2 : L + ((i - lo) / (hi - lo)) * PW;
+    }
+    function sy(v) {
+      var span = spec.y_max - spec.y_min || 1;
+      return T + PH - ((v - spec.y_min) / span) * PH;
+    }
+    function indexAt(px) {
+      var f = (px - L) / PW;
+      return Math.max(lo, Math.min(hi, Math.round(lo + f * (hi - lo))));
+    }
+    function mouseX(ev) {
+      var r = svg.getBoundingClientRect();
+      return ((ev.clientX - r.left) / r.width) * W;
+    }
+
+    function draw() {
+      while (svg.firstChild) svg.removeChild(svg.firstChild);
+      el("rect", { x: L, y: T, width: PW, height: PH, fill: "#fff", stroke: "#ddd" }, svg);
+      for (var t = 0; t <= 4; t++) {
+        var v = spec.y_min + ((spec.y_max - spec.y_min) * t) / 4;
+        var y = sy(v);
+        el("line", { x1: L, y1: y, x2: L + PW, y2: y, stroke: "#eee" }, svg);
+        el("text", { x: L - 6, y: y + 4, "text-anchor": "end", "font-size": 11 }, svg).textContent = fmt(v);
+      }
+      for (var k = 0; k <= 5; k++) {
+        var i = Math.round(lo + ((hi - lo) * k) / 5);
+        el("text", { x: sx(i), y: T + PH + 14, "text-anchor": "middle", "font-size": 11 }, svg).textContent = fmt(spec.x[i]);
+      }
+      el("text", { x: L + PW / 2, y: H - 4, "text-anchor": "middle", "font-size": 12 }, svg).textContent = spec.x_label;
+      el("text", { x: 12, y: T + PH / 2, "text-anchor": "middle", "font-size": 12, transform: "rotate(-90 12 " + (T + PH / 2) + ")" }, svg).textContent = spec.y_label;
+      spec.series.forEach(function (s) {
+        var pts = [];
+        for (var i = lo; i <= hi; i++) pts.push(sx(i).toFixed(1) + "," + sy(s.values[i]).toFixed(1));
+        el("polyline", { points: pts.join(" "), fill: "none", stroke: s.color, "stroke-width": 1.5 }, svg);
+      });
+      draw.guide = el("line", { x1: 0, y1: T, x2: 0, y2: T + PH, stroke: "#999", visibility: "hidden" }, svg);
+      draw.band = el("rect", { x: 0, y: T, width: 0, height: PH, fill: "#99c", opacity: 0.2, visibility: "hidden" }, svg);
+    }
+
+    svg.addEventListener("mousemove", function (ev) {
+      var px = mouseX(ev);
+      var i = indexAt(px);
+      draw.guide.setAttribute("x1", sx(i));
+      draw.guide.setAttribute("x2", sx(i));
+      draw.guide.setAttribute("visibility", "visible");
+      var lines = [spec.x_label + " " + fmt(spec.x[i])];
+      spec.series.forEach(function (s) {
+        lines.push(s.name + ": " + fmt(s.values[i]));
+      });
+      tip.textContent = lines.join("\n");
+      tip.style.left = ((sx(i) / W) * svg.getBoundingClientRect().width + 12) + "px";
+      tip.style.display = "block";
+      if (dragFrom !== null) {
+        var a = Math.min(dragFrom, px), b = Math.max(dragFrom, px);
+        draw.band.setAttribute("x", a);
+        draw.band.setAttribute("width", b - a);
+        draw.band.setAttribute("visibility", "visible");
+      }
+    });
+    svg.addEventListener("mouseleave", function () {
+      tip.style.display = "none";
+      draw.guide.setAttribute("visibility", "hidden");
+      dragFrom = null;
+      draw.band.setAttribute("visibility", "hidden");
+    });
+    svg.addEventListener("mousedown", function (ev) {
+      dragFrom = mouseX(ev);
+      ev.preventDefault();
+    });
+    svg.addEventListener("mouseup", function (ev) {
+      if (dragFrom === null) return;
+      var a = indexAt(Math.min(dragFrom, mouseX(ev)));
+      var b = indexAt(Math.max(dragFrom, mouseX(ev)));
+      dragFrom = null;
+      if (b - a >= 2) {
+        lo = a;
+        hi = b;
+      }
+      draw();
+    });
+    svg.addEventListener("dblclick", function () {
+      lo = 0;
+      hi = n - 1;
+      draw();
+    });
+    draw();
+  }
+
+  document.querySelectorAll(".iplot").forEach(function (root) {
+    var data = root.querySelector("script[type='application/json']");
+    if (data) render(root, JSON.parse(data.textContent));
+  });
+})();
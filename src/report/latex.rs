@@ -1,7 +1,9 @@
 use crate::core::engine::RunOutput;
+use crate::core::metrics::FinalMetrics;
 use crate::core::model::Mode;
 use crate::report::html;
 use anyhow::{Context, Result};
+use log::warn;
 use std::fs;
 use std::io::Write;
 use std::path::Path;
@@ -72,128 +74,217 @@ pub fn write(out_dir: &Path, output: &RunOutput, mode: LatexMode) -> Result<()>
                 }
             }
         }
-        LatexMode::Supplement => {
-            if output.ctx.mode == Mode::Short {
-                figures.extend([
-                    fig(
-                        "per_base_quality",
-                        "Per base sequence quality",
-                        html::latex_svg_per_base_quality(&metrics)?,
-                    ),
-                    fig(
-                        "per_sequence_quality",
-                        "Per sequence quality scores",
-                        html::latex_svg_per_seq_quality(&metrics)?,
-                    ),
-                    fig(
-                        "per_base_content",
-                        "Per base sequence content",
-                        html::latex_svg_per_base_content(&metrics)?,
-                    ),
-                    fig(
-                        "per_sequence_gc",
-                        "Per sequence GC content",
-                        html::latex_svg_per_seq_gc(&metrics)?,
-                    ),
-                    fig(
-                        "per_base_n",
-                        "Per base N content",
-                        html::latex_svg_per_base_n(&metrics)?,
-                    ),
-                    fig(
-                        "sequence_length_distribution",
-                        "Sequence length distribution",
-                        html::latex_svg_length_dist(&metrics)?,
-                    ),
-                    fig(
-                        "duplication_levels",
-                        "Sequence duplication levels",
-                        html::latex_svg_duplication(&metrics)?,
-                    ),
-                    fig(
-                        "overrepresented_sequences",
-                        "Overrepresented sequences",
-                        html::latex_svg_overrep(&metrics)?,
-                    ),
-                    fig(
-                        "adapter_content",
-                        "Adapter content",
-                        html::latex_svg_adapter_content(&metrics)?,
-                    ),
-                ]);
-                #[cfg(not(feature = "no-kmer"))]
-                figures.push(fig(
-                    "kmer_content",
-                    "Kmer content",
-                    html::latex_svg_kmer_content(&metrics)?,
-                ));
-            } else {
-                figures.extend([
-                    fig(
-                        "sequence_length_distribution",
-                        "Sequence length distribution",
-                        html::latex_svg_length_dist(&metrics)?,
-                    ),
-                    fig(
-                        "per_sequence_quality",
-                        "Per sequence quality scores",
-                        html::latex_svg_per_seq_quality(&metrics)?,
-                    ),
-                    fig(
-                        "per_sequence_gc",
-                        "Per sequence GC content",
-                        html::latex_svg_per_seq_gc(&metrics)?,
-                    ),
-                    fig(
-                        "per_sequence_n",
-                        "Per sequence N content",
-                        html::latex_svg_per_seq_n(&metrics)?,
-                    ),
-                    fig(
-                        "adapter_content",
-                        "Adapter content",
-                        html::latex_svg_adapter_content(&metrics)?,
-                    ),
-                ]);
-            }
-        }
+        LatexMode::Supplement => figures.extend(module_figures(&metrics, output.ctx.mode)?),
     }
+    figures.retain(|f| !skipped_figure(&metrics, f.name));
 
-    write_figures(&figures_dir, &figures)?;
-    write_readme(&latex_dir)?;
-    write_tex(
+    write_document(
         &latex_dir,
         &output.ctx.file_name,
         output.ctx.mode,
         mode,
         &figures,
-    )?;
-    write_latex_zip(&latex_dir)?;
-    Ok(())
+    )
 }
 
-struct Figure {
-    name: &'static str,
-    caption: &'static str,
-    svg: String,
+// Figures, README, .tex and ZIP, once the tables are in place.
+fn write_document(
+    latex_dir: &Path,
+    file_name: &str,
+    mode: Mode,
+    export_mode: LatexMode,
+    figures: &[Figure],
+) -> Result<()> {
+    let has_pdf = write_figures(&latex_dir.join("figures"), figures)?;
+    write_readme(latex_dir)?;
+    write_tex(latex_dir, file_name, mode, export_mode, figures, &has_pdf)?;
+    write_latex_zip(latex_dir)
+}
+
+// Figures of modules turned off with --skip.
+fn skipped_figure(metrics: &FinalMetrics, name: &str) -> bool {
+    let skipped = metrics.skipped;
+    match name {
+        "duplication_levels" => skipped.duplication,
+        "overrepresented_sequences" => skipped.overrepresented,
+        "adapter_content" => skipped.adapter_content,
+        "kmer_content" => skipped.kmer_content,
+        _ => false,
+    }
+}
+
+pub(crate) struct Figure {
+    pub(crate) name: &'static str,
+    pub(crate) caption: &'static str,
+    pub(crate) svg: String,
+}
+
+// Plots for every module of the given mode, in report order.
+pub(crate) fn module_figures(metrics: &FinalMetrics, mode: Mode) -> Result<Vec<Figure>> {
+    let mut figures = Vec::new();
+    if mode == Mode::Short {
+        figures.extend([
+            fig(
+                "per_base_quality",
+                "Per base sequence quality",
+                html::latex_svg_per_base_quality(metrics)?,
+            ),
+            fig(
+                "per_base_error_rate",
+                "Per base error rate",
+                html::latex_svg_error_rate(metrics)?,
+            ),
+            fig(
+                "per_sequence_quality",
+                "Per sequence quality scores",
+                html::latex_svg_per_seq_quality(metrics)?,
+            ),
+            fig(
+                "per_base_content",
+                "Per base sequence content",
+                html::latex_svg_per_base_content(metrics)?,
+            ),
+            fig(
+                "per_sequence_gc",
+                "Per sequence GC content",
+                html::latex_svg_per_seq_gc(metrics)?,
+            ),
+            fig(
+                "per_base_n",
+                "Per base N content",
+                html::latex_svg_per_base_n(metrics)?,
+            ),
+            fig(
+                "per_base_gc_skew",
+                "Per base GC skew",
+                html::latex_svg_gc_skew(metrics)?,
+            ),
+            fig(
+                "sequence_length_distribution",
+                "Sequence length distribution",
+                html::latex_svg_length_dist(metrics)?,
+            ),
+            fig(
+                "duplication_levels",
+                "Sequence duplication levels",
+                html::latex_svg_duplication(metrics)?,
+            ),
+            fig(
+                "overrepresented_sequences",
+                "Overrepresented sequences",
+                html::latex_svg_overrep(metrics)?,
+            ),
+            fig(
+                "adapter_content",
+                "Adapter content",
+                html::latex_svg_adapter_content(metrics)?,
+            ),
+            fig(
+                "sequence_complexity",
+                "Sequence complexity",
+                html::latex_svg_sequence_complexity(metrics)?,
+            ),
+        ]);
+        #[cfg(not(feature = "no-kmer"))]
+        figures.push(fig(
+            "kmer_content",
+            "Kmer content",
+            html::latex_svg_kmer_content(metrics)?,
+        ));
+    } else {
+        figures.extend([
+            fig(
+                "sequence_length_distribution",
+                "Sequence length distribution",
+                html::latex_svg_length_dist(metrics)?,
+            ),
+            fig(
+                "per_sequence_quality",
+                "Per sequence quality scores",
+                html::latex_svg_per_seq_quality(metrics)?,
+            ),
+            fig(
+                "per_sequence_gc",
+                "Per sequence GC content",
+                html::latex_svg_per_seq_gc(metrics)?,
+            ),
+            fig(
+                "per_sequence_n",
+                "Per sequence N content",
+                html::latex_svg_per_seq_n(metrics)?,
+            ),
+            fig(
+                "adapter_content",
+                "Adapter content",
+                html::latex_svg_adapter_content(metrics)?,
+            ),
+            fig(
+                "sequence_complexity",
+                "Sequence complexity",
+                html::latex_svg_sequence_complexity(metrics)?,
+            ),
+        ]);
+        if !metrics.length_quality.is_empty() {
+            figures.push(fig(
+                "length_vs_quality",
+                "Read length vs mean quality",
+                html::latex_svg_length_quality(metrics)?,
+            ));
+        }
+    }
+    figures.retain(|f| !skipped_figure(metrics, f.name));
+    Ok(figures)
 }
 
 fn fig(name: &'static str, caption: &'static str, svg: String) -> Figure {
     Figure { name, caption, svg }
 }
 
-fn write_figures(dir: &Path, figures: &[Figure]) -> Result<()> {
+// Writes each figure as SVG and PDF. A figure whose PDF conversion fails
+// keeps only its SVG; the returned flags say which figures have a PDF.
+fn write_figures(dir: &Path, figures: &[Figure]) -> Result<Vec<bool>> {
+    let mut has_pdf = Vec::with_capacity(figures.len());
     for f in figures {
         let svg_path = dir.join(format!("{}.svg", f.name));
         fs::write(&svg_path, &f.svg)
             .with_context(|| format!("failed to write {}", svg_path.display()))?;
-        let pdf =
-            svg_to_pdf(&f.svg).with_context(|| format!("failed to convert {} to PDF", f.name))?;
+        let pdf = match svg_to_pdf(&f.svg) {
+            Ok(pdf) => pdf,
+            Err(e) => {
+                warn!(
+                    "LaTeX export: could not convert {} to PDF ({:#}); keeping {}",
+                    f.name,
+                    e,
+                    svg_path.display()
+                );
+                // Don't ship a stale PDF from an earlier export.
+                let _ = fs::remove_file(dir.join(format!("{}.pdf", f.name)));
+                has_pdf.push(false);
+                continue;
+            }
+        };
         let pdf_path = dir.join(format!("{}.pdf", f.name));
         fs::write(&pdf_path, pdf)
             .with_context(|| format!("failed to write {}", pdf_path.display()))?;
+        has_pdf.push(true);
+    }
+    Ok(has_pdf)
+}
+
+// Figure environment body: the PDF, or a framed note pointing at the SVG
+// when no PDF could be made.
+fn figure_graphic(f: &Figure, has_pdf: bool) -> String {
+    if has_pdf {
+        format!(
+            "\\includegraphics[width=\\linewidth]{{figures/{}.pdf}}\n",
+            f.name
+        )
+    } else {
+        format!(
+            "\\fbox{{\\parbox{{0.9\\linewidth}}{{\\centering Figure not available as PDF; see \\texttt{{{}}}.}}}}\n",
+            escape_tex(&format!("figures/{}.svg", f.name))
+        )
     }
-    Ok(())
 }
 
 fn write_basic_stats_table(
@@ -207,6 +298,9 @@ fn write_basic_stats_table(
     out.push_str("Measure & Value \\\\\n");
     out.push_str("\\midrule\n");
     out.push_str(&format!("Filename & {} \\\\\n", escape_tex(file)));
+    if let Some(hash) = &metrics.basic.file_hash {
+        out.push_str(&format!("File hash & {} \\\\\n", escape_tex(hash)));
+    }
     out.push_str(&format!(
         "File type & {} \\\\\n",
         escape_tex(&metrics.basic.file_type)
@@ -235,6 +329,51 @@ fn write_basic_stats_table(
         ));
     }
     out.push_str(&format!("%GC & {} \\\\\n", metrics.basic.gc_percent));
+    out.push_str(&format!(
+        "\\%Ambiguous & {:.2} \\\\\n",
+        metrics.basic.ambiguous_percent
+    ));
+    if let Some(pct) = metrics.basic.softmasked_percent {
+        out.push_str(&format!("\\%Soft-masked & {:.2} \\\\\n", pct));
+    }
+    if let Some(coverage) = metrics.basic.estimated_coverage {
+        out.push_str(&format!(
+            "Estimated coverage & {:.1}$\\times$ \\\\\n",
+            coverage
+        ));
+    }
+    if let Some(record) = metrics.basic.first_invalid_qual_record {
+        out.push_str(&format!(
+            "Invalid quality bytes & {} (first in record {}) \\\\\n",
+            fmt_int(metrics.basic.invalid_qual_bytes),
+            fmt_int(record)
+        ));
+    }
+    if metrics.basic.malformed_records > 0 {
+        out.push_str(&format!(
+            "Malformed records skipped & {} \\\\\n",
+            fmt_int(metrics.basic.malformed_records)
+        ));
+    }
+    if metrics.basic.ws_trimmed_records > 0 {
+        out.push_str(&format!(
+            "Records with trailing whitespace trimmed & {} \\\\\n",
+            fmt_int(metrics.basic.ws_trimmed_records)
+        ));
+    }
+    for (label, values) in [
+        ("Instruments", &metrics.basic.instruments),
+        ("Flowcells", &metrics.basic.flowcells),
+        ("Lanes", &metrics.basic.lanes),
+    ] {
+        if !values.is_empty() {
+            out.push_str(&format!(
+                "{} & {} \\\\\n",
+                label,
+                escape_tex(&values.join(", "))
+            ));
+        }
+    }
     out.push_str("\\bottomrule\n");
     out.push_str("\\end{tabular}\n");
     let path = tables_dir.join("basic_statistics.tex");
@@ -248,6 +387,7 @@ fn write_tex(
     mode: Mode,
     export_mode: LatexMode,
     figures: &[Figure],
+    has_pdf: &[bool],
 ) -> Result<()> {
     let mut out = String::new();
     out.push_str("\\documentclass{article}\n");
@@ -281,26 +421,20 @@ fn write_tex(
     match export_mode {
         LatexMode::Summary => {
             out.push_str("\\section*{Quality metrics}\n");
-            for f in figures {
+            for (f, &pdf) in figures.iter().zip(has_pdf) {
                 out.push_str("\\begin{figure}[H]\n");
                 out.push_str("\\centering\n");
-                out.push_str(&format!(
-                    "\\includegraphics[width=\\linewidth]{{figures/{}.pdf}}\n",
-                    f.name
-                ));
+                out.push_str(&figure_graphic(f, pdf));
                 out.push_str(&format!("\\caption{{{}}}\n", f.caption));
                 out.push_str("\\end{figure}\n");
             }
         }
         LatexMode::Supplement => {
-            for f in figures {
+            for (f, &pdf) in figures.iter().zip(has_pdf) {
                 out.push_str(&format!("\\section*{{{}}}\n", f.caption));
                 out.push_str("\\begin{figure}[H]\n");
                 out.push_str("\\centering\n");
-                out.push_str(&format!(
-                    "\\includegraphics[width=\\linewidth]{{figures/{}.pdf}}\n",
-                    f.name
-                ));
+                out.push_str(&figure_graphic(f, pdf));
                 out.push_str(&format!("\\caption{{{}}}\n", f.caption));
                 out.push_str("\\end{figure}\n");
             }
@@ -364,7 +498,7 @@ fn escape_tex(s: &str) -> String {
         .replace('^', "\\textasciicircum{}")
 }
 
-fn svg_to_pdf(svg: &str) -> Result<Vec<u8>> {
+pub(crate) fn svg_to_pdf(svg: &str) -> Result<Vec<u8>> {
     let mut opt = usvg::Options::default();
     opt.fontdb_mut().load_system_fonts();
     let tree =
@@ -431,3 +565,66 @@ fn add_file_to_zip(
     zip.write_all(&data)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    struct TempDir(std::path::PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn figure_that_fails_to_convert_keeps_its_svg_and_the_export_completes() {
+        let dir =
+            TempDir(std::env::temp_dir().join(format!("kira-qc-latex-{}", std::process::id())));
+        let latex_dir = dir.0.join("latex");
+        fs::create_dir_all(latex_dir.join("figures")).unwrap();
+        fs::create_dir_all(latex_dir.join("tables")).unwrap();
+        fs::write(latex_dir.join("tables/basic_statistics.tex"), "").unwrap();
+
+        let figures = [
+            fig(
+                "good",
+                "Good",
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\"><rect width=\"5\" height=\"5\"/></svg>"
+                    .to_string(),
+            ),
+            fig("broken", "Broken", "<svg><rect width=\"5\"".to_string()),
+        ];
+        write_document(
+            &latex_dir,
+            "in.fastq",
+            Mode::Short,
+            LatexMode::Summary,
+            &figures,
+        )
+        .unwrap();
+
+        let figures_dir = latex_dir.join("figures");
+        assert!(figures_dir.join("good.pdf").exists());
+        assert!(figures_dir.join("broken.svg").exists());
+        assert!(!figures_dir.join("broken.pdf").exists());
+        let tex = fs::read_to_string(latex_dir.join("kira_qc.tex")).unwrap();
+        assert!(tex.contains("\\includegraphics[width=\\linewidth]{figures/good.pdf}"));
+        assert!(tex.contains("see \\texttt{figures/broken.svg}"));
+
+        let zip = fs::File::open(latex_dir.join("kira_qc_latex.zip")).unwrap();
+        let mut zip = zip::ZipArchive::new(zip).unwrap();
+        let names: Vec<&str> = zip.file_names().collect();
+        assert!(names.contains(&"latex/figures/broken.svg"));
+        assert!(names.contains(&"latex/figures/good.pdf"));
+        assert!(!names.contains(&"latex/figures/broken.pdf"));
+        let mut tex_in_zip = String::new();
+        zip.by_name("latex/kira_qc.tex")
+            .unwrap()
+            .read_to_string(&mut tex_in_zip)
+            .unwrap();
+        assert_eq!(tex_in_zip, tex);
+    }
+}
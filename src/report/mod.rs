@@ -1,5 +1,9 @@
 pub mod fastqc_txt;
+pub mod figures;
+pub mod gzip;
 pub mod html;
+pub mod index;
 pub mod latex;
+pub mod overrep_csv;
 pub mod summary_txt;
 pub mod zip;
@@ -0,0 +1,39 @@
+use crate::core::engine::RunOutput;
+use crate::core::metrics::classify_source;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Dumps every tracked overrepresented-sequence candidate, not just the ones
+/// past the report threshold. `error` is the SpaceSaving overestimate bound:
+/// the true count lies in `[count - error, count]`.
+pub fn write(path: &Path, output: &RunOutput) -> Result<()> {
+    let mut w = BufWriter::new(File::create(path).with_context(|| "create overrep dump failed")?);
+    let total_reads = output.agg.total_reads;
+    let mut entries: Vec<_> = output.agg.overrep_space.entries().iter().collect();
+    entries.sort_by(|a, b| b.count.cmp(&a.count).then(a.key.cmp(&b.key)));
+
+    writeln!(w, "sequence,count,error,percent,source")?;
+    for e in entries {
+        if e.count == 0 {
+            continue;
+        }
+        let pct = if total_reads == 0 {
+            0.0
+        } else {
+            e.count as f64 * 100.0 / total_reads as f64
+        };
+        writeln!(
+            w,
+            "{},{},{},{:.6},{}",
+            e.display_seq(),
+            e.count,
+            e.error,
+            pct,
+            classify_source(&e.seq)
+        )?;
+    }
+    w.flush()?;
+    Ok(())
+}
@@ -5,16 +5,17 @@ use std::path::Path;
 use zip::write::SimpleFileOptions;
 use zip::{CompressionMethod, ZipWriter};
 
-pub fn write_zip(out_dir: &Path, sample_name: &str) -> Result<()> {
-    let root = format!("{}_fastqc", sample_name);
-    let zip_name = format!("{}_fastqc.zip", sample_name);
+// Writes `<root>.zip` into `out_dir`, with the named report files from
+// `report_dir` under a `<root>/` entry.
+pub fn write_zip(out_dir: &Path, report_dir: &Path, root: &str, files: &[&str]) -> Result<()> {
+    let zip_name = format!("{}.zip", root);
     let zip_path = out_dir.join(&zip_name);
     let tmp_path = out_dir.join(format!("{}.tmp", zip_name));
 
     let file = File::create(&tmp_path)
         .with_context(|| format!("failed to create {}", tmp_path.display()))?;
     let mut zip = ZipWriter::new(file);
-    let result = write_zip_entries(&mut zip, out_dir, &root);
+    let result = write_zip_entries(&mut zip, report_dir, root, files);
 
     match result.and_then(|_| zip.finish().with_context(|| "failed to finalize zip")) {
         Ok(_) => {
@@ -29,7 +30,12 @@ pub fn write_zip(out_dir: &Path, sample_name: &str) -> Result<()> {
     }
 }
 
-fn write_zip_entries(zip: &mut ZipWriter<File>, out_dir: &Path, root: &str) -> Result<()> {
+fn write_zip_entries(
+    zip: &mut ZipWriter<File>,
+    report_dir: &Path,
+    root: &str,
+    files: &[&str],
+) -> Result<()> {
     let options = SimpleFileOptions::default()
         .compression_method(CompressionMethod::Deflated)
         .last_modified_time(zip::DateTime::from_date_and_time(1980, 1, 1, 0, 0, 0).unwrap());
@@ -37,10 +43,8 @@ fn write_zip_entries(zip: &mut ZipWriter<File>, out_dir: &Path, root: &str) -> R
     zip.add_directory(format!("{}/", root), options)
         .with_context(|| "failed to add directory entry to zip")?;
 
-    let files = ["fastqc_data.txt", "summary.txt", "fastqc_report.html"];
-
-    for name in files {
-        let src_path = out_dir.join(root).join(name);
+    for &name in files {
+        let src_path = report_dir.join(name);
         let zip_path = format!("{}/{}", root, name);
         add_file(zip, &src_path, &zip_path, options)
             .with_context(|| format!("failed to add {} to zip", name))?;
@@ -238,3 +238,78 @@ pub unsafe fn acgt_2bit_block_16_neon(input_ptr: *const u8) -> (u16, u32) {
     }
     (mask, packed)
 }
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+pub unsafe fn find_n_runs_neon(seq: &[u8], on_run: &mut dyn FnMut(usize, usize)) {
+    use super::scalar::n_run_step;
+
+    let len = seq.len();
+    let mut i = 0usize;
+    let mut open: Option<usize> = None;
+
+    let upper_mask = vdupq_n_u8(0xDF);
+    let vn = vdupq_n_u8(b'N');
+
+    while i + 16 <= len {
+        let ptr = seq.as_ptr().add(i);
+        let eq = vceqq_u8(vandq_u8(vld1q_u8(ptr), upper_mask), vn);
+        if vmaxvq_u8(eq) == 0 {
+            if let Some(start) = open.take() {
+                on_run(start, i - start);
+            }
+        } else if vminvq_u8(eq) == 0xFF {
+            if open.is_none() {
+                open = Some(i);
+            }
+        } else {
+            for lane in 0..16 {
+                n_run_step(&mut open, i + lane, (seq[i + lane] & 0xDF) == b'N', on_run);
+            }
+        }
+        i += 16;
+    }
+
+    for (j, &b) in seq[i..].iter().enumerate() {
+        n_run_step(&mut open, i + j, (b & 0xDF) == b'N', on_run);
+    }
+    if let Some(start) = open {
+        on_run(start, len - start);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+pub unsafe fn reverse_complement_neon(seq: &[u8], out: &mut Vec<u8>) {
+    use super::scalar::{COMPLEMENT_PAIRS, complement_base};
+
+    let len = seq.len();
+    out.clear();
+    out.resize(len, 0);
+    let mut i = 0usize;
+
+    let lower_a = vdupq_n_u8(b'a');
+    let letters = vdupq_n_u8(26);
+    let case_bit = vdupq_n_u8(0x20);
+    let pairs = COMPLEMENT_PAIRS.map(|(from, to)| (vdupq_n_u8(from), vdupq_n_u8(to)));
+
+    // Output block i..i+16 is the complement of input block len-i-16..len-i,
+    // reversed.
+    while i + 16 <= len {
+        let raw = vld1q_u8(seq.as_ptr().add(len - i - 16));
+        // Uppercase a..z only: clear the case bit where raw - 'a' < 26.
+        let lower = vcltq_u8(vsubq_u8(raw, lower_a), letters);
+        let v = vbicq_u8(raw, vandq_u8(lower, case_bit));
+        let mut comp = v;
+        for &(from, to) in &pairs {
+            comp = vbslq_u8(vceqq_u8(v, from), to, comp);
+        }
+        let rev = vrev64q_u8(comp);
+        vst1q_u8(out.as_mut_ptr().add(i), vextq_u8(rev, rev, 8));
+        i += 16;
+    }
+
+    for (o, &b) in out[i..].iter_mut().zip(seq[..len - i].iter().rev()) {
+        *o = complement_base(b);
+    }
+}
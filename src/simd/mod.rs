@@ -148,3 +148,98 @@ pub fn acgt_2bit_block_16(input_ptr: *const u8) -> (u16, u32) {
         return scalar::acgt_2bit_block_16_scalar(input_ptr);
     }
 }
+
+// Calls `on_run(start, len)` for every maximal run of N/n bases, in order.
+pub fn find_n_runs(seq: &[u8], on_run: &mut dyn FnMut(usize, usize)) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if avx2_available() {
+            // SAFETY: guarded by runtime AVX2 CPU feature detection.
+            unsafe {
+                return x86_avx2::find_n_runs_avx2(seq, on_run);
+            }
+        }
+        scalar::find_n_runs(seq, on_run)
+    }
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        aarch64_neon::find_n_runs_neon(seq, on_run)
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        scalar::find_n_runs(seq, on_run)
+    }
+}
+
+// Writes the reverse complement of `seq` to `out` (replacing its contents).
+// Letters are uppercased and IUPAC ambiguity codes complemented; other bytes
+// are kept as they are.
+pub fn reverse_complement(seq: &[u8], out: &mut Vec<u8>) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if avx2_available() {
+            // SAFETY: guarded by runtime AVX2 CPU feature detection.
+            unsafe {
+                return x86_avx2::reverse_complement_avx2(seq, out);
+            }
+        }
+        scalar::reverse_complement(seq, out)
+    }
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        aarch64_neon::reverse_complement_neon(seq, out)
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        scalar::reverse_complement(seq, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(find: fn(&[u8], &mut dyn FnMut(usize, usize)), seq: &[u8]) -> Vec<(usize, usize)> {
+        let mut out = Vec::new();
+        find(seq, &mut |start, len| out.push((start, len)));
+        out
+    }
+
+    #[test]
+    fn find_n_runs_matches_scalar_across_blocks() {
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        // Lengths around one, two and three 32-byte blocks (16 for NEON).
+        for len in 0..=100 {
+            let all_n = vec![b'N'; len];
+            assert_eq!(runs(find_n_runs, &all_n), runs(scalar::find_n_runs, &all_n));
+            for density in [2u64, 8, 64] {
+                let seq: Vec<u8> = (0..len)
+                    .map(|_| match next() % density {
+                        0 => b'N',
+                        1 => b'n',
+                        _ => b"ACGT"[(next() % 4) as usize],
+                    })
+                    .collect();
+                assert_eq!(
+                    runs(find_n_runs, &seq),
+                    runs(scalar::find_n_runs, &seq),
+                    "{}",
+                    String::from_utf8_lossy(&seq)
+                );
+            }
+        }
+        // Runs that end, start or span exactly at block boundaries.
+        for (start, end) in [(0, 32), (31, 33), (32, 64), (16, 80), (63, 64), (30, 100)] {
+            let mut seq = vec![b'A'; 100];
+            seq[start..end].fill(b'N');
+            assert_eq!(runs(find_n_runs, &seq), vec![(start, end - start)]);
+            assert_eq!(runs(scalar::find_n_runs, &seq), vec![(start, end - start)]);
+        }
+    }
+}
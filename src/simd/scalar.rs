@@ -131,3 +131,64 @@ pub fn acgt_2bit_block_16_scalar(input_ptr: *const u8) -> (u16, u32) {
     }
     (mask, packed)
 }
+
+pub fn find_n_runs(seq: &[u8], on_run: &mut dyn FnMut(usize, usize)) {
+    let mut open = None;
+    for (i, &b) in seq.iter().enumerate() {
+        n_run_step(&mut open, i, (b & 0xDF) == b'N', on_run);
+    }
+    if let Some(start) = open {
+        on_run(start, seq.len() - start);
+    }
+}
+
+// Advances the run state by one position; shared with the SIMD versions for
+// blocks that mix N and non-N bases and for the tail.
+#[inline]
+pub fn n_run_step(
+    open: &mut Option<usize>,
+    pos: usize,
+    is_n: bool,
+    on_run: &mut dyn FnMut(usize, usize),
+) {
+    if is_n {
+        if open.is_none() {
+            *open = Some(pos);
+        }
+    } else if let Some(start) = open.take() {
+        on_run(start, pos - start);
+    }
+}
+
+pub fn reverse_complement(seq: &[u8], out: &mut Vec<u8>) {
+    out.clear();
+    out.extend(seq.iter().rev().map(|&b| complement_base(b)));
+}
+
+// Pairs of IUPAC codes that complement each other; S, W, N and any other
+// byte complement to themselves (letters uppercased).
+pub const COMPLEMENT_PAIRS: [(u8, u8); 12] = [
+    (b'A', b'T'),
+    (b'T', b'A'),
+    (b'C', b'G'),
+    (b'G', b'C'),
+    (b'R', b'Y'),
+    (b'Y', b'R'),
+    (b'K', b'M'),
+    (b'M', b'K'),
+    (b'B', b'V'),
+    (b'V', b'B'),
+    (b'D', b'H'),
+    (b'H', b'D'),
+];
+
+// Complement of one base following COMPLEMENT_PAIRS, with ASCII letters
+// uppercased. Shared with the SIMD versions for the tail.
+#[inline]
+pub fn complement_base(b: u8) -> u8 {
+    let u = b.to_ascii_uppercase();
+    COMPLEMENT_PAIRS
+        .iter()
+        .find(|&&(from, _)| from == u)
+        .map_or(u, |&(_, to)| to)
+}
@@ -220,3 +220,88 @@ pub unsafe fn acgt_2bit_block_16_avx2(input_ptr: *const u8) -> (u16, u32) {
     let mask = _mm_movemask_epi8(valid) as u16;
     (mask, packed)
 }
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+pub unsafe fn find_n_runs_avx2(seq: &[u8], on_run: &mut dyn FnMut(usize, usize)) {
+    use super::scalar::n_run_step;
+
+    let len = seq.len();
+    let mut i = 0usize;
+    let mut open: Option<usize> = None;
+
+    let upper_mask = _mm256_set1_epi8(0xDFu8 as i8);
+    let vn = _mm256_set1_epi8(b'N' as i8);
+
+    while i + 32 <= len {
+        let ptr = unsafe { seq.as_ptr().add(i) as *const __m256i };
+        let v = _mm256_and_si256(unsafe { _mm256_loadu_si256(ptr) }, upper_mask);
+        let mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(v, vn)) as u32;
+        if mask == 0 {
+            if let Some(start) = open.take() {
+                on_run(start, i - start);
+            }
+        } else if mask == u32::MAX {
+            if open.is_none() {
+                open = Some(i);
+            }
+        } else {
+            for lane in 0..32 {
+                n_run_step(&mut open, i + lane, (mask >> lane) & 1 != 0, on_run);
+            }
+        }
+        i += 32;
+    }
+
+    for (j, &b) in seq[i..].iter().enumerate() {
+        n_run_step(&mut open, i + j, (b & 0xDF) == b'N', on_run);
+    }
+    if let Some(start) = open {
+        on_run(start, len - start);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+pub unsafe fn reverse_complement_avx2(seq: &[u8], out: &mut Vec<u8>) {
+    use super::scalar::{COMPLEMENT_PAIRS, complement_base};
+
+    let len = seq.len();
+    out.clear();
+    out.resize(len, 0);
+    let mut i = 0usize;
+
+    let lower_a = _mm256_set1_epi8(b'a' as i8);
+    let last_lower = _mm256_set1_epi8(25);
+    let case_bit = _mm256_set1_epi8(0x20);
+    let pairs = COMPLEMENT_PAIRS
+        .map(|(from, to)| (_mm256_set1_epi8(from as i8), _mm256_set1_epi8(to as i8)));
+    // Reverses the bytes of each 128-bit lane; the lanes are swapped after.
+    let reverse = _mm256_setr_epi8(
+        15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6,
+        5, 4, 3, 2, 1, 0,
+    );
+
+    // Output block i..i+32 is the complement of input block len-i-32..len-i,
+    // reversed.
+    while i + 32 <= len {
+        let ptr = unsafe { seq.as_ptr().add(len - i - 32) as *const __m256i };
+        let raw = unsafe { _mm256_loadu_si256(ptr) };
+        // Uppercase a..z only: clear the case bit where raw - 'a' <= 25.
+        let t = _mm256_sub_epi8(raw, lower_a);
+        let lower = _mm256_cmpeq_epi8(_mm256_min_epu8(t, last_lower), t);
+        let v = _mm256_andnot_si256(_mm256_and_si256(lower, case_bit), raw);
+        let mut comp = v;
+        for &(from, to) in &pairs {
+            comp = _mm256_blendv_epi8(comp, to, _mm256_cmpeq_epi8(v, from));
+        }
+        let rev = _mm256_permute4x64_epi64(_mm256_shuffle_epi8(comp, reverse), 0x4E);
+        let dst = unsafe { out.as_mut_ptr().add(i) as *mut __m256i };
+        unsafe { _mm256_storeu_si256(dst, rev) };
+        i += 32;
+    }
+
+    for (o, &b) in out[i..].iter_mut().zip(seq[..len - i].iter().rev()) {
+        *o = complement_base(b);
+    }
+}